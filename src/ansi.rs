@@ -0,0 +1,131 @@
+use std::env;
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+use std::sync::Mutex;
+
+use crate::TermLike;
+
+/// Width assumed when it isn't given by the `COLUMNS` environment variable
+const DEFAULT_WIDTH: usize = 80;
+
+/// A minimal terminal writing plain ANSI escape sequences to any writer
+///
+/// Unlike the terminals used by [`ProgressDrawTarget::stderr()`], this doesn't query the
+/// terminal for its size or check whether it is one, so it can be used where those aren't
+/// available, e.g. in static binaries on embedded systems. Only moving the cursor and clearing
+/// lines is supported, which is all progress bars need. Combined with disabling the default
+/// `console` feature, this drops the `console` dependency entirely.
+///
+/// ```rust,no_run
+/// # use indicatif::{AnsiTerm, ProgressBar, ProgressDrawTarget};
+/// let target = ProgressDrawTarget::term_like(Box::new(AnsiTerm::stderr()));
+/// let pb = ProgressBar::with_draw_target(100, target);
+/// ```
+///
+/// [`ProgressDrawTarget::stderr()`]: crate::ProgressDrawTarget::stderr
+pub struct AnsiTerm<W: Write> {
+    writer: Mutex<BufWriter<W>>,
+    width: usize,
+}
+
+impl<W: Write> AnsiTerm<W> {
+    /// Creates a terminal writing to `writer`, laying out progress bars for `width` columns
+    pub fn new(writer: W, width: usize) -> AnsiTerm<W> {
+        AnsiTerm {
+            writer: Mutex::new(BufWriter::new(writer)),
+            width,
+        }
+    }
+
+    fn write_fmt(&self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        self.writer.lock().unwrap().write_fmt(args)
+    }
+}
+
+impl AnsiTerm<io::Stderr> {
+    /// Creates a terminal writing to the standard error
+    ///
+    /// The width is taken from the `COLUMNS` environment variable, and defaults to 80 columns.
+    pub fn stderr() -> AnsiTerm<io::Stderr> {
+        let width = env::var("COLUMNS").ok().and_then(|s| s.parse().ok());
+        AnsiTerm::new(io::stderr(), width.unwrap_or(DEFAULT_WIDTH))
+    }
+}
+
+impl<W: Write> fmt::Debug for AnsiTerm<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnsiTerm")
+            .field("width", &self.width)
+            .finish()
+    }
+}
+
+impl<W: Write + Send> TermLike for AnsiTerm<W> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn move_cursor_up(&self, n: usize) -> io::Result<()> {
+        match n {
+            0 => Ok(()),
+            n => self.write_fmt(format_args!("\x1b[{}A", n)),
+        }
+    }
+
+    fn move_cursor_down(&self, n: usize) -> io::Result<()> {
+        match n {
+            0 => Ok(()),
+            n => self.write_fmt(format_args!("\x1b[{}B", n)),
+        }
+    }
+
+    fn move_cursor_right(&self, n: usize) -> io::Result<()> {
+        match n {
+            0 => Ok(()),
+            n => self.write_fmt(format_args!("\x1b[{}C", n)),
+        }
+    }
+
+    fn move_cursor_left(&self, n: usize) -> io::Result<()> {
+        match n {
+            0 => Ok(()),
+            n => self.write_fmt(format_args!("\x1b[{}D", n)),
+        }
+    }
+
+    fn write_line(&self, s: &str) -> io::Result<()> {
+        self.write_fmt(format_args!("{}\n", s))
+    }
+
+    fn write_str(&self, s: &str) -> io::Result<()> {
+        self.writer.lock().unwrap().write_all(s.as_bytes())
+    }
+
+    fn clear_line(&self) -> io::Result<()> {
+        self.write_str("\r\x1b[2K")
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.writer.lock().unwrap().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_sequences() {
+        let term = AnsiTerm::new(Vec::new(), 40);
+        term.write_line("first").unwrap();
+        term.move_cursor_up(1).unwrap();
+        term.move_cursor_down(0).unwrap();
+        term.clear_line().unwrap();
+        term.write_str("second").unwrap();
+        term.flush().unwrap();
+
+        let writer = term.writer.into_inner().unwrap();
+        let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(output, "first\n\x1b[1A\r\x1b[2Ksecond");
+    }
+}
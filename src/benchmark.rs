@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use crate::draw_target::ProgressDrawTarget;
+use crate::progress_bar::ProgressBar;
+use crate::style::ProgressStyle;
+use crate::time::Instant;
+
+/// Measures the overhead of a progress bar
+///
+/// A benchmark advances a progress bar drawing to a [`ProgressDrawTarget::noop_counted()`]
+/// target, measuring the cost of the updates and counting how often the bar would have been
+/// drawn, then measures how long formatting the style takes. Together, these estimate the
+/// overhead of a progress bar with the given style and draw rate in a hot loop.
+///
+/// ```rust
+/// # use indicatif::{Benchmark, ProgressStyle};
+/// let report = Benchmark::new(ProgressStyle::default_bar())
+///     .updates(10_000)
+///     .draw_rate(20)
+///     .run();
+/// println!("{:?} per update", report.per_update());
+/// ```
+#[derive(Clone)]
+pub struct Benchmark {
+    style: ProgressStyle,
+    updates: u64,
+    draw_rate: Option<u64>,
+    width: usize,
+}
+
+/// The results of a [`Benchmark`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BenchmarkReport {
+    /// Number of updates of the progress bar
+    pub updates: u64,
+    /// Number of times the progress bar would have been drawn
+    pub draws: u64,
+    /// Time taken by the updates, without drawing
+    pub update_time: Duration,
+    /// Average time taken to format the style once
+    pub format_time: Duration,
+}
+
+impl Benchmark {
+    /// Creates a benchmark for `style`, with 100 000 updates at the default draw rate
+    pub fn new(style: ProgressStyle) -> Benchmark {
+        Benchmark {
+            style,
+            updates: 100_000,
+            draw_rate: None,
+            width: 80,
+        }
+    }
+
+    /// Sets the number of updates, i.e. calls to [`ProgressBar::inc()`]
+    pub fn updates(mut self, updates: u64) -> Benchmark {
+        self.updates = updates;
+        self
+    }
+
+    /// Sets the draw rate, see [`ProgressBar::set_draw_rate()`]
+    pub fn draw_rate(mut self, draw_rate: u64) -> Benchmark {
+        self.draw_rate = Some(draw_rate);
+        self
+    }
+
+    /// Sets the terminal width the style is formatted for, 80 columns by default
+    pub fn width(mut self, width: usize) -> Benchmark {
+        self.width = width;
+        self
+    }
+
+    /// Runs the benchmark
+    pub fn run(&self) -> BenchmarkReport {
+        let (target, counter) = ProgressDrawTarget::noop_counted();
+        let pb = ProgressBar::with_draw_target(self.updates, target).with_style(self.style.clone());
+        if let Some(rate) = self.draw_rate {
+            pb.set_draw_rate(rate);
+        }
+
+        let start = Instant::now();
+        for _ in 0..self.updates {
+            pb.inc(1);
+        }
+        let update_time = start.elapsed();
+        let draws = counter.count();
+
+        // Formats as often as the bar would have been drawn, bounded to keep the benchmark short
+        let rounds = draws.clamp(1, 1000);
+        let format_time = pb.with_state(|state| {
+            let mut lines = Vec::new();
+            let start = Instant::now();
+            for _ in 0..rounds {
+                lines.clear();
+                state
+                    .style
+                    .format_state(state, &mut lines, self.width, Some(true));
+            }
+            start.elapsed() / rounds as u32
+        });
+
+        BenchmarkReport {
+            updates: self.updates,
+            draws,
+            update_time,
+            format_time,
+        }
+    }
+}
+
+impl BenchmarkReport {
+    /// Returns the estimated overhead per update, including drawing
+    pub fn per_update(&self) -> Duration {
+        match self.updates {
+            0 => Duration::from_secs(0),
+            updates => Duration::from_secs_f64(self.total().as_secs_f64() / updates as f64),
+        }
+    }
+
+    /// Returns the estimated total overhead of the updates, including drawing
+    pub fn total(&self) -> Duration {
+        self.update_time
+            + Duration::from_secs_f64(self.format_time.as_secs_f64() * self.draws as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benchmark_counts_draws() {
+        let report = Benchmark::new(ProgressStyle::default_bar())
+            .updates(1000)
+            .run();
+        assert_eq!(report.updates, 1000);
+        assert!(report.draws >= 1 && report.draws <= 1000);
+        assert!(report.total() >= report.update_time);
+    }
+}
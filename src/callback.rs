@@ -0,0 +1,141 @@
+use std::io;
+use std::sync::Mutex;
+
+use crate::TermLike;
+
+type LinesCallback = Box<dyn Fn(&[String]) + Send + Sync>;
+
+/// Hands the rendered lines of every frame to a callback, see [`ProgressDrawTarget::callback()`]
+///
+/// [`ProgressDrawTarget::callback()`]: crate::ProgressDrawTarget::callback
+pub(crate) struct CallbackTerm {
+    width: usize,
+    callback: LinesCallback,
+    state: Mutex<Frame>,
+}
+
+#[derive(Debug, Default)]
+struct Frame {
+    /// Output of the frame being drawn
+    text: String,
+    /// Whether the previous frame was cleared
+    cleared: bool,
+}
+
+impl CallbackTerm {
+    pub(crate) fn new(width: usize, callback: LinesCallback) -> CallbackTerm {
+        CallbackTerm {
+            width,
+            callback,
+            state: Mutex::new(Frame::default()),
+        }
+    }
+}
+
+impl std::fmt::Debug for CallbackTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackTerm")
+            .field("width", &self.width)
+            .finish()
+    }
+}
+
+impl TermLike for CallbackTerm {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn move_cursor_up(&self, _: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn move_cursor_down(&self, _: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn move_cursor_right(&self, _: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn move_cursor_left(&self, _: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_line(&self, s: &str) -> io::Result<()> {
+        let mut frame = self.state.lock().unwrap();
+        frame.text.push_str(s);
+        frame.text.push('\n');
+        Ok(())
+    }
+
+    fn write_str(&self, s: &str) -> io::Result<()> {
+        self.state.lock().unwrap().text.push_str(s);
+        Ok(())
+    }
+
+    fn clear_line(&self) -> io::Result<()> {
+        self.state.lock().unwrap().cleared = true;
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut frame = self.state.lock().unwrap();
+        if frame.text.is_empty() && !frame.cleared {
+            return Ok(());
+        }
+
+        // The last line is padded to the width of the terminal
+        let lines = frame
+            .text
+            .lines()
+            .map(|line| line.trim_end_matches(&['\r', ' '][..]).to_owned())
+            .collect::<Vec<_>>();
+        frame.text.clear();
+        frame.cleared = false;
+        drop(frame);
+        (self.callback)(&lines);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+    #[test]
+    fn callback_receives_frames() {
+        let frames = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let target = ProgressDrawTarget::callback(40, {
+            let frames = frames.clone();
+            move |lines: &[String]| frames.lock().unwrap().push(lines.to_vec())
+        });
+        let pb = ProgressBar::with_draw_target(10, target)
+            .with_style(ProgressStyle::default_bar().template("{msg}\n{pos}/{len}"));
+        pb.set_message("copying");
+        pb.println("log line");
+        pb.finish_and_clear();
+
+        let frames = frames.lock().unwrap();
+        assert!(frames.contains(&vec!["log line".into(), "copying".into(), "0/10".into()]));
+        assert_eq!(frames.last().unwrap(), &Vec::<String>::new());
+    }
+
+    #[test]
+    fn fallback_width() {
+        let frames = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let target = ProgressDrawTarget::callback(0, {
+            let frames = frames.clone();
+            move |lines: &[String]| frames.lock().unwrap().push(lines.to_vec())
+        })
+        .with_fallback_width(10);
+        let pb = ProgressBar::with_draw_target(10, target).with_style(
+            ProgressStyle::default_bar()
+                .template("{wide_bar}")
+                .progress_chars("#>-"),
+        );
+        pb.finish();
+        assert_eq!(frames.lock().unwrap().last().unwrap(), &["##########"]);
+    }
+}
@@ -0,0 +1,19 @@
+//! The terminal and styling helpers of `console`, or their built-in replacements without the
+//! `console` feature
+
+#[cfg(feature = "console")]
+pub(crate) use console::{colors_enabled, measure_text_width, strip_ansi_codes, AnsiCodeIterator};
+#[cfg(feature = "console")]
+pub use console::{Color, Style, StyledObject, Term};
+
+#[cfg(all(test, not(feature = "console")))]
+pub(crate) use crate::fallback::set_colors_enabled;
+#[cfg(all(test, feature = "console"))]
+pub(crate) use console::set_colors_enabled;
+
+#[cfg(not(feature = "console"))]
+pub(crate) use crate::fallback::{
+    colors_enabled, measure_text_width, strip_ansi_codes, AnsiCodeIterator,
+};
+#[cfg(not(feature = "console"))]
+pub use crate::fallback::{Color, Style, StyledObject, Term};
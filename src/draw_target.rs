@@ -1,18 +1,72 @@
+use std::borrow::Cow;
+use std::fmt::Write;
 use std::io;
-use std::sync::{Arc, RwLock, RwLockWriteGuard};
-use std::time::Instant;
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
+use std::time::Duration;
 
-use console::Term;
+use crate::compat::{self, Term};
 
-use crate::multi::{MultiProgressAlignment, MultiProgressState};
+use crate::callback::CallbackTerm;
+use crate::graphics::{self, BarGraphics};
+use crate::multi::{
+    AfterUnlock, EventQueue, MultiProgressAlignment, MultiProgressState, SkippedDraw,
+};
+use crate::state::ProgressState;
+use crate::ticker::{frame_epoch, next_frame};
+use crate::time::Instant;
 use crate::TermLike;
 
+static DRAW_ENABLED: AtomicBool = AtomicBool::new(true);
+/// Bits of the `f64` the refresh rates of all draw targets are multiplied by
+static REFRESH_MULTIPLIER: AtomicU64 = AtomicU64::new(0x3ff0_0000_0000_0000); // 1.0
+
+/// Enables or disables drawing for all progress bars.
+///
+/// While disabled, every draw target behaves as if it was hidden, including those of progress
+/// bars created by libraries. This allows applications to honor flags like `--no-progress`.
+/// Progress bars that were already drawn are left as they are.
+///
+/// ```rust
+/// # use indicatif::{set_global_draw_enabled, ProgressBar, ProgressDrawTarget, Term};
+/// # let no_progress = true;
+/// if no_progress {
+///     set_global_draw_enabled(false);
+/// }
+/// let target = ProgressDrawTarget::term_like(Box::new(Term::stderr()));
+/// let pb = ProgressBar::with_draw_target(10, target);
+/// assert!(pb.is_hidden());
+/// ```
+pub fn set_global_draw_enabled(enabled: bool) {
+    DRAW_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Multiplies the refresh rate of all rate limited draw targets by `multiplier`.
+///
+/// E.g. `0.5` halves the refresh rates, which lowers the overhead of progress bars created by
+/// libraries. Draw targets without a maximum refresh rate are not affected.
+pub fn set_global_refresh_multiplier(multiplier: f64) {
+    assert!(multiplier > 0.0, "refresh multiplier must be positive");
+    REFRESH_MULTIPLIER.store(multiplier.to_bits(), Ordering::Relaxed);
+}
+
+fn refresh_multiplier() -> f64 {
+    f64::from_bits(REFRESH_MULTIPLIER.load(Ordering::Relaxed))
+}
+
 /// Target for draw operations
 ///
 /// This tells a progress bar or a multi progress object where to paint to.
 /// The draw target is a stateful wrapper over a drawing destination and
 /// internally optimizes how often the state is painted to the output
 /// device.
+///
+/// The `with_*` methods configure draw targets that write to a terminal or a [`TermLike`], they
+/// have no effect on hidden draw targets. Progress bars added to a [`MultiProgress`] are drawn by
+/// the draw target of the `MultiProgress`, so that's the one to configure.
+///
+/// [`MultiProgress`]: crate::MultiProgress
 #[derive(Debug)]
 pub struct ProgressDrawTarget {
     kind: ProgressDrawTargetKind,
@@ -71,8 +125,24 @@ impl ProgressDrawTarget {
     }
 
     pub(crate) fn new_remote(state: Arc<RwLock<MultiProgressState>>, idx: usize) -> Self {
+        let (queue, events, skipped, layout) = {
+            let state = state.read().unwrap();
+            (
+                state.queue.clone(),
+                state.events.clone(),
+                state.skipped.clone(),
+                (state.width(idx), state.colors()),
+            )
+        };
         Self {
-            kind: ProgressDrawTargetKind::Remote { state, idx },
+            kind: ProgressDrawTargetKind::Remote {
+                state,
+                idx,
+                queue,
+                events,
+                skipped,
+                layout,
+            },
         }
     }
 
@@ -97,8 +167,19 @@ impl ProgressDrawTarget {
     /// hidden.  This is done so that piping to a file will not produce
     /// useless escape codes in that file.
     ///
+    /// If the locale is set to an encoding other than UTF-8 (e.g. `LANG=C`), the
+    /// [`Filter::AsciiBars`] filter is added, so that the default bars and spinners degrade to
+    /// ASCII instead of rendering as mojibake. The filter works on whole lines, so block and
+    /// braille characters in messages are replaced as well, while other characters such as emoji
+    /// are left untouched. Add [`Filter::AsciiOnly`] to remove all non-ASCII characters.
+    ///
     /// Will panic if refresh_rate is `Some(0)`. To disable rate limiting use `None` instead.
     pub fn term(term: Term, refresh_rate: impl Into<Option<u64>>) -> ProgressDrawTarget {
+        let mut draw_state = ProgressDrawState::new(Vec::new(), false);
+        if !locale_supports_unicode() {
+            draw_state.filters.push(Filter::AsciiBars);
+        }
+
         ProgressDrawTarget {
             kind: ProgressDrawTargetKind::Term {
                 term,
@@ -107,12 +188,241 @@ impl ProgressDrawTarget {
                     bucket: MAX_GROUP_SIZE,
                     leak_rate: rate as f64,
                     last_update: Instant::now(),
+                    max_rate: None,
+                    backoff: None,
+                    aligned: false,
+                    last_frame: None,
+                    pending_frame: None,
                 }),
-                draw_state: ProgressDrawState::new(Vec::new(), false),
+                draw_state,
             },
         }
     }
 
+    /// Adapts the refresh rate to the latency of the terminal.
+    ///
+    /// When writing to the terminal is slow (e.g. over SSH), the refresh rate is lowered
+    /// automatically, and restored up to the configured rate once the latency recovers. This has
+    /// no effect on draw targets without a refresh rate.
+    pub fn with_adaptive_refresh(mut self) -> ProgressDrawTarget {
+        if let ProgressDrawTargetKind::Term {
+            leaky_bucket: Some(bucket),
+            ..
+        } = &mut self.kind
+        {
+            bucket.max_rate = Some(bucket.leak_rate);
+        }
+        self
+    }
+
+    /// Aligns draws to fixed frame boundaries.
+    ///
+    /// By default, a progress bar is drawn whenever it is updated, as long as the refresh rate
+    /// allows it. With aligned refresh, the time is divided into frames of one refresh interval
+    /// and the progress bar is drawn at most once per frame. Combined with a steady tick (see
+    /// [`ProgressBar::enable_steady_tick()`]) of the same interval, whose ticks also land on
+    /// frame boundaries, this animates spinners and bars smoothly however irregular the
+    /// updates are. An update that comes in after its frame was drawn is drawn at the start of the
+    /// next frame, so the last update is never lost. This has no effect on draw targets without a
+    /// refresh rate.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::{ProgressBar, ProgressDrawTarget};
+    /// let pb = ProgressBar::with_draw_target(100, ProgressDrawTarget::stderr_with_hz(20).with_aligned_refresh());
+    /// pb.enable_steady_tick(50);
+    /// ```
+    ///
+    /// [`ProgressBar::enable_steady_tick()`]: crate::ProgressBar::enable_steady_tick
+    pub fn with_aligned_refresh(mut self) -> ProgressDrawTarget {
+        if let ProgressDrawTargetKind::Term {
+            leaky_bucket: Some(bucket),
+            ..
+        } = &mut self.kind
+        {
+            bucket.aligned = true;
+        }
+        self
+    }
+
+    /// Lowers the refresh rate as the draw target ages.
+    ///
+    /// The configured refresh rate is used for the first minute. After that the rate decreases
+    /// in proportion to the time elapsed, until the progress bar is redrawn only once every
+    /// `max_interval`. This keeps long-running jobs from flooding the scrollback of the terminal.
+    /// Forced draws (e.g. when finishing) are not affected. This has no effect on draw targets
+    /// without a refresh rate.
+    ///
+    /// ```rust,no_run
+    /// # use std::time::Duration;
+    /// # use indicatif::ProgressDrawTarget;
+    /// let target = ProgressDrawTarget::stderr().with_backoff_refresh(Duration::from_secs(10));
+    /// ```
+    pub fn with_backoff_refresh(mut self, max_interval: Duration) -> ProgressDrawTarget {
+        if let ProgressDrawTargetKind::Term {
+            leaky_bucket: Some(bucket),
+            ..
+        } = &mut self.kind
+        {
+            bucket.backoff = Some(Backoff {
+                started: Instant::now(),
+                max_interval: max_interval.as_secs_f64(),
+            });
+        }
+        self
+    }
+
+    /// Adds a filter that post-processes the rendered lines before they are written.
+    ///
+    /// Filters are applied in the order they were added.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::{Filter, ProgressDrawTarget};
+    /// let target = ProgressDrawTarget::stderr()
+    ///     .with_filter(Filter::StripColors)
+    ///     .with_filter(Filter::AsciiOnly);
+    /// ```
+    pub fn with_filter(mut self, filter: Filter) -> ProgressDrawTarget {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term { draw_state, .. }
+            | ProgressDrawTargetKind::TermLike { draw_state, .. } => {
+                draw_state.filters.push(filter)
+            }
+            ProgressDrawTargetKind::Remote { .. } | ProgressDrawTargetKind::Hidden { .. } => {}
+        }
+        self
+    }
+
+    /// Ends lines with `\r\n` instead of `\n`.
+    ///
+    /// This makes progress bars render correctly when the terminal is in raw mode, where a bare
+    /// `\n` only moves the cursor down without returning it to the start of the line.
+    pub fn with_crlf_line_endings(mut self) -> ProgressDrawTarget {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term { draw_state, .. }
+            | ProgressDrawTargetKind::TermLike { draw_state, .. } => draw_state.crlf = true,
+            ProgressDrawTargetKind::Remote { .. } | ProgressDrawTargetKind::Hidden { .. } => {}
+        }
+        self
+    }
+
+    /// Reports the progress to the terminal as well, for display in the taskbar or tab title.
+    ///
+    /// The progress is emitted as `OSC 9;4` escape sequences, which are understood by Windows
+    /// Terminal, ConEmu and iTerm2 and ignored by most other terminals. Spinners are reported as
+    /// indeterminate progress, and the indicator is removed once the progress bar finishes. For a
+    /// `MultiProgress` the average progress of the unfinished bars (or the progress of its total
+    /// bar, if any) is reported.
+    pub fn with_system_progress(mut self) -> ProgressDrawTarget {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term { draw_state, .. }
+            | ProgressDrawTargetKind::TermLike { draw_state, .. } => {
+                draw_state.report_progress = true
+            }
+            ProgressDrawTargetKind::Remote { .. } | ProgressDrawTargetKind::Hidden { .. } => {}
+        }
+        self
+    }
+
+    /// Controls whether styles emit ANSI colors, independent of `console`'s detection.
+    ///
+    /// This is useful to support `--color=always` when piping into a pager that understands ANSI
+    /// escape codes. For a `MultiProgress` the setting of its draw target applies to all its
+    /// progress bars.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::{ColorChoice, ProgressDrawTarget};
+    /// let target = ProgressDrawTarget::stdout().with_colors(ColorChoice::Always);
+    /// ```
+    pub fn with_colors(mut self, colors: ColorChoice) -> ProgressDrawTarget {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term { draw_state, .. }
+            | ProgressDrawTargetKind::TermLike { draw_state, .. } => draw_state.colors = colors,
+            ProgressDrawTargetKind::Remote { .. } | ProgressDrawTargetKind::Hidden { .. } => {}
+        }
+        self
+    }
+
+    /// Limits the width of the rendered lines to `width` columns.
+    ///
+    /// Progress bars are rendered as if the terminal was `width` columns wide, unless the
+    /// terminal is narrower. This makes the output consistent across terminals, e.g. for
+    /// screenshots and logs.
+    pub fn with_width(mut self, width: usize) -> ProgressDrawTarget {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term { draw_state, .. }
+            | ProgressDrawTargetKind::TermLike { draw_state, .. } => {
+                draw_state.max_width = Some(width)
+            }
+            ProgressDrawTargetKind::Remote { .. } | ProgressDrawTargetKind::Hidden { .. } => {}
+        }
+        self
+    }
+
+    /// Lays out progress bars for `width` columns if the terminal reports a width of 0.
+    ///
+    /// Some CI systems and pseudo terminals don't report their size. Without a fallback width,
+    /// lines are not truncated in that case and `{wide_bar}` and `{wide_msg}` take their natural
+    /// width, like `{bar}` and `{msg}`.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::ProgressDrawTarget;
+    /// let target = ProgressDrawTarget::stderr().with_fallback_width(80);
+    /// ```
+    pub fn with_fallback_width(mut self, width: usize) -> ProgressDrawTarget {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term { draw_state, .. }
+            | ProgressDrawTargetKind::TermLike { draw_state, .. } => {
+                draw_state.fallback_width = Some(width)
+            }
+            ProgressDrawTargetKind::Remote { .. } | ProgressDrawTargetKind::Hidden { .. } => {}
+        }
+        self
+    }
+
+    /// Draws the bars of the progress bars with `graphics`, if the terminal supports it
+    ///
+    /// See [`BarGraphics`] for details. Progress bars in a [`MultiProgress`] are always drawn as
+    /// text.
+    ///
+    /// [`MultiProgress`]: crate::MultiProgress
+    pub fn with_bar_graphics(mut self, graphics: impl BarGraphics + 'static) -> ProgressDrawTarget {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term { draw_state, .. }
+            | ProgressDrawTargetKind::TermLike { draw_state, .. } => {
+                draw_state.graphics = Some(Arc::new(graphics))
+            }
+            ProgressDrawTargetKind::Remote { .. } | ProgressDrawTargetKind::Hidden { .. } => {}
+        }
+        self
+    }
+
+    /// Writes the lines printed with [`ProgressBar::println()`] to `log` instead of above the
+    /// progress bars
+    ///
+    /// The progress bars are cleared before the lines are written and flushed, and redrawn
+    /// afterwards, so the output stays in order even if both go to the same terminal. This
+    /// allows drawing to stderr while logging to stdout: redirecting stdout to a file then
+    /// captures the logs only, while the progress bars stay on the terminal.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::{ProgressBar, ProgressDrawTarget, Term};
+    /// let target = ProgressDrawTarget::stderr().with_log_target(Box::new(Term::stdout()));
+    /// let pb = ProgressBar::with_draw_target(100, target);
+    /// pb.println("written to stdout");
+    /// ```
+    ///
+    /// [`ProgressBar::println()`]: crate::ProgressBar::println
+    pub fn with_log_target(mut self, log: Box<dyn TermLike>) -> ProgressDrawTarget {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term { draw_state, .. }
+            | ProgressDrawTargetKind::TermLike { draw_state, .. } => {
+                draw_state.log = Some(Arc::from(log))
+            }
+            ProgressDrawTargetKind::Remote { .. } | ProgressDrawTargetKind::Hidden { .. } => {}
+        }
+        self
+    }
+
     /// Draw to a boxed object that implements the [`TermLike`] trait.
     pub fn term_like(term_like: Box<dyn TermLike>) -> ProgressDrawTarget {
         ProgressDrawTarget {
@@ -124,12 +434,75 @@ impl ProgressDrawTarget {
         }
     }
 
+    /// Hand every rendered frame to a callback instead of a terminal.
+    ///
+    /// The callback receives the lines of the frame, without styling padding, whenever the bars
+    /// are redrawn, and an empty slice when they are cleared. Lines printed with
+    /// [`ProgressBar::println()`] come first. This is useful to render progress in environments
+    /// without a terminal, like a GUI or a web page (see the `wasm` feature); `width` is the width
+    /// bars are laid out for.
+    /// To get the raw [`ProgressState`] instead of rendered lines, see
+    /// `ProgressBar::set_renderer()` behind the `unstable-renderer` feature.
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressBar, ProgressDrawTarget};
+    /// let target = ProgressDrawTarget::callback(80, |lines: &[String]| {
+    ///     for line in lines {
+    ///         eprintln!("{}", line);
+    ///     }
+    /// });
+    /// let pb = ProgressBar::with_draw_target(100, target);
+    /// pb.inc(1);
+    /// ```
+    ///
+    /// [`ProgressBar::println()`]: crate::ProgressBar::println
+    /// [`ProgressState`]: crate::ProgressState
+    pub fn callback<F>(width: usize, callback: F) -> ProgressDrawTarget
+    where
+        F: Fn(&[String]) + Send + Sync + 'static,
+    {
+        ProgressDrawTarget::term_like(Box::new(CallbackTerm::new(width, Box::new(callback))))
+    }
+
     /// A hidden draw target.
     ///
     /// This forces a progress bar to be not rendered at all.
     pub fn hidden() -> ProgressDrawTarget {
         ProgressDrawTarget {
-            kind: ProgressDrawTargetKind::Hidden,
+            kind: ProgressDrawTargetKind::Hidden { draws: None },
+        }
+    }
+
+    /// A hidden draw target counting the draws that would have happened.
+    ///
+    /// Progress bars drawing to this target skip all formatting, like with a [hidden] target,
+    /// but the returned [`DrawCounter`] counts how often they would have been drawn. This helps
+    /// measuring the overhead of progress bars, see also [`Benchmark`].
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressBar, ProgressDrawTarget};
+    /// let (target, counter) = ProgressDrawTarget::noop_counted();
+    /// let pb = ProgressBar::with_draw_target(10, target);
+    /// pb.inc(1);
+    /// assert_eq!(counter.count(), 1);
+    /// ```
+    ///
+    /// [hidden]: ProgressDrawTarget::hidden
+    /// [`Benchmark`]: crate::Benchmark
+    pub fn noop_counted() -> (ProgressDrawTarget, DrawCounter) {
+        let counter = DrawCounter::default();
+        let target = ProgressDrawTarget {
+            kind: ProgressDrawTargetKind::Hidden {
+                draws: Some(counter.0.clone()),
+            },
+        };
+        (target, counter)
+    }
+
+    /// Counts a draw skipped because the draw target is hidden
+    pub(crate) fn count_draw(&self) {
+        if let ProgressDrawTargetKind::Hidden { draws: Some(draws) } = &self.kind {
+            draws.fetch_add(1, Ordering::Relaxed);
         }
     }
 
@@ -138,20 +511,56 @@ impl ProgressDrawTarget {
     /// This is internally used in progress bars to figure out if overhead
     /// from drawing can be prevented.
     pub fn is_hidden(&self) -> bool {
+        if !DRAW_ENABLED.load(Ordering::Relaxed) {
+            return true;
+        }
         match self.kind {
-            ProgressDrawTargetKind::Hidden => true,
+            ProgressDrawTargetKind::Hidden { .. } => true,
             ProgressDrawTargetKind::Term { ref term, .. } => !term.is_term(),
             _ => false,
         }
     }
 
+    /// Returns the time between two draws allowed by the refresh rate, if it's limited
+    pub(crate) fn refresh_interval(&self, now: Instant) -> Option<Duration> {
+        match &self.kind {
+            ProgressDrawTargetKind::Term {
+                leaky_bucket: Some(bucket),
+                ..
+            } => Some(Duration::from_secs_f64(1.0 / bucket.rate(now))),
+            _ => None,
+        }
+    }
+
+    /// Returns when to draw a frame that was dropped to keep draws aligned to frames, see
+    /// [`ProgressDrawTarget::with_aligned_refresh()`].
+    pub(crate) fn take_pending_frame(&mut self) -> Option<Instant> {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term {
+                leaky_bucket: Some(bucket),
+                ..
+            } => bucket.pending_frame.take(),
+            _ => None,
+        }
+    }
+
     /// Returns the current width of the draw target.
     pub(crate) fn width(&self) -> usize {
         match self.kind {
-            ProgressDrawTargetKind::Term { ref term, .. } => term.size().1 as usize,
-            ProgressDrawTargetKind::Remote { ref state, .. } => state.read().unwrap().width(),
-            ProgressDrawTargetKind::Hidden => 0,
-            ProgressDrawTargetKind::TermLike { ref inner, .. } => inner.width(),
+            ProgressDrawTargetKind::Term {
+                ref term,
+                ref draw_state,
+                ..
+            } => draw_state.limit_width(term.size().1 as usize),
+            ProgressDrawTargetKind::Remote { ref state, idx, .. } => {
+                state.read().unwrap().width(idx)
+            }
+            ProgressDrawTargetKind::Hidden { .. } => 0,
+            ProgressDrawTargetKind::TermLike {
+                ref inner,
+                ref draw_state,
+                ..
+            } => draw_state.limit_width(inner.width()),
         }
     }
 
@@ -174,13 +583,44 @@ impl ProgressDrawTarget {
                     true => Some(Drawable::Term {
                         term,
                         last_line_count,
+                        leaky_bucket,
                         draw_state,
                     }),
-                    false => None, // rate limited
+                    false => {
+                        // rate limited
+                        if let Some(bucket) = leaky_bucket {
+                            bucket.defer(now);
+                        }
+                        None
+                    }
                 }
             }
-            ProgressDrawTargetKind::Remote { idx, state, .. } => {
-                let state = state.write().unwrap();
+            ProgressDrawTargetKind::Remote {
+                idx,
+                state,
+                skipped,
+                layout,
+                ..
+            } => {
+                // Don't wait while another thread draws the multi progress, so that progress
+                // bars don't slow down the work they report on. The frame is laid out with the
+                // last known width and handed to that thread, which draws it once it's done, see
+                // `MultiProgressState::draw()`.
+                let state = match force_draw {
+                    true => state.write().unwrap(),
+                    false => match state.try_write() {
+                        Ok(state) => state,
+                        Err(_) => {
+                            return Some(Drawable::Skipped {
+                                frame: Box::new(SkippedDraw::new(*idx)),
+                                skipped,
+                                width: layout.0,
+                                colors: layout.1,
+                            })
+                        }
+                    },
+                };
+                *layout = (state.width(*idx), state.colors());
                 Some(Drawable::Multi {
                     idx: *idx,
                     state,
@@ -205,6 +645,32 @@ impl ProgressDrawTarget {
         }
     }
 
+    /// Returns whether styles should be forced on or off, `None` uses `console`'s detection.
+    pub(crate) fn colors(&self) -> Option<bool> {
+        match &self.kind {
+            ProgressDrawTargetKind::Term { draw_state, .. }
+            | ProgressDrawTargetKind::TermLike { draw_state, .. } => draw_state.colors.forced(),
+            ProgressDrawTargetKind::Remote { state, .. } => state.read().unwrap().colors(),
+            ProgressDrawTargetKind::Hidden { .. } => None,
+        }
+    }
+
+    /// Rings the terminal bell, unless the draw target is hidden.
+    pub(crate) fn ring_bell(&self) {
+        match self.kind {
+            ProgressDrawTargetKind::Term { ref term, .. } if term.is_term() => {
+                let _ = term.write_str("\x07").and_then(|()| term.flush());
+            }
+            ProgressDrawTargetKind::Remote { ref state, .. } => {
+                state.read().unwrap().ring_bell();
+            }
+            ProgressDrawTargetKind::TermLike { ref inner, .. } => {
+                let _ = inner.write_str("\x07").and_then(|()| inner.flush());
+            }
+            _ => {}
+        }
+    }
+
     /// Properly disconnects from the draw target
     pub(crate) fn disconnect(&self, now: Instant) {
         match self.kind {
@@ -219,19 +685,304 @@ impl ProgressDrawTarget {
                 }
                 .clear();
             }
-            ProgressDrawTargetKind::Hidden => {}
+            ProgressDrawTargetKind::Hidden { .. } => {}
             ProgressDrawTargetKind::TermLike { .. } => {}
         };
     }
 
+    /// Switches the terminal to its alternate screen buffer, starting from a cleared screen.
+    pub(crate) fn enter_alternate_screen(&mut self) -> io::Result<()> {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term {
+                term,
+                last_line_count,
+                ..
+            } if term.is_term() => enter_alternate_screen(term, last_line_count),
+            ProgressDrawTargetKind::TermLike {
+                inner,
+                last_line_count,
+                ..
+            } => enter_alternate_screen(&**inner, last_line_count),
+            _ => Ok(()),
+        }
+    }
+
+    /// Switches the terminal back to its primary screen buffer, then writes `lines` to it.
+    pub(crate) fn leave_alternate_screen(&mut self, lines: &[String]) -> io::Result<()> {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term {
+                term,
+                last_line_count,
+                draw_state,
+                ..
+            } if term.is_term() => leave_alternate_screen(term, last_line_count, draw_state, lines),
+            ProgressDrawTargetKind::TermLike {
+                inner,
+                last_line_count,
+                draw_state,
+            } => leave_alternate_screen(&**inner, last_line_count, draw_state, lines),
+            _ => Ok(()),
+        }
+    }
+
+    /// Restores the full terminal as scroll region if lines were pinned to the bottom by the
+    /// [`DrawStrategy::Scroll`] strategy, and moves the cursor below them.
+    pub(crate) fn release_scroll_region(&mut self) -> io::Result<()> {
+        match &mut self.kind {
+            ProgressDrawTargetKind::Term {
+                term,
+                last_line_count,
+                draw_state,
+                ..
+            } => release_scroll_region(term, last_line_count, draw_state),
+            ProgressDrawTargetKind::TermLike {
+                inner,
+                last_line_count,
+                draw_state,
+            } => release_scroll_region(&**inner, last_line_count, draw_state),
+            _ => Ok(()),
+        }
+    }
+
+    /// Queues `lines` to be printed above a multi progress, returns `false` if this isn't a remote
+    /// draw target.
+    ///
+    /// The lines are written by whichever thread draws the multi progress next, so printing
+    /// doesn't wait for other threads to finish drawing.
+    pub(crate) fn queue_lines(&self, lines: &str, now: Instant) -> bool {
+        match &self.kind {
+            ProgressDrawTargetKind::Remote { queue, .. } => {
+                queue.lock().unwrap().extend(lines.lines().map(Into::into));
+                self.flush_queued(now);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Draws the multi progress if lines are queued and no other thread is drawing it.
+    pub(crate) fn flush_queued(&self, now: Instant) {
+        if let ProgressDrawTargetKind::Remote { state, queue, .. } = &self.kind {
+            // Another thread drawing the multi progress checks the queue again once it's done
+            while !queue.lock().unwrap().is_empty() {
+                match state.try_write() {
+                    // The lines are drawn once drawing is resumed
+                    Ok(state) if state.suspended => break,
+                    Ok(mut state) => {
+                        let _ = state.draw(true, now);
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+    }
+
     pub(crate) fn remote(&self) -> Option<(&Arc<RwLock<MultiProgressState>>, usize)> {
         match &self.kind {
-            ProgressDrawTargetKind::Remote { state, idx } => Some((state, *idx)),
+            ProgressDrawTargetKind::Remote { state, idx, .. } => Some((state, *idx)),
+            _ => None,
+        }
+    }
+
+    /// Returns the work to do once the progress bar is unlocked, if this is a remote target.
+    pub(crate) fn after_unlock(&self) -> Option<AfterUnlock> {
+        match &self.kind {
+            ProgressDrawTargetKind::Remote {
+                state,
+                events,
+                skipped,
+                ..
+            } => Some(AfterUnlock {
+                state: state.clone(),
+                events: events.clone(),
+                skipped: skipped.clone(),
+            }),
             _ => None,
         }
     }
 }
 
+fn enter_alternate_screen(
+    term: &(impl TermLike + ?Sized),
+    last_line_count: &mut usize,
+) -> io::Result<()> {
+    term.write_str("\x1b[?1049h\x1b[2J\x1b[H")?;
+    term.flush()?;
+    *last_line_count = 0;
+    Ok(())
+}
+
+fn leave_alternate_screen(
+    term: &(impl TermLike + ?Sized),
+    last_line_count: &mut usize,
+    draw_state: &ProgressDrawState,
+    lines: &[String],
+) -> io::Result<()> {
+    term.write_str("\x1b[?1049l")?;
+    for line in lines {
+        draw_state.write_line(term, line)?;
+    }
+    term.flush()?;
+    *last_line_count = 0;
+    Ok(())
+}
+
+fn release_scroll_region(
+    term: &(impl TermLike + ?Sized),
+    last_line_count: &mut usize,
+    draw_state: &ProgressDrawState,
+) -> io::Result<()> {
+    let height = term.height();
+    if draw_state.strategy != DrawStrategy::Scroll || *last_line_count == 0 || height <= 1 {
+        return Ok(());
+    }
+
+    term.write_str(&format!("\x1b[r\x1b[{};1H", height))?;
+    draw_state.write_line(term, "")?;
+    term.flush()?;
+    *last_line_count = 0;
+    Ok(())
+}
+
+/// How a [`MultiProgress`] replaces the lines it previously drew
+///
+/// See [`MultiProgress::set_draw_strategy()`].
+///
+/// [`MultiProgress`]: crate::MultiProgress
+/// [`MultiProgress::set_draw_strategy()`]: crate::MultiProgress::set_draw_strategy
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DrawStrategy {
+    /// Clear the previous lines, then draw the new ones (the default)
+    ClearAndRedraw,
+    /// Move the cursor up and overwrite the previous lines
+    ///
+    /// This can reduce flickering, but leaves stale lines behind if the number of lines shrinks.
+    CursorMovement,
+    /// Pin the lines to the bottom of the terminal, and let printed lines scroll above them
+    ///
+    /// This uses a terminal scroll region, so the progress bars are never redrawn while lines are
+    /// printed. Falls back to `ClearAndRedraw` if the height of the terminal is unknown.
+    Scroll,
+}
+
+impl Default for DrawStrategy {
+    fn default() -> Self {
+        Self::ClearAndRedraw
+    }
+}
+
+/// Whether a [`ProgressDrawTarget`] emits colors
+///
+/// See [`ProgressDrawTarget::with_colors()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    /// Always emit colors
+    Always,
+    /// Never emit colors
+    Never,
+    /// Emit colors if `console` detects support for them
+    Auto,
+}
+
+impl ColorChoice {
+    fn forced(self) -> Option<bool> {
+        match self {
+            ColorChoice::Always => Some(true),
+            ColorChoice::Never => Some(false),
+            ColorChoice::Auto => None,
+        }
+    }
+}
+
+impl Default for ColorChoice {
+    fn default() -> Self {
+        ColorChoice::Auto
+    }
+}
+
+/// A post-processing step applied to rendered lines by a [`ProgressDrawTarget`]
+///
+/// See [`ProgressDrawTarget::with_filter()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Filter {
+    /// Removes all ANSI escape codes, including colors
+    StripColors,
+    /// Replaces block characters used by bars with `#` and `-` and removes any other non-ASCII
+    /// characters such as emoji or spinner glyphs
+    ///
+    /// Partial blocks are rounded: those filling at least half a cell become `#`, narrower ones
+    /// become `-`.
+    AsciiOnly,
+    /// Replaces block characters used by bars with `#` and `-` and the braille characters used by
+    /// spinners with `*`, leaving any other characters untouched
+    ///
+    /// Partial blocks are rounded like with [`Filter::AsciiOnly`]. The characters are replaced
+    /// anywhere in the line, including in messages and prefixes.
+    AsciiBars,
+}
+
+impl Filter {
+    fn apply<'a>(&self, line: Cow<'a, str>) -> Cow<'a, str> {
+        match self {
+            Filter::StripColors => match compat::strip_ansi_codes(&line) {
+                Cow::Borrowed(_) => line,
+                Cow::Owned(stripped) => Cow::Owned(stripped),
+            },
+            Filter::AsciiOnly if line.is_ascii() => line,
+            Filter::AsciiOnly => Cow::Owned(
+                line.chars()
+                    .filter_map(|c| match c {
+                        c if c.is_ascii() => Some(c),
+                        c => ascii_block(c),
+                    })
+                    .collect(),
+            ),
+            Filter::AsciiBars if line.is_ascii() => line,
+            Filter::AsciiBars => Cow::Owned(
+                line.chars()
+                    .map(|c| match c {
+                        '\u{2800}'..='\u{28ff}' => '*',
+                        c => ascii_block(c).unwrap_or(c),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Returns the ASCII replacement of a block character used by bars
+///
+/// The full block and the left blocks filling at least half a cell are filled, the narrower left
+/// blocks and the shades are empty.
+fn ascii_block(c: char) -> Option<char> {
+    match c {
+        '\u{2588}'..='\u{258c}' => Some('#'),
+        '\u{258d}'..='\u{258f}' | '\u{2591}'..='\u{2593}' => Some('-'),
+        _ => None,
+    }
+}
+
+/// Returns false if the locale is explicitly set to an encoding other than UTF-8.
+fn locale_supports_unicode() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty());
+    match locale {
+        Some(locale) => is_utf8_locale(&locale),
+        None => true,
+    }
+}
+
+fn is_utf8_locale(locale: &str) -> bool {
+    let locale = locale.to_ascii_lowercase();
+    locale.ends_with(".utf-8")
+        || locale.ends_with(".utf8")
+        || locale.contains(".utf-8@")
+        || locale.contains(".utf8@")
+}
+
 #[derive(Debug)]
 enum ProgressDrawTargetKind {
     Term {
@@ -243,8 +994,19 @@ enum ProgressDrawTargetKind {
     Remote {
         state: Arc<RwLock<MultiProgressState>>,
         idx: usize,
+        /// Lines printed above the multi progress, waiting for it to be drawn
+        queue: Arc<Mutex<Vec<String>>>,
+        /// Events of the multi progress, dispatched once the progress bar is unlocked
+        events: Arc<EventQueue>,
+        /// Frames of the progress bars of the multi progress whose draws were skipped
+        skipped: Arc<Mutex<Vec<SkippedDraw>>>,
+        /// The width and colors of the last draw, used to lay out skipped draws
+        layout: (usize, Option<bool>),
+    },
+    Hidden {
+        /// Counts the draws that would have happened, if set
+        draws: Option<Arc<AtomicU64>>,
     },
-    Hidden,
     TermLike {
         inner: Box<dyn TermLike>,
         last_line_count: usize,
@@ -252,10 +1014,24 @@ enum ProgressDrawTargetKind {
     },
 }
 
+/// Counts the draws of a [`ProgressDrawTarget::noop_counted()`] target
+///
+/// This is cheap to clone, clones share the same count.
+#[derive(Clone, Debug, Default)]
+pub struct DrawCounter(Arc<AtomicU64>);
+
+impl DrawCounter {
+    /// Returns the number of draws so far
+    pub fn count(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
 pub(crate) enum Drawable<'a> {
     Term {
         term: &'a Term,
         last_line_count: &'a mut usize,
+        leaky_bucket: &'a mut Option<LeakyBucket>,
         draw_state: &'a mut ProgressDrawState,
     },
     Multi {
@@ -269,6 +1045,13 @@ pub(crate) enum Drawable<'a> {
         last_line_count: &'a mut usize,
         draw_state: &'a mut ProgressDrawState,
     },
+    /// A draw of a progress bar of a multi progress another thread is drawing
+    Skipped {
+        frame: Box<SkippedDraw>,
+        skipped: &'a Mutex<Vec<SkippedDraw>>,
+        width: usize,
+        colors: Option<bool>,
+    },
 }
 
 impl<'a> Drawable<'a> {
@@ -277,12 +1060,61 @@ impl<'a> Drawable<'a> {
             Drawable::Term { draw_state, .. } => DrawStateWrapper::for_term(draw_state),
             Drawable::Multi { state, idx, .. } => state.draw_state(*idx),
             Drawable::TermLike { draw_state, .. } => DrawStateWrapper::for_term(draw_state),
+            Drawable::Skipped { frame, .. } => DrawStateWrapper::for_term(&mut frame.draw_state),
         };
 
         state.reset();
         state
     }
 
+    /// Returns the width available to the progress bar.
+    pub(crate) fn width(&self) -> usize {
+        match self {
+            Drawable::Term {
+                term, draw_state, ..
+            } => draw_state.limit_width(term.size().1 as usize),
+            Drawable::Multi { state, idx, .. } => state.width(*idx),
+            Drawable::TermLike {
+                term_like,
+                draw_state,
+                ..
+            } => draw_state.limit_width(term_like.width()),
+            Drawable::Skipped { width, .. } => *width,
+        }
+    }
+
+    /// Returns whether styles should be forced on or off, `None` uses `console`'s detection.
+    pub(crate) fn colors(&self) -> Option<bool> {
+        match self {
+            Drawable::Term { draw_state, .. } | Drawable::TermLike { draw_state, .. } => {
+                draw_state.colors.forced()
+            }
+            Drawable::Multi { state, .. } => state.colors(),
+            Drawable::Skipped { colors, .. } => *colors,
+        }
+    }
+
+    /// Returns the graphics the bars are drawn with, if any
+    pub(crate) fn graphics(&self) -> Option<Arc<dyn BarGraphics>> {
+        match self {
+            Drawable::Term { draw_state, .. } | Drawable::TermLike { draw_state, .. } => {
+                draw_state.graphics.clone()
+            }
+            Drawable::Multi { .. } | Drawable::Skipped { .. } => None,
+        }
+    }
+
+    /// Exchanges progress information with the `MultiProgress` the progress bar is part of.
+    pub(crate) fn sync_progress(&mut self, progress: &mut ProgressState) {
+        match self {
+            Drawable::Multi {
+                state, idx, now, ..
+            } => state.sync_progress(*idx, progress, *now),
+            Drawable::Skipped { frame, .. } => frame.record_progress(progress),
+            _ => {}
+        }
+    }
+
     pub(crate) fn clear(mut self) -> io::Result<()> {
         let state = self.state();
         drop(state);
@@ -294,8 +1126,16 @@ impl<'a> Drawable<'a> {
             Drawable::Term {
                 term,
                 last_line_count,
+                leaky_bucket,
                 draw_state,
-            } => draw_state.draw_to_term(term, last_line_count),
+            } => {
+                let start = Instant::now();
+                let result = draw_state.draw_frame(term, last_line_count);
+                if let Some(bucket) = leaky_bucket {
+                    bucket.record_latency(start.elapsed());
+                }
+                result
+            }
             Drawable::Multi {
                 mut state,
                 force_draw,
@@ -307,6 +1147,12 @@ impl<'a> Drawable<'a> {
                 last_line_count,
                 draw_state,
             } => draw_state.draw_to_term(term_like, last_line_count),
+            Drawable::Skipped { frame, skipped, .. } => {
+                let mut skipped = skipped.lock().unwrap();
+                skipped.retain(|pending| pending.idx != frame.idx);
+                skipped.push(*frame);
+                Ok(())
+            }
         }
     }
 }
@@ -358,11 +1204,115 @@ impl Drop for DrawStateWrapper<'_> {
     }
 }
 
+/// Fork of console::clear_last_lines that assumes that the last line doesn't contain a '\n'
+fn clear_last_lines(term: &(impl TermLike + ?Sized), n: usize) -> io::Result<()> {
+    term.move_cursor_up(n.saturating_sub(1))?;
+    for i in 0..n {
+        term.clear_line()?;
+        if i + 1 != n {
+            term.move_cursor_down(1)?;
+        }
+    }
+    term.move_cursor_up(n.saturating_sub(1))
+}
+
+/// Collects the output written to it, and writes it to the terminal at once when flushed
 #[derive(Debug)]
-struct LeakyBucket {
+struct FrameBuffer<'a, T: ?Sized> {
+    term: &'a T,
+    buf: Mutex<String>,
+}
+
+impl<'a, T: TermLike + ?Sized> FrameBuffer<'a, T> {
+    /// Buffers the output for `term` in `buf`, which is cleared first
+    fn new(term: &'a T, mut buf: String) -> Self {
+        buf.clear();
+        Self {
+            term,
+            buf: Mutex::new(buf),
+        }
+    }
+
+    /// Returns the buffer, to reuse its allocation for the next frame
+    fn into_inner(self) -> String {
+        self.buf.into_inner().unwrap()
+    }
+
+    fn push_escape(&self, n: usize, code: char) -> io::Result<()> {
+        if n > 0 {
+            let mut buf = self.buf.lock().unwrap();
+            buf.write_fmt(format_args!("\x1b[{}{}", n, code)).unwrap();
+        }
+        Ok(())
+    }
+}
+
+impl<T: TermLike + ?Sized> TermLike for FrameBuffer<'_, T> {
+    fn width(&self) -> usize {
+        self.term.width()
+    }
+
+    fn height(&self) -> usize {
+        self.term.height()
+    }
+
+    fn move_cursor_up(&self, n: usize) -> io::Result<()> {
+        self.push_escape(n, 'A')
+    }
+
+    fn move_cursor_down(&self, n: usize) -> io::Result<()> {
+        self.push_escape(n, 'B')
+    }
+
+    fn move_cursor_right(&self, n: usize) -> io::Result<()> {
+        self.push_escape(n, 'C')
+    }
+
+    fn move_cursor_left(&self, n: usize) -> io::Result<()> {
+        self.push_escape(n, 'D')
+    }
+
+    fn write_line(&self, s: &str) -> io::Result<()> {
+        let mut buf = self.buf.lock().unwrap();
+        buf.push_str(s);
+        buf.push('\n');
+        Ok(())
+    }
+
+    fn write_str(&self, s: &str) -> io::Result<()> {
+        self.buf.lock().unwrap().push_str(s);
+        Ok(())
+    }
+
+    fn clear_line(&self) -> io::Result<()> {
+        self.write_str("\r\x1b[2K")
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut buf = self.buf.lock().unwrap();
+        if !buf.is_empty() {
+            self.term.write_str(&buf)?;
+            buf.clear();
+        }
+        self.term.flush()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct LeakyBucket {
     leak_rate: f64,
     last_update: Instant,
     bucket: f64,
+    /// The configured rate, if the leak rate adapts to the draw latency
+    max_rate: Option<f64>,
+    /// Lowers the leak rate over time, if set
+    backoff: Option<Backoff>,
+    /// Whether draws are aligned to frames instead of leaking
+    aligned: bool,
+    /// The frame drawn in last, if draws are aligned
+    last_frame: Option<u64>,
+    /// The start of the next frame, if an aligned draw was dropped and is still to be done
+    pending_frame: Option<Instant>,
 }
 
 /// Rate limit but allow occasional bursts above desired rate
@@ -370,6 +1320,19 @@ impl LeakyBucket {
     /// try to add some work to the bucket
     /// return false if the bucket is already full and the work should be skipped
     fn try_add_work(&mut self, now: Instant) -> bool {
+        if self.aligned {
+            // Measured in whole nanoseconds, like the ticker, so that ticks on a frame boundary
+            // don't round into the previous frame
+            let interval = self.frame_nanos(now);
+            let elapsed = now.saturating_duration_since(frame_epoch()).as_nanos();
+            let frame = Some((elapsed / interval) as u64);
+            if frame == self.last_frame {
+                return false;
+            }
+            self.last_frame = frame;
+            return true;
+        }
+
         self.leak(now);
         if self.bucket < MAX_GROUP_SIZE {
             self.bucket += 1.0;
@@ -379,8 +1342,49 @@ impl LeakyBucket {
         }
     }
 
+    /// the length of a frame in nanoseconds, if draws are aligned
+    fn frame_nanos(&self, now: Instant) -> u128 {
+        ((1e9 / self.rate(now)).round() as u128).max(1)
+    }
+
+    /// remember to draw at the start of the next frame, if draws are aligned
+    fn defer(&mut self, now: Instant) {
+        if self.aligned {
+            let interval = Duration::from_nanos(self.frame_nanos(now) as u64);
+            self.pending_frame = Some(next_frame(now, interval));
+        }
+    }
+
+    /// adapt the leak rate to the time it took to draw, if adaptive refresh is enabled
+    ///
+    /// Halves the rate when a draw takes more than a quarter of the frame interval, and
+    /// raises it back towards the configured rate when draws are fast again.
+    fn record_latency(&mut self, latency: Duration) {
+        let max_rate = match self.max_rate {
+            Some(max_rate) => max_rate,
+            None => return,
+        };
+
+        let interval = 1.0 / self.leak_rate;
+        let latency = latency.as_secs_f64();
+        if latency > interval / 4.0 {
+            self.leak_rate = (self.leak_rate / 2.0).max(MIN_ADAPTIVE_RATE.min(max_rate));
+        } else if latency < interval / 20.0 {
+            self.leak_rate = (self.leak_rate * 1.25).min(max_rate);
+        }
+    }
+
+    /// the leak rate at `now`, taking the backoff and the global multiplier into account
+    fn rate(&self, now: Instant) -> f64 {
+        let rate = self.leak_rate * refresh_multiplier();
+        match &self.backoff {
+            Some(backoff) => backoff.rate(rate, now),
+            None => rate,
+        }
+    }
+
     fn leak(&mut self, now: Instant) {
-        let ticks = (now - self.last_update).as_secs_f64() * self.leak_rate;
+        let ticks = (now - self.last_update).as_secs_f64() * self.rate(now);
         self.bucket -= ticks;
         if self.bucket < 0.0 {
             self.bucket = 0.0;
@@ -389,7 +1393,29 @@ impl LeakyBucket {
     }
 }
 
+#[derive(Debug)]
+struct Backoff {
+    started: Instant,
+    /// The longest time between two draws, in seconds
+    max_interval: f64,
+}
+
+impl Backoff {
+    /// scale `rate` down by the time elapsed since the start, once the grace period is over
+    fn rate(&self, rate: f64, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        if elapsed <= BACKOFF_GRACE_SECS {
+            return rate;
+        }
+
+        let min_rate = (1.0 / self.max_interval).min(rate);
+        (rate * BACKOFF_GRACE_SECS / elapsed).max(min_rate)
+    }
+}
+
 const MAX_GROUP_SIZE: f64 = 32.0;
+const BACKOFF_GRACE_SECS: f64 = 60.0;
+const MIN_ADAPTIVE_RATE: f64 = 1.0;
 
 /// The drawn state of an element.
 #[derive(Clone, Debug)]
@@ -400,21 +1426,91 @@ pub(crate) struct ProgressDrawState {
     pub(crate) orphan_lines: usize,
     /// True if drawing should be forced.
     force_draw: bool,
-    /// True if we should move the cursor up when possible instead of clearing lines.
-    pub(crate) move_cursor: bool,
+    /// How previously drawn lines are replaced
+    pub(crate) strategy: DrawStrategy,
     /// Controls how the multi progress is aligned if some of its progress bars get removed, default is `Top`
     pub(crate) alignment: MultiProgressAlignment,
+    /// Filters applied to the lines before they are written
+    filters: Vec<Filter>,
+    /// True if lines should end with `\r\n` instead of `\n`.
+    crlf: bool,
+    /// Time at which the progress bar that drew these lines finished
+    pub(crate) finished_at: Option<Instant>,
+    /// The progress to report to the terminal
+    pub(crate) progress: SystemProgress,
+    /// True if the progress should be reported to the terminal with `OSC 9;4`.
+    report_progress: bool,
+    /// Whether styles emit colors
+    colors: ColorChoice,
+    /// Maximum width of the lines, if narrower than the terminal
+    max_width: Option<usize>,
+    /// Width used if the terminal reports a width of 0
+    fallback_width: Option<usize>,
+    /// Draws the bars with graphics instead of text
+    graphics: Option<Arc<dyn BarGraphics>>,
+    /// Target the orphan lines are written to instead of the terminal, if any
+    log: Option<Arc<dyn TermLike>>,
+    /// Cleared lines of the previous draw, reused when formatting
+    pub(crate) spare_lines: Vec<String>,
+    /// Buffer a frame is written to before it is sent to the terminal in one write
+    frame: String,
 }
 
 impl ProgressDrawState {
+    /// Applies the fallback and the maximum width to the width of the terminal
+    fn limit_width(&self, width: usize) -> usize {
+        let width = match width {
+            0 => self.fallback_width.unwrap_or(0),
+            width => width,
+        };
+        self.max_width.map_or(width, |max| width.min(max))
+    }
+
     pub(crate) fn new(lines: Vec<String>, force_draw: bool) -> Self {
         Self {
             lines,
             orphan_lines: 0,
             force_draw,
-            move_cursor: false,
+            strategy: DrawStrategy::default(),
             alignment: Default::default(),
+            filters: Vec::new(),
+            crlf: false,
+            finished_at: None,
+            progress: SystemProgress::None,
+            report_progress: false,
+            colors: ColorChoice::Auto,
+            max_width: None,
+            fallback_width: None,
+            graphics: None,
+            log: None,
+            spare_lines: Vec::new(),
+            frame: String::new(),
+        }
+    }
+
+    fn write_line(&self, term: &(impl TermLike + ?Sized), line: &str) -> io::Result<()> {
+        match self.crlf {
+            true => {
+                term.write_str(line)?;
+                term.write_str("\r\n")
+            }
+            false => term.write_line(line),
+        }
+    }
+
+    /// Draws to `term`, writing each frame with a single write to avoid tearing
+    ///
+    /// The legacy Windows console moves the cursor through API calls instead of escape codes, so
+    /// frames are written piecewise there.
+    fn draw_frame(&mut self, term: &Term, last_line_count: &mut usize) -> io::Result<()> {
+        if cfg!(windows) {
+            return self.draw_to_term(term, last_line_count);
         }
+
+        let frame = FrameBuffer::new(term, mem::take(&mut self.frame));
+        let result = self.draw_to_term(&frame, last_line_count);
+        self.frame = frame.into_inner();
+        result
     }
 
     fn draw_to_term(
@@ -422,26 +1518,22 @@ impl ProgressDrawState {
         term: &(impl TermLike + ?Sized),
         last_line_count: &mut usize,
     ) -> io::Result<()> {
-        if !self.lines.is_empty() && self.move_cursor {
+        self.write_log(term, last_line_count)?;
+        if self.strategy == DrawStrategy::Scroll && term.height() > 1 {
+            return self.draw_pinned(term, last_line_count);
+        }
+
+        if !self.lines.is_empty() && self.strategy == DrawStrategy::CursorMovement {
             term.move_cursor_up(*last_line_count)?;
         } else {
-            // Fork of console::clear_last_lines that assumes that the last line doesn't contain a '\n'
-            let n = *last_line_count;
-            term.move_cursor_up(n.saturating_sub(1))?;
-            for i in 0..n {
-                term.clear_line()?;
-                if i + 1 != n {
-                    term.move_cursor_down(1)?;
-                }
-            }
-            term.move_cursor_up(n.saturating_sub(1))?;
+            clear_last_lines(term, *last_line_count)?;
         }
 
         let shift = match self.alignment {
             MultiProgressAlignment::Bottom if self.lines.len() < *last_line_count => {
                 let shift = *last_line_count - self.lines.len();
                 for _ in 0..shift {
-                    term.write_line("")?;
+                    self.write_line(term, "")?;
                 }
                 shift
             }
@@ -450,25 +1542,376 @@ impl ProgressDrawState {
 
         let len = self.lines.len();
         for (idx, line) in self.lines.iter().enumerate() {
+            let line = &*self
+                .filters
+                .iter()
+                .fold(Cow::Borrowed(line.as_str()), |line, filter| {
+                    filter.apply(line)
+                });
             if idx + 1 != len {
-                term.write_line(line)?;
+                self.write_line(term, line)?;
             } else {
                 // Don't append a '\n' if this is the last line
                 term.write_str(line)?;
                 // Keep the cursor on the right terminal side
                 // So that next user writes/prints will happen on the next line
-                let line_width = console::measure_text_width(line);
+                let line_width = graphics::measure_line(line);
                 term.write_str(&" ".repeat(term.width().saturating_sub(line_width)))?;
             }
         }
 
+        if self.report_progress {
+            term.write_str(&self.progress.osc())?;
+        }
+
         term.flush()?;
         *last_line_count = self.lines.len() - self.orphan_lines + shift;
         Ok(())
     }
 
+    /// Writes the orphan lines to the log target, if any, once the progress bars are cleared
+    fn write_log(
+        &mut self,
+        term: &(impl TermLike + ?Sized),
+        last_line_count: &mut usize,
+    ) -> io::Result<()> {
+        let log = match &self.log {
+            Some(log) if self.orphan_lines > 0 => log.clone(),
+            _ => return Ok(()),
+        };
+
+        clear_last_lines(term, *last_line_count)?;
+        term.flush()?;
+        *last_line_count = 0;
+        for line in self.lines.drain(..self.orphan_lines) {
+            log.write_line(&line)?;
+        }
+        self.orphan_lines = 0;
+        log.flush()
+    }
+
+    /// Draws the lines at the bottom of the terminal, outside of a scroll region in which the
+    /// orphan lines are written.
+    fn draw_pinned(
+        &mut self,
+        term: &(impl TermLike + ?Sized),
+        last_line_count: &mut usize,
+    ) -> io::Result<()> {
+        let height = term.height();
+        let filters = &self.filters;
+        let filter = |line: &str| {
+            filters
+                .iter()
+                .fold(Cow::Borrowed(line), |line, filter| filter.apply(line))
+                .into_owned()
+        };
+
+        // Orphan lines are written at the cursor, scrolling the region above the progress bars
+        for line in &self.lines[..self.orphan_lines] {
+            self.write_line(term, &filter(line))?;
+        }
+
+        let pinned = &self.lines[self.orphan_lines..];
+        let count = Ord::min(pinned.len(), height - 1);
+        let mut out = String::new();
+        if count > *last_line_count {
+            // Make sure the cursor stays above the rows taken by the progress bars
+            let grow = count - *last_line_count;
+            let newline = match self.crlf {
+                true => "\r\n",
+                false => "\n",
+            };
+            out.push_str(&newline.repeat(grow));
+            out.push_str(&format!("\x1b[{}A", grow));
+        }
+
+        out.push_str("\x1b7");
+        if count != *last_line_count {
+            for row in height - Ord::max(count, *last_line_count) + 1..=height {
+                out.push_str(&format!("\x1b[{};1H\x1b[2K", row));
+            }
+            match count {
+                0 => out.push_str("\x1b[r"),
+                _ => out.push_str(&format!("\x1b[1;{}r", height - count)),
+            }
+        }
+        for (i, line) in pinned[..count].iter().enumerate() {
+            let row = height - count + 1 + i;
+            out.push_str(&format!("\x1b[{};1H\x1b[2K{}", row, filter(line)));
+        }
+        out.push_str("\x1b8");
+        term.write_str(&out)?;
+
+        if self.report_progress {
+            term.write_str(&self.progress.osc())?;
+        }
+
+        term.flush()?;
+        *last_line_count = count;
+        Ok(())
+    }
+
     fn reset(&mut self) {
+        // Keep the allocations of the lines for the next draw, the first spare line is the
+        // scratch buffer of the formatting and each line then reuses its predecessor
+        self.spare_lines.truncate(1);
+        let recycled = self.lines.drain(self.orphan_lines..).rev();
+        self.spare_lines.extend(recycled.map(|mut line| {
+            line.clear();
+            line
+        }));
         self.lines.clear();
         self.orphan_lines = 0;
+        self.finished_at = None;
+        self.progress = SystemProgress::None;
+    }
+}
+
+/// Progress as reported to the terminal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SystemProgress {
+    /// No progress indicator is shown
+    None,
+    /// Progress of unknown length
+    Indeterminate,
+    /// Percentage of completion
+    Percent(u8),
+}
+
+impl SystemProgress {
+    /// Combines the progress of several progress bars into their average.
+    pub(crate) fn average(progress: impl Iterator<Item = SystemProgress>) -> SystemProgress {
+        let (mut sum, mut count, mut indeterminate) = (0u64, 0u64, false);
+        for p in progress {
+            match p {
+                SystemProgress::None => {}
+                SystemProgress::Indeterminate => indeterminate = true,
+                SystemProgress::Percent(pct) => {
+                    sum += u64::from(pct);
+                    count += 1;
+                }
+            }
+        }
+
+        match (count, indeterminate) {
+            (0, false) => SystemProgress::None,
+            (0, true) => SystemProgress::Indeterminate,
+            (n, _) => SystemProgress::Percent((sum / n) as u8),
+        }
+    }
+
+    /// The `OSC 9;4` escape sequence reporting this progress
+    fn osc(self) -> String {
+        let (state, pct) = match self {
+            SystemProgress::None => (0, 0),
+            SystemProgress::Indeterminate => (3, 0),
+            SystemProgress::Percent(pct) => (1, pct),
+        };
+        format!("\x1b]9;4;{};{}\x07", state, pct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters() {
+        let line = Cow::Borrowed("\x1b[31m█▉░\x1b[0m ⠋ 🚀 done");
+        assert_eq!(Filter::StripColors.apply(line.clone()), "█▉░ ⠋ 🚀 done");
+        assert_eq!(
+            Filter::AsciiOnly.apply(line.clone()),
+            "\x1b[31m##-\x1b[0m   done"
+        );
+        assert_eq!(
+            Filter::AsciiBars.apply(line),
+            "\x1b[31m##-\x1b[0m * 🚀 done"
+        );
+        assert!(matches!(
+            Filter::StripColors.apply(Cow::Borrowed("plain")),
+            Cow::Borrowed(_)
+        ));
+
+        // Partially filled cells are rounded
+        let partial = Cow::Borrowed("[█▉▊▋▌▍▎▏░]");
+        assert_eq!(Filter::AsciiOnly.apply(partial.clone()), "[#####----]");
+        assert_eq!(Filter::AsciiBars.apply(partial), "[#####----]");
+
+        let style = crate::ProgressStyle::default_bar()
+            .template("{bar:10}")
+            .progress_chars("█▉▊▋▌▍▎▏░");
+        let mut state = ProgressState::new(100);
+        let mut lines = Vec::new();
+        for &(pos, ascii) in &[(33, "###-------"), (37, "####------")] {
+            state.pos = pos;
+            lines.clear();
+            style.format_state(&state, &mut lines, 10, Some(false));
+            assert_eq!(Filter::AsciiBars.apply(Cow::Borrowed(&lines[0])), ascii);
+        }
+    }
+
+    #[test]
+    fn hidden_draws_are_rate_limited() {
+        use crate::ProgressBar;
+
+        let (target, counter) = ProgressDrawTarget::noop_counted();
+        let pb = ProgressBar::with_draw_target(100, target);
+        pb.set_draw_delta(10);
+        for _ in 0..100 {
+            pb.inc(1);
+        }
+        assert_eq!(counter.count(), 10);
+
+        let (target, counter) = ProgressDrawTarget::noop_counted();
+        let pb = ProgressBar::with_draw_target(100, target);
+        pb.set_draw_rate(1);
+        for _ in 0..100 {
+            pb.inc(1);
+        }
+        assert_eq!(counter.count(), 1);
+    }
+
+    #[test]
+    fn log_target() {
+        use crate::term_like::tests::Recorder;
+        use crate::{ProgressBar, ProgressStyle};
+
+        let (bars, log) = (Arc::new(Recorder::default()), Arc::new(Recorder::default()));
+        let target = ProgressDrawTarget::term_like(Box::new(bars.clone()))
+            .with_log_target(Box::new(log.clone()));
+        let pb = ProgressBar::with_draw_target(10, target)
+            .with_style(ProgressStyle::default_bar().template("{pos}/{len}"));
+        pb.println("first");
+        pb.println("second");
+        pb.finish();
+
+        assert_eq!(*log.0.lock().unwrap(), ["first\n", "second\n"]);
+        let bars = bars.0.lock().unwrap();
+        assert!(!bars
+            .iter()
+            .any(|s| s.contains("first") || s.contains("second")));
+        assert!(bars.iter().any(|s| s == "10/10"));
+    }
+
+    #[test]
+    fn frame_buffer() {
+        use crate::term_like::tests::Recorder;
+
+        let term = Arc::new(Recorder::default());
+        let mut state = ProgressDrawState::new(vec!["a".into(), "b".into()], false);
+        let mut last_line_count = 2;
+        let frame = FrameBuffer::new(&term, String::new());
+        state.draw_to_term(&frame, &mut last_line_count).unwrap();
+
+        let writes = term.0.lock().unwrap();
+        assert_eq!(writes.len(), 1);
+        assert!(writes[0].starts_with("\x1b[1A\r\x1b[2K\x1b[1B\r\x1b[2K"));
+        assert!(writes[0].contains("a\nb"));
+    }
+
+    #[test]
+    fn utf8_locales() {
+        assert!(is_utf8_locale("en_US.UTF-8"));
+        assert!(is_utf8_locale("de_DE.utf8"));
+        assert!(is_utf8_locale("sr_RS.UTF-8@latin"));
+        assert!(!is_utf8_locale("C"));
+        assert!(!is_utf8_locale("POSIX"));
+        assert!(!is_utf8_locale("en_US.ISO-8859-1"));
+    }
+
+    #[test]
+    fn adaptive_refresh_rate() {
+        let mut bucket = LeakyBucket {
+            leak_rate: 20.0,
+            last_update: Instant::now(),
+            bucket: 0.0,
+            max_rate: Some(20.0),
+            backoff: None,
+            aligned: false,
+            last_frame: None,
+            pending_frame: None,
+        };
+
+        bucket.record_latency(Duration::from_millis(30));
+        assert!((bucket.leak_rate - 10.0).abs() < f64::EPSILON);
+        for _ in 0..10 {
+            bucket.record_latency(Duration::from_secs(1));
+        }
+        assert!((bucket.leak_rate - MIN_ADAPTIVE_RATE).abs() < f64::EPSILON);
+        for _ in 0..20 {
+            bucket.record_latency(Duration::from_micros(100));
+        }
+        assert!((bucket.leak_rate - 20.0).abs() < f64::EPSILON);
+
+        bucket.max_rate = None;
+        bucket.record_latency(Duration::from_secs(1));
+        assert!((bucket.leak_rate - 20.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn aligned_refresh() {
+        let epoch = frame_epoch();
+        let mut bucket = LeakyBucket {
+            leak_rate: 10.0,
+            last_update: epoch,
+            bucket: 0.0,
+            max_rate: None,
+            backoff: None,
+            aligned: true,
+            last_frame: None,
+            pending_frame: None,
+        };
+
+        let at = |millis| epoch + Duration::from_millis(millis);
+        assert!(bucket.try_add_work(at(10)));
+        assert!(!bucket.try_add_work(at(50)));
+        assert!(!bucket.try_add_work(at(99)));
+        assert!(bucket.try_add_work(at(100)));
+        assert!(bucket.try_add_work(at(350)));
+        assert!(!bucket.try_add_work(at(399)));
+    }
+
+    #[test]
+    fn aligned_refresh_defers_dropped_draws() {
+        let at = |millis| frame_epoch() + Duration::from_millis(millis);
+        let mut target =
+            ProgressDrawTarget::term(Term::buffered_stdout(), 10).with_aligned_refresh();
+        assert!(target.drawable(false, at(10)).is_some());
+        assert_eq!(target.take_pending_frame(), None);
+        assert!(target.drawable(false, at(50)).is_none());
+        assert_eq!(target.take_pending_frame(), Some(at(100)));
+        assert_eq!(target.take_pending_frame(), None);
+    }
+
+    #[test]
+    fn backoff_refresh_rate() {
+        let started = Instant::now();
+        let backoff = Backoff {
+            started,
+            max_interval: 10.0,
+        };
+
+        let rate = |secs| backoff.rate(20.0, started + Duration::from_secs(secs));
+        assert!((rate(30) - 20.0).abs() < f64::EPSILON);
+        assert!((rate(120) - 10.0).abs() < f64::EPSILON);
+        assert!((rate(3600) - 1.0 / 3.0).abs() < 1e-9);
+        assert!((rate(24 * 3600) - 0.1).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn system_progress() {
+        use SystemProgress::*;
+
+        assert_eq!(Percent(42).osc(), "\x1b]9;4;1;42\x07");
+        assert_eq!(None.osc(), "\x1b]9;4;0;0\x07");
+        assert_eq!(
+            SystemProgress::average(vec![Percent(20), None, Percent(60)].into_iter()),
+            Percent(40)
+        );
+        assert_eq!(
+            SystemProgress::average(vec![None, Indeterminate].into_iter()),
+            Indeterminate
+        );
+        assert_eq!(SystemProgress::average(vec![None].into_iter()), None);
     }
 }
@@ -0,0 +1,594 @@
+//! Built-in replacements for the parts of `console` used without the `console` feature
+//!
+//! The styles render the same escape codes as `console`, so templates look the same with and
+//! without the feature. Terminals are only detected through environment variables, see [`Term`].
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use once_cell::sync::Lazy;
+
+use crate::style::measure;
+use crate::{AnsiTerm, TermLike};
+
+/// Width assumed when it isn't given by the `COLUMNS` environment variable
+const DEFAULT_WIDTH: u16 = 80;
+
+/// A terminal on the standard output or error
+///
+/// Without the `console` feature, the terminal isn't queried. Its size is read from the
+/// `COLUMNS` and `LINES` environment variables (80 columns and an unknown height by default),
+/// and the stream is considered a terminal if `TERM` is set to anything but `dumb`, so progress
+/// bars are hidden when that's not the case.
+pub struct Term {
+    inner: AnsiTerm<Box<dyn Write + Send>>,
+    height: u16,
+}
+
+impl Term {
+    fn new(writer: Box<dyn Write + Send>) -> Term {
+        let width = env_size("COLUMNS").unwrap_or(DEFAULT_WIDTH);
+        Term {
+            inner: AnsiTerm::new(writer, usize::from(width)),
+            height: env_size("LINES").unwrap_or(0),
+        }
+    }
+
+    /// Returns a terminal writing to the standard output
+    pub fn stdout() -> Term {
+        Term::new(Box::new(io::stdout()))
+    }
+
+    /// Returns a terminal writing to the standard error
+    pub fn stderr() -> Term {
+        Term::new(Box::new(io::stderr()))
+    }
+
+    /// Returns a terminal writing to the standard output, buffered until flushed
+    ///
+    /// Output is always buffered, this is the same as [`Term::stdout()`].
+    pub fn buffered_stdout() -> Term {
+        Term::stdout()
+    }
+
+    /// Returns a terminal writing to the standard error, buffered until flushed
+    ///
+    /// Output is always buffered, this is the same as [`Term::stderr()`].
+    pub fn buffered_stderr() -> Term {
+        Term::stderr()
+    }
+
+    /// Returns whether the output is considered a terminal, see [`Term`]
+    pub fn is_term(&self) -> bool {
+        is_term()
+    }
+
+    /// Returns the number of rows and columns of the terminal, 0 rows if it isn't known
+    pub fn size(&self) -> (u16, u16) {
+        (self.height, self.inner.width() as u16)
+    }
+}
+
+impl fmt::Debug for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Term").field("size", &self.size()).finish()
+    }
+}
+
+impl TermLike for Term {
+    fn width(&self) -> usize {
+        self.inner.width()
+    }
+
+    fn height(&self) -> usize {
+        usize::from(self.height)
+    }
+
+    fn move_cursor_up(&self, n: usize) -> io::Result<()> {
+        self.inner.move_cursor_up(n)
+    }
+
+    fn move_cursor_down(&self, n: usize) -> io::Result<()> {
+        self.inner.move_cursor_down(n)
+    }
+
+    fn move_cursor_right(&self, n: usize) -> io::Result<()> {
+        self.inner.move_cursor_right(n)
+    }
+
+    fn move_cursor_left(&self, n: usize) -> io::Result<()> {
+        self.inner.move_cursor_left(n)
+    }
+
+    fn write_line(&self, s: &str) -> io::Result<()> {
+        self.inner.write_line(s)
+    }
+
+    fn write_str(&self, s: &str) -> io::Result<()> {
+        self.inner.write_str(s)
+    }
+
+    fn clear_line(&self) -> io::Result<()> {
+        self.inner.clear_line()
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn env_size(name: &str) -> Option<u16> {
+    env::var(name).ok()?.parse().ok().filter(|&n| n > 0)
+}
+
+fn is_term() -> bool {
+    matches!(env::var("TERM"), Ok(term) if !term.is_empty() && term != "dumb")
+}
+
+static COLORS: Lazy<AtomicBool> = Lazy::new(|| {
+    let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+    let enabled = is_term()
+        && var("NO_COLOR").is_none()
+        && var("CLICOLOR").map_or(true, |value| value != "0");
+    AtomicBool::new(enabled || var("CLICOLOR_FORCE").map_or(false, |value| value != "0"))
+});
+
+/// Returns whether styles emit colors unless forced, following the `CLICOLOR`, `CLICOLOR_FORCE`
+/// and `NO_COLOR` conventions
+pub(crate) fn colors_enabled() -> bool {
+    COLORS.load(Ordering::Relaxed)
+}
+
+/// Forces colors on or off for styles that aren't forced themselves
+#[cfg(test)]
+pub(crate) fn set_colors_enabled(enabled: bool) {
+    COLORS.store(enabled, Ordering::Relaxed)
+}
+
+/// A terminal color
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    /// One of the 256 colors of the extended palette
+    Color256(u8),
+}
+
+impl Color {
+    fn ansi_num(self) -> usize {
+        match self {
+            Color::Black => 0,
+            Color::Red => 1,
+            Color::Green => 2,
+            Color::Yellow => 3,
+            Color::Blue => 4,
+            Color::Magenta => 5,
+            Color::Cyan => 6,
+            Color::White => 7,
+            Color::Color256(x) => x as usize,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd)]
+enum Attribute {
+    Bold,
+    Dim,
+    Italic,
+    Underlined,
+    Blink,
+    BlinkFast,
+    Reverse,
+    Hidden,
+    StrikeThrough,
+}
+
+impl Attribute {
+    fn ansi_num(self) -> usize {
+        match self {
+            Attribute::Bold => 1,
+            Attribute::Dim => 2,
+            Attribute::Italic => 3,
+            Attribute::Underlined => 4,
+            Attribute::Blink => 5,
+            Attribute::BlinkFast => 6,
+            Attribute::Reverse => 7,
+            Attribute::Hidden => 8,
+            Attribute::StrikeThrough => 9,
+        }
+    }
+}
+
+/// A style that can be applied to text, see [`Style::from_dotted_str()`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    fg_bright: bool,
+    bg_bright: bool,
+    attrs: BTreeSet<Attribute>,
+    force: Option<bool>,
+}
+
+macro_rules! style_methods {
+    ($($name:ident => $method:ident($($arg:expr)?),)*) => {
+        $(
+            pub fn $name(self) -> Self {
+                self.$method($($arg)?)
+            }
+        )*
+    };
+}
+
+impl Style {
+    /// Returns a style without colors or attributes
+    pub fn new() -> Style {
+        Style::default()
+    }
+
+    /// Creates a style from a dotted string
+    ///
+    /// The string is split at each dot and the terms in between are applied, e.g. `red.on_blue`
+    /// is red on a blue background. `9.on_12` is the same with 256 color numbers. Unknown terms
+    /// are ignored.
+    pub fn from_dotted_str(s: &str) -> Style {
+        s.split('.').fold(Style::new(), |style, part| match part {
+            "black" => style.black(),
+            "red" => style.red(),
+            "green" => style.green(),
+            "yellow" => style.yellow(),
+            "blue" => style.blue(),
+            "magenta" => style.magenta(),
+            "cyan" => style.cyan(),
+            "white" => style.white(),
+            "bright" => style.bright(),
+            "on_black" => style.on_black(),
+            "on_red" => style.on_red(),
+            "on_green" => style.on_green(),
+            "on_yellow" => style.on_yellow(),
+            "on_blue" => style.on_blue(),
+            "on_magenta" => style.on_magenta(),
+            "on_cyan" => style.on_cyan(),
+            "on_white" => style.on_white(),
+            "on_bright" => style.on_bright(),
+            "bold" => style.bold(),
+            "dim" => style.dim(),
+            "italic" => style.italic(),
+            "underlined" => style.underlined(),
+            "blink" => style.blink(),
+            "blink_fast" => style.blink_fast(),
+            "reverse" => style.reverse(),
+            "hidden" => style.hidden(),
+            "strikethrough" => style.strikethrough(),
+            part => match part.strip_prefix("on_") {
+                Some(n) => match n.parse() {
+                    Ok(n) => style.on_color256(n),
+                    Err(_) => style,
+                },
+                None => match part.parse() {
+                    Ok(n) => style.color256(n),
+                    Err(_) => style,
+                },
+            },
+        })
+    }
+
+    /// Applies the style to something that can be displayed
+    pub fn apply_to<D>(&self, val: D) -> StyledObject<D> {
+        StyledObject {
+            style: self.clone(),
+            val,
+        }
+    }
+
+    /// Forces styling on or off, instead of following the environment
+    pub fn force_styling(mut self, value: bool) -> Style {
+        self.force = Some(value);
+        self
+    }
+
+    /// Sets the foreground color
+    pub fn fg(mut self, color: Color) -> Style {
+        self.fg = Some(color);
+        self
+    }
+
+    /// Sets the background color
+    pub fn bg(mut self, color: Color) -> Style {
+        self.bg = Some(color);
+        self
+    }
+
+    /// Sets the foreground color to one of the 256 colors of the extended palette
+    pub fn color256(self, color: u8) -> Style {
+        self.fg(Color::Color256(color))
+    }
+
+    /// Sets the background color to one of the 256 colors of the extended palette
+    pub fn on_color256(self, color: u8) -> Style {
+        self.bg(Color::Color256(color))
+    }
+
+    /// Uses the bright variant of the foreground color
+    pub fn bright(mut self) -> Style {
+        self.fg_bright = true;
+        self
+    }
+
+    /// Uses the bright variant of the background color
+    pub fn on_bright(mut self) -> Style {
+        self.bg_bright = true;
+        self
+    }
+
+    fn attr(mut self, attr: Attribute) -> Style {
+        self.attrs.insert(attr);
+        self
+    }
+
+    style_methods! {
+        black => fg(Color::Black),
+        red => fg(Color::Red),
+        green => fg(Color::Green),
+        yellow => fg(Color::Yellow),
+        blue => fg(Color::Blue),
+        magenta => fg(Color::Magenta),
+        cyan => fg(Color::Cyan),
+        white => fg(Color::White),
+        on_black => bg(Color::Black),
+        on_red => bg(Color::Red),
+        on_green => bg(Color::Green),
+        on_yellow => bg(Color::Yellow),
+        on_blue => bg(Color::Blue),
+        on_magenta => bg(Color::Magenta),
+        on_cyan => bg(Color::Cyan),
+        on_white => bg(Color::White),
+        bold => attr(Attribute::Bold),
+        dim => attr(Attribute::Dim),
+        italic => attr(Attribute::Italic),
+        underlined => attr(Attribute::Underlined),
+        blink => attr(Attribute::Blink),
+        blink_fast => attr(Attribute::BlinkFast),
+        reverse => attr(Attribute::Reverse),
+        hidden => attr(Attribute::Hidden),
+        strikethrough => attr(Attribute::StrikeThrough),
+    }
+
+    fn write_start(&self, f: &mut fmt::Formatter<'_>) -> Result<bool, fmt::Error> {
+        if !self.force.unwrap_or_else(colors_enabled) {
+            return Ok(false);
+        }
+
+        let mut styled = false;
+        if let Some(fg) = self.fg {
+            match (fg, self.fg_bright) {
+                (Color::Color256(_), _) => write!(f, "\x1b[38;5;{}m", fg.ansi_num())?,
+                (_, true) => write!(f, "\x1b[38;5;{}m", fg.ansi_num() + 8)?,
+                (_, false) => write!(f, "\x1b[{}m", fg.ansi_num() + 30)?,
+            }
+            styled = true;
+        }
+        if let Some(bg) = self.bg {
+            match (bg, self.bg_bright) {
+                (Color::Color256(_), _) => write!(f, "\x1b[48;5;{}m", bg.ansi_num())?,
+                (_, true) => write!(f, "\x1b[48;5;{}m", bg.ansi_num() + 8)?,
+                (_, false) => write!(f, "\x1b[{}m", bg.ansi_num() + 40)?,
+            }
+            styled = true;
+        }
+        for attr in &self.attrs {
+            write!(f, "\x1b[{}m", attr.ansi_num())?;
+            styled = true;
+        }
+        Ok(styled)
+    }
+}
+
+/// A value displayed with a [`Style`]
+#[derive(Clone)]
+pub struct StyledObject<D> {
+    style: Style,
+    val: D,
+}
+
+macro_rules! styled_object_methods {
+    ($($name:ident,)*) => {
+        $(
+            pub fn $name(mut self) -> StyledObject<D> {
+                self.style = self.style.$name();
+                self
+            }
+        )*
+    };
+}
+
+impl<D> StyledObject<D> {
+    /// Forces styling on or off, instead of following the environment
+    pub fn force_styling(mut self, value: bool) -> StyledObject<D> {
+        self.style = self.style.force_styling(value);
+        self
+    }
+
+    /// Sets the foreground color
+    pub fn fg(mut self, color: Color) -> StyledObject<D> {
+        self.style = self.style.fg(color);
+        self
+    }
+
+    /// Sets the background color
+    pub fn bg(mut self, color: Color) -> StyledObject<D> {
+        self.style = self.style.bg(color);
+        self
+    }
+
+    styled_object_methods! {
+        black, red, green, yellow, blue, magenta, cyan, white, bright,
+        on_black, on_red, on_green, on_yellow, on_blue, on_magenta, on_cyan, on_white, on_bright,
+        bold, dim, italic, underlined, blink, blink_fast, reverse, hidden, strikethrough,
+    }
+}
+
+impl<D: fmt::Display> fmt::Display for StyledObject<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let styled = self.style.write_start(f)?;
+        self.val.fmt(f)?;
+        match styled {
+            true => f.write_str("\x1b[0m"),
+            false => Ok(()),
+        }
+    }
+}
+
+impl<D: fmt::Debug> fmt::Debug for StyledObject<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let styled = self.style.write_start(f)?;
+        self.val.fmt(f)?;
+        match styled {
+            true => f.write_str("\x1b[0m"),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Returns the length of the escape sequence `s` starts with, 0 if it doesn't start with one
+///
+/// Recognizes CSI sequences (`ESC [`, e.g. colors and cursor movements), string sequences like
+/// OSC (`ESC ]`) and DCS (`ESC P`, e.g. sixel images) terminated by `BEL` or `ESC \\`, and two
+/// character escapes like `ESC 7`.
+fn escape_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    if bytes.first() != Some(&b'\x1b') {
+        return 0;
+    }
+
+    match bytes.get(1) {
+        Some(b'[') => match bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+            Some(end) => end + 3,
+            None => 0,
+        },
+        Some(b']') | Some(b'P') | Some(b'X') | Some(b'^') | Some(b'_') => {
+            let rest = &s[2..];
+            match (rest.find('\x07'), rest.find("\x1b\\")) {
+                (Some(bel), Some(st)) if st < bel => st + 4,
+                (Some(bel), _) => bel + 3,
+                (None, Some(st)) => st + 4,
+                (None, None) => 0,
+            }
+        }
+        Some(c) if (0x30..=0x7e).contains(c) => 2,
+        _ => 0,
+    }
+}
+
+/// Iterates over the parts of a string, telling whether each one is an escape sequence
+pub(crate) struct AnsiCodeIterator<'a> {
+    s: &'a str,
+}
+
+impl<'a> AnsiCodeIterator<'a> {
+    pub(crate) fn new(s: &'a str) -> AnsiCodeIterator<'a> {
+        AnsiCodeIterator { s }
+    }
+}
+
+impl<'a> Iterator for AnsiCodeIterator<'a> {
+    type Item = (&'a str, bool);
+
+    fn next(&mut self) -> Option<(&'a str, bool)> {
+        if self.s.is_empty() {
+            return None;
+        }
+
+        let (len, is_ansi) = match escape_len(self.s) {
+            0 => {
+                let first = self.s.chars().next().map_or(0, char::len_utf8);
+                let next = self.s[first..]
+                    .match_indices('\x1b')
+                    .map(|(idx, _)| idx + first)
+                    .find(|&idx| escape_len(&self.s[idx..]) > 0);
+                (next.unwrap_or(self.s.len()), false)
+            }
+            len => (len, true),
+        };
+
+        let (part, rest) = self.s.split_at(len);
+        self.s = rest;
+        Some((part, is_ansi))
+    }
+}
+
+/// Removes the escape sequences from `s`
+pub(crate) fn strip_ansi_codes(s: &str) -> Cow<'_, str> {
+    let mut parts = AnsiCodeIterator::new(s);
+    match parts.any(|(_, is_ansi)| is_ansi) {
+        true => AnsiCodeIterator::new(s)
+            .filter(|(_, is_ansi)| !is_ansi)
+            .map(|(part, _)| part)
+            .collect::<String>()
+            .into(),
+        false => Cow::Borrowed(s),
+    }
+}
+
+/// Returns the number of columns `s` takes up, ignoring escape sequences
+pub(crate) fn measure_text_width(s: &str) -> usize {
+    measure(&strip_ansi_codes(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dotted_styles() {
+        let style = Style::from_dotted_str("red.on_blue.bold.unknown").force_styling(true);
+        assert_eq!(
+            style.apply_to("x").to_string(),
+            "\x1b[31m\x1b[44m\x1b[1mx\x1b[0m"
+        );
+
+        let style = Style::from_dotted_str("9.on_12.bright").force_styling(true);
+        assert_eq!(
+            style.apply_to("x").to_string(),
+            "\x1b[38;5;9m\x1b[48;5;12mx\x1b[0m"
+        );
+
+        let style = Style::from_dotted_str("green").force_styling(false);
+        assert_eq!(style.apply_to("x").to_string(), "x");
+    }
+
+    #[test]
+    fn escape_codes() {
+        let s = "\x1b[1;31mred\x1b[0m \x1b]8;;url\x1b\\link\x1b]8;;\x07 \x1b7plain";
+        let parts = AnsiCodeIterator::new(s).collect::<Vec<_>>();
+        assert_eq!(
+            parts,
+            [
+                ("\x1b[1;31m", true),
+                ("red", false),
+                ("\x1b[0m", true),
+                (" ", false),
+                ("\x1b]8;;url\x1b\\", true),
+                ("link", false),
+                ("\x1b]8;;\x07", true),
+                (" ", false),
+                ("\x1b7", true),
+                ("plain", false),
+            ]
+        );
+
+        assert_eq!(strip_ansi_codes(s), "red link plain");
+        assert_eq!(measure_text_width(s), 14);
+        assert_eq!(strip_ansi_codes("\x1b[1;3"), "\x1b[1;3");
+    }
+}
@@ -3,6 +3,8 @@ use std::time::Duration;
 
 use number_prefix::NumberPrefix;
 
+use crate::time::{SystemTime, UNIX_EPOCH};
+
 const SECOND: Duration = Duration::from_secs(1);
 const MINUTE: Duration = Duration::from_secs(60);
 const HOUR: Duration = Duration::from_secs(60 * 60);
@@ -18,6 +20,13 @@ pub struct FormattedDuration(pub Duration);
 #[derive(Debug)]
 pub struct HumanDuration(pub Duration);
 
+/// Wraps an std duration for formatting with a precision adapted to its magnitude.
+///
+/// Long durations are shown in hours and minutes, shorter ones in minutes and seconds, and the
+/// final seconds in tenths of a second, e.g. `1h 05m`, `4m 07s`, `42s` and `3.4s`.
+#[derive(Debug)]
+pub struct AdaptiveDuration(pub Duration);
+
 /// Formats bytes for human readability
 #[derive(Debug)]
 pub struct HumanBytes(pub u64);
@@ -34,6 +43,13 @@ pub struct BinaryBytes(pub u64);
 #[derive(Debug)]
 pub struct HumanCount(pub u64);
 
+/// Formats numbers compactly using SI prefixes (`1.2k`, `3.4M`, `5.6G`)
+///
+/// The number is shown with one decimal by default, a precision given in the format string
+/// (e.g. `{:.2}`) takes precedence.
+#[derive(Debug)]
+pub struct SiNumber(pub f64);
+
 impl fmt::Display for FormattedDuration {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut t = self.0.as_secs();
@@ -52,6 +68,21 @@ impl fmt::Display for FormattedDuration {
     }
 }
 
+impl fmt::Display for AdaptiveDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        if self.0 >= HOUR {
+            write!(f, "{}h {:02}m", secs / 3600, secs / 60 % 60)
+        } else if self.0 >= MINUTE {
+            write!(f, "{}m {:02}s", secs / 60, secs % 60)
+        } else if self.0 >= 10 * SECOND {
+            write!(f, "{}s", secs)
+        } else {
+            write!(f, "{}.{}s", secs, self.0.subsec_millis() / 100)
+        }
+    }
+}
+
 // `HumanDuration` should be as intuitively understandable as possible.
 // So we want to round, not truncate: otherwise 1 hour and 59 minutes
 // would display an ETA of "1 hour" which underestimates the time
@@ -69,22 +100,8 @@ impl fmt::Display for FormattedDuration {
 
 impl fmt::Display for HumanDuration {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut idx = 0;
-        for (i, &(cur, _, _)) in UNITS.iter().enumerate() {
-            idx = i;
-            match UNITS.get(i + 1) {
-                Some(&next) if self.0 + next.0 / 2 >= cur + cur / 2 => break,
-                _ => continue,
-            }
-        }
-
-        let (unit, name, alt) = UNITS[idx];
-        // FIXME when `div_duration_f64` is stable
-        let mut t = (self.0.as_secs_f64() / unit.as_secs_f64()).round() as usize;
-        if idx < UNITS.len() - 1 {
-            t = Ord::max(t, 2);
-        }
-
+        let (t, idx) = human_units(self.0);
+        let (_, name, alt) = UNITS[idx];
         match (f.alternate(), t) {
             (true, _) => write!(f, "{}{}", t, alt),
             (false, 1) => write!(f, "{} {}", t, name),
@@ -93,6 +110,31 @@ impl fmt::Display for HumanDuration {
     }
 }
 
+/// Returns the rounded number of units of `d`, and the index of the unit in `UNITS`
+fn human_units(d: Duration) -> (usize, usize) {
+    let mut idx = 0;
+    for (i, &(cur, _, _)) in UNITS.iter().enumerate() {
+        idx = i;
+        match UNITS.get(i + 1) {
+            Some(&next)
+                if d.checked_add(next.0 / 2)
+                    .map_or(true, |d| d >= cur + cur / 2) =>
+            {
+                break
+            }
+            _ => continue,
+        }
+    }
+
+    let unit = UNITS[idx].0;
+    // FIXME when `div_duration_f64` is stable
+    let mut t = (d.as_secs_f64() / unit.as_secs_f64()).round() as usize;
+    if idx < UNITS.len() - 1 {
+        t = Ord::max(t, 2);
+    }
+    (t, idx)
+}
+
 const UNITS: &[(Duration, &str, &str)] = &[
     (YEAR, "year", "y"),
     (WEEK, "week", "w"),
@@ -102,6 +144,205 @@ const UNITS: &[(Duration, &str, &str)] = &[
     (SECOND, "second", "s"),
 ];
 
+/// Names of a unit of time: singular, plural and abbreviation
+pub type UnitNames = (&'static str, &'static str, &'static str);
+
+/// Time zone of the clock time keys like `{end_time_estimate}`, see [`ProgressStyle::clock()`]
+///
+/// [`ProgressStyle::clock()`]: crate::ProgressStyle::clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clock {
+    /// The local time zone of the system, falls back to UTC on platforms other than Unix and
+    /// without the `local-time` feature
+    Local,
+    /// Coordinated Universal Time
+    Utc,
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::Local
+    }
+}
+
+/// Formats a point in time as the time of day, like `14:35:02`
+pub(crate) struct ClockTime(pub(crate) SystemTime, pub(crate) Clock);
+
+impl fmt::Display for ClockTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = match self.0.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_secs() as i64,
+            Err(err) => -(err.duration().as_secs() as i64),
+        };
+        let local = match self.1 {
+            Clock::Local => local_seconds_of_day(secs),
+            Clock::Utc => None,
+        };
+        let t = local.unwrap_or_else(|| secs.rem_euclid(24 * 60 * 60));
+        write!(f, "{:02}:{:02}:{:02}", t / 3600, t / 60 % 60, t % 60)
+    }
+}
+
+/// Returns the seconds since midnight in the local time zone at `secs` since the epoch
+#[cfg(all(unix, feature = "local-time"))]
+fn local_seconds_of_day(secs: i64) -> Option<i64> {
+    let time = secs as libc::time_t;
+    let mut tm = std::mem::MaybeUninit::uninit();
+    // SAFETY: `localtime_r` initializes `tm` if it succeeds
+    let tm = unsafe {
+        if libc::localtime_r(&time, tm.as_mut_ptr()).is_null() {
+            return None;
+        }
+        tm.assume_init()
+    };
+    Some(i64::from(tm.tm_hour) * 3600 + i64::from(tm.tm_min) * 60 + i64::from(tm.tm_sec))
+}
+
+#[cfg(not(all(unix, feature = "local-time")))]
+fn local_seconds_of_day(_: i64) -> Option<i64> {
+    None
+}
+
+/// Conventions for formatting numbers and durations, see [`ProgressStyle::locale()`]
+///
+/// The locale affects the decimal separator and the digit grouping of numbers, and the names of
+/// units in human readable durations, like those of the `{eta}` and `{elapsed}` keys.
+///
+/// ```rust
+/// # use indicatif::{Locale, ProgressStyle};
+/// let style = ProgressStyle::default_bar()
+///     .template("{human_pos}/{human_len} ({eta})")
+///     .locale(Locale::german().long_durations(true));
+/// ```
+///
+/// [`ProgressStyle::locale()`]: crate::ProgressStyle::locale
+#[derive(Clone, Debug)]
+pub struct Locale {
+    decimal_separator: char,
+    group_separator: char,
+    /// Names of years, weeks, days, hours, minutes and seconds
+    units: [UnitNames; 6],
+    long_durations: bool,
+}
+
+impl Locale {
+    /// English conventions, as used without a locale: `1,234.5` and `2 minutes`
+    pub fn english() -> Locale {
+        Locale {
+            decimal_separator: '.',
+            group_separator: ',',
+            units: [
+                ("year", "years", "y"),
+                ("week", "weeks", "w"),
+                ("day", "days", "d"),
+                ("hour", "hours", "h"),
+                ("minute", "minutes", "m"),
+                ("second", "seconds", "s"),
+            ],
+            long_durations: false,
+        }
+    }
+
+    /// German conventions: `1.234,5` and `2 Minuten`
+    pub fn german() -> Locale {
+        Locale {
+            decimal_separator: ',',
+            group_separator: '.',
+            units: [
+                ("Jahr", "Jahre", "J"),
+                ("Woche", "Wochen", "W"),
+                ("Tag", "Tage", "T"),
+                ("Stunde", "Stunden", "h"),
+                ("Minute", "Minuten", "min"),
+                ("Sekunde", "Sekunden", "s"),
+            ],
+            long_durations: false,
+        }
+    }
+
+    /// French conventions: `1 234,5` (with a narrow no-break space) and `2 minutes`
+    pub fn french() -> Locale {
+        Locale {
+            decimal_separator: ',',
+            group_separator: '\u{202f}',
+            units: [
+                ("an", "ans", "a"),
+                ("semaine", "semaines", "sem"),
+                ("jour", "jours", "j"),
+                ("heure", "heures", "h"),
+                ("minute", "minutes", "min"),
+                ("seconde", "secondes", "s"),
+            ],
+            long_durations: false,
+        }
+    }
+
+    /// Sets the character separating the integer part of numbers from the fractional part
+    pub fn decimal_separator(mut self, separator: char) -> Locale {
+        self.decimal_separator = separator;
+        self
+    }
+
+    /// Sets the character separating groups of three digits in large numbers
+    pub fn group_separator(mut self, separator: char) -> Locale {
+        self.group_separator = separator;
+        self
+    }
+
+    /// Sets the names of years, weeks, days, hours, minutes and seconds, in that order
+    pub fn duration_units(mut self, units: [UnitNames; 6]) -> Locale {
+        self.units = units;
+        self
+    }
+
+    /// Writes durations with words, like `2 minutes`, instead of abbreviations like `2m`
+    pub fn long_durations(mut self, long: bool) -> Locale {
+        self.long_durations = long;
+        self
+    }
+
+    /// Replaces the decimal and group separators of a number formatted in English in `buf`
+    pub(crate) fn localize_number(&self, buf: &mut String) {
+        if !buf.contains(&['.', ','][..]) {
+            return;
+        }
+        *buf = buf
+            .chars()
+            .map(|c| match c {
+                '.' => self.decimal_separator,
+                ',' => self.group_separator,
+                c => c,
+            })
+            .collect();
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Locale {
+        Locale::english()
+    }
+}
+
+/// Formats a [`HumanDuration`] as abbreviation, or with the conventions of a locale
+pub(crate) struct LocalizedDuration<'a>(pub(crate) Duration, pub(crate) Option<&'a Locale>);
+
+impl fmt::Display for LocalizedDuration<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let locale = match self.1 {
+            Some(locale) => locale,
+            None => return write!(f, "{:#}", HumanDuration(self.0)),
+        };
+
+        let (t, idx) = human_units(self.0);
+        let (name, plural, alt) = locale.units[idx];
+        match (locale.long_durations, t) {
+            (false, _) => write!(f, "{}{}", t, alt),
+            (true, 1) => write!(f, "{} {}", t, name),
+            (true, _) => write!(f, "{} {}", t, plural),
+        }
+    }
+}
+
 impl fmt::Display for HumanBytes {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match NumberPrefix::binary(self.0 as f64) {
@@ -146,12 +387,41 @@ impl fmt::Display for HumanCount {
     }
 }
 
+impl fmt::Display for SiNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(1);
+        match NumberPrefix::decimal(self.0) {
+            NumberPrefix::Standalone(number) if number.fract() == 0.0 => {
+                write!(f, "{:.0}", number)
+            }
+            NumberPrefix::Standalone(number) => write!(f, "{:.*}", precision, number),
+            NumberPrefix::Prefixed(prefix, number) => {
+                write!(f, "{:.*}{}", precision, number, prefix)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     const MILLI: Duration = Duration::from_millis(1);
 
+    #[test]
+    fn adaptive_duration() {
+        let cases = [
+            (2 * HOUR + 5 * MINUTE + 7 * SECOND, "2h 05m"),
+            (4 * MINUTE + 7 * SECOND, "4m 07s"),
+            (42 * SECOND + MILLI * 900, "42s"),
+            (3 * SECOND + MILLI * 450, "3.4s"),
+            (MILLI * 50, "0.0s"),
+        ];
+        for (duration, expected) in cases.iter() {
+            assert_eq!(AdaptiveDuration(*duration).to_string(), *expected);
+        }
+    }
+
     #[test]
     fn human_duration_alternate() {
         for (unit, _, alt) in UNITS {
@@ -275,6 +545,47 @@ mod tests {
         assert_eq!("3 years", format!("{}", HumanDuration(3 * YEAR)));
     }
 
+    #[test]
+    fn si_number() {
+        assert_eq!("42", format!("{}", SiNumber(42.0)));
+        assert_eq!("2.5", format!("{}", SiNumber(2.5)));
+        assert_eq!("1.2k", format!("{}", SiNumber(1234.0)));
+        assert_eq!("3.46M", format!("{:.2}", SiNumber(3_456_789.0)));
+        assert_eq!("5.6G", format!("{}", SiNumber(5.6e9)));
+    }
+
+    #[test]
+    fn locale() {
+        let locale = Locale::german();
+        let mut buf = HumanCount(1234567).to_string();
+        locale.localize_number(&mut buf);
+        assert_eq!(buf, "1.234.567");
+        let mut buf = HumanBytes(1536).to_string();
+        locale.localize_number(&mut buf);
+        assert_eq!(buf, "1,50 KiB");
+
+        fn duration(d: Duration, locale: &Locale) -> String {
+            LocalizedDuration(d, Some(locale)).to_string()
+        }
+        assert_eq!(duration(2 * MINUTE, &locale), "2min");
+        let locale = locale.long_durations(true);
+        assert_eq!(duration(2 * MINUTE, &locale), "2 Minuten");
+        assert_eq!(duration(SECOND, &locale), "1 Sekunde");
+        assert_eq!(
+            duration(3 * HOUR, &Locale::english()),
+            format!("{:#}", HumanDuration(3 * HOUR))
+        );
+    }
+
+    #[test]
+    fn clock_time() {
+        let time = UNIX_EPOCH + Duration::from_secs(3 * 24 * 60 * 60 + 14 * 60 * 60 + 35 * 60 + 2);
+        assert_eq!(format!("{}", ClockTime(time, Clock::Utc)), "14:35:02");
+        let before = UNIX_EPOCH - Duration::from_secs(60);
+        assert_eq!(format!("{}", ClockTime(before, Clock::Utc)), "23:59:00");
+        assert_eq!(format!("{}", ClockTime(time, Clock::Local)).len(), 8);
+    }
+
     #[test]
     fn human_count() {
         assert_eq!("42", format!("{}", HumanCount(42)));
@@ -0,0 +1,336 @@
+use std::env;
+use std::fmt;
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::compat::{self, Color};
+
+/// Draws progress bars with terminal graphics instead of text
+///
+/// Set with [`ProgressDrawTarget::with_bar_graphics()`], the `{bar}` and `{wide_bar}` of the
+/// styles are laid out as text first, then the cells covered by each bar are handed to
+/// [`BarGraphics::render()`]. If the terminal doesn't support the graphics, or `render()` returns
+/// `None`, the text bar is drawn.
+///
+/// Bars showing chunks or overflow are always drawn as text.
+///
+/// [`ProgressDrawTarget::with_bar_graphics()`]: crate::ProgressDrawTarget::with_bar_graphics
+pub trait BarGraphics: Send + Sync + fmt::Debug {
+    /// Returns whether the terminal supports the graphics
+    fn is_supported(&self) -> bool;
+
+    /// Returns the escape sequences drawing `bar`, or `None` to draw the text bar
+    ///
+    /// The output replaces `bar.cells` columns of text, it must leave the cursor right after
+    /// them, on the same line.
+    fn render(&self, bar: &BarCells) -> Option<String>;
+}
+
+/// A bar to draw with [`BarGraphics`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct BarCells {
+    /// Completion shown by the bar, between 0 and 1
+    pub fraction: f32,
+    /// Number of columns covered by the bar
+    pub cells: usize,
+    /// Colors of the filled and the empty part of the bar, see
+    /// [`ProgressStyle::graphics_colors()`]
+    ///
+    /// [`ProgressStyle::graphics_colors()`]: crate::ProgressStyle::graphics_colors
+    pub colors: Option<(Color, Color)>,
+}
+
+/// Replaces the `bars` of `lines`, given as a line index and a byte range, with `graphics`
+///
+/// The ranges of a line must be sorted and not overlap.
+pub(crate) fn apply(
+    lines: &mut [String],
+    bars: &[(usize, Range<usize>)],
+    graphics: &dyn BarGraphics,
+    fraction: f32,
+    colors: Option<(Color, Color)>,
+) {
+    // Going backwards keeps the ranges left to replace valid
+    for (line, range) in bars.iter().rev() {
+        let line = &mut lines[*line];
+        let bar = BarCells {
+            fraction,
+            cells: compat::measure_text_width(&line[range.clone()]),
+            colors,
+        };
+        if let Some(rendered) = graphics.render(&bar) {
+            line.replace_range(range.clone(), &rendered);
+        }
+    }
+}
+
+/// Measures the visible width of `line` like the text of the styles, ignoring sixel images and counting
+/// the columns skipped by cursor forward sequences
+pub(crate) fn measure_line(line: &str) -> usize {
+    let mut width = 0;
+    let mut rest = line;
+    while let Some(start) = rest.find("\x1bP") {
+        width += measure_text(&rest[..start]);
+        rest = match rest[start..].find("\x1b\\") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+    width + measure_text(rest)
+}
+
+fn measure_text(text: &str) -> usize {
+    let skipped = text
+        .split("\x1b[")
+        .skip(1)
+        .filter_map(|csi| csi.split('C').next()?.parse::<usize>().ok())
+        .sum::<usize>();
+    compat::measure_text_width(text) + skipped
+}
+
+/// Draws bars as sixel images, with a smooth gradient and sub-cell precision
+///
+/// Sixel support is detected from the environment: Windows Terminal, WezTerm, foot, mlterm and
+/// terminals advertising it in `TERM` are assumed to support it. Use [`SixelBar::force()`] to
+/// override the detection.
+///
+/// ```rust,no_run
+/// # use indicatif::{Color, ProgressBar, ProgressDrawTarget, ProgressStyle, SixelBar};
+/// let target = ProgressDrawTarget::stderr().with_bar_graphics(SixelBar::new());
+/// let pb = ProgressBar::with_draw_target(100, target).with_style(
+///     ProgressStyle::default_bar().graphics_colors(Color::Cyan, Color::Color256(238)),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct SixelBar {
+    supported: bool,
+    cell_size: (usize, usize),
+}
+
+impl SixelBar {
+    /// Creates a sixel renderer for cells of 10x20 pixels
+    pub fn new() -> SixelBar {
+        SixelBar {
+            supported: detect_sixel(),
+            cell_size: (10, 20),
+        }
+    }
+
+    /// Overrides the detection of sixel support
+    pub fn force(mut self, supported: bool) -> SixelBar {
+        self.supported = supported;
+        self
+    }
+
+    /// Sets the size of a terminal cell in pixels
+    pub fn cell_size(mut self, width: usize, height: usize) -> SixelBar {
+        self.cell_size = (width.max(1), height.max(6));
+        self
+    }
+}
+
+impl Default for SixelBar {
+    fn default() -> SixelBar {
+        SixelBar::new()
+    }
+}
+
+impl BarGraphics for SixelBar {
+    fn is_supported(&self) -> bool {
+        self.supported
+    }
+
+    fn render(&self, bar: &BarCells) -> Option<String> {
+        if !self.supported || bar.cells == 0 {
+            return None;
+        }
+
+        let (filled, empty) = bar.colors.unwrap_or((Color::Green, Color::Color256(238)));
+        let (filled, empty) = (rgb(filled), rgb(empty));
+        let width = bar.cells * self.cell_size.0;
+        let filled_px = (width as f32 * bar.fraction.clamp(0.0, 1.0)).round() as usize;
+        // Leave some space above and below the bar, in whole sixel bands
+        let bands = (self.cell_size.1 / 6 / 2).max(1);
+
+        // The gradient goes from a darker shade to the filled color in `STEPS` registers
+        const STEPS: usize = 16;
+        let mut out = String::new();
+        out.push_str("\x1b7\x1bP0;1q");
+        write!(out, "\"1;1;{};{}", width, bands * 6).unwrap();
+        for step in 0..STEPS {
+            let shade = 0.5 + 0.5 * step as f32 / (STEPS - 1) as f32;
+            let [r, g, b] = percent(filled, shade);
+            write!(out, "#{};2;{};{};{}", step, r, g, b).unwrap();
+        }
+        let [r, g, b] = percent(empty, 1.0);
+        write!(out, "#{};2;{};{};{}", STEPS, r, g, b).unwrap();
+
+        // Each column's register, the runs of a register are drawn one register at a time
+        let register = |x: usize| match x < filled_px {
+            true => x * STEPS / filled_px.max(1),
+            false => STEPS,
+        };
+        let mut band = String::new();
+        for idx in 0..=STEPS {
+            let mut x = 0;
+            let mut runs = String::new();
+            while x < width {
+                let on = register(x) == idx;
+                let run = (x..width)
+                    .take_while(|&x| (register(x) == idx) == on)
+                    .count();
+                write!(runs, "!{}{}", run, if on { '~' } else { '?' }).unwrap();
+                x += run;
+            }
+            if runs.contains('~') {
+                write!(band, "#{}{}$", idx, runs).unwrap();
+            }
+        }
+        for i in 0..bands {
+            out.push_str(&band);
+            if i + 1 < bands {
+                out.push('-');
+            }
+        }
+        write!(out, "\x1b\\\x1b8\x1b[{}C", bar.cells).unwrap();
+        Some(out)
+    }
+}
+
+fn detect_sixel() -> bool {
+    let var = |name| env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    env::var_os("WT_SESSION").is_some()
+        || var("TERM_PROGRAM") == "WezTerm"
+        || term.contains("sixel")
+        || term.starts_with("foot")
+        || term.starts_with("mlterm")
+}
+
+/// Converts `rgb` scaled by `shade` to the percentages used by sixel color registers
+fn percent(rgb: [u8; 3], shade: f32) -> [u8; 3] {
+    let c = |c: u8| (c as f32 * shade / 2.55).round() as u8;
+    [c(rgb[0]), c(rgb[1]), c(rgb[2])]
+}
+
+/// Approximates the RGB value of `color` with the xterm palette
+fn rgb(color: Color) -> [u8; 3] {
+    const BASIC: [[u8; 3]; 16] = [
+        [0, 0, 0],
+        [205, 0, 0],
+        [0, 205, 0],
+        [205, 205, 0],
+        [0, 0, 238],
+        [205, 0, 205],
+        [0, 205, 205],
+        [229, 229, 229],
+        [127, 127, 127],
+        [255, 0, 0],
+        [0, 255, 0],
+        [255, 255, 0],
+        [92, 92, 255],
+        [255, 0, 255],
+        [0, 255, 255],
+        [255, 255, 255],
+    ];
+    let idx = match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::White => 7,
+        Color::Color256(idx) => idx,
+    };
+    match idx {
+        0..=15 => BASIC[idx as usize],
+        16..=231 => {
+            let level = |c: u8| if c == 0 { 0 } else { 55 + 40 * c };
+            let idx = idx - 16;
+            [level(idx / 36), level(idx / 6 % 6), level(idx % 6)]
+        }
+        _ => [8 + 10 * (idx - 232); 3],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+    #[derive(Debug)]
+    struct Blocks;
+
+    impl BarGraphics for Blocks {
+        fn is_supported(&self) -> bool {
+            true
+        }
+
+        fn render(&self, bar: &BarCells) -> Option<String> {
+            let filled = (bar.fraction * bar.cells as f32) as usize;
+            Some(format!("<{}|{}>", filled, bar.cells))
+        }
+    }
+
+    #[test]
+    fn bar_graphics() {
+        let pb = ProgressBar::with_draw_target(4, ProgressDrawTarget::hidden())
+            .with_style(ProgressStyle::default_bar().template("{pos} {bar:8} {wide_bar} {len}"));
+        pb.set_position(2);
+        pb.with_state(|state| {
+            let mut lines = Vec::new();
+            state.style.format_state_with(
+                state,
+                &mut lines,
+                &mut Vec::new(),
+                20,
+                None,
+                Some(&Blocks),
+            );
+            assert_eq!(lines, ["2 <4|8> <3|7> 4"]);
+
+            // Without graphics, the text bars are kept
+            lines.clear();
+            state.style.format_state(state, &mut lines, 20, None);
+            assert_eq!(lines, ["2 ████▏░░░ ███▌░░░ 4"]);
+        });
+
+        // The bars are found after the wide elements are expanded, on every line
+        pb.set_style(
+            ProgressStyle::default_bar().template("{wide_msg} {bar:4}\n{bar:2}{wide_bar}"),
+        );
+        pb.set_message("msg");
+        pb.with_state(|state| {
+            let mut lines = Vec::new();
+            state.style.format_state_with(
+                state,
+                &mut lines,
+                &mut Vec::new(),
+                10,
+                None,
+                Some(&Blocks),
+            );
+            assert_eq!(lines, ["msg   <2|4>", "<1|2><4|8>"]);
+        });
+
+        let sixel = SixelBar::new().force(true).render(&BarCells {
+            fraction: 0.5,
+            cells: 2,
+            colors: None,
+        });
+        let sixel = sixel.unwrap();
+        assert!(sixel.starts_with("\x1b7\x1bP") && sixel.ends_with("\x1b8\x1b[2C"));
+        assert_eq!(measure_line(&format!("a{}b", sixel)), 4);
+        assert_eq!(
+            SixelBar::new().force(false).render(&BarCells {
+                fraction: 0.5,
+                cells: 2,
+                colors: None,
+            }),
+            None
+        );
+    }
+}
@@ -57,28 +57,28 @@ impl TermLike for InMemoryTerm {
         self.state
             .lock()
             .unwrap()
-            .write_str(&*format!("\x1b[{}A", n))
+            .write_str(&format!("\x1b[{}A", n))
     }
 
     fn move_cursor_down(&self, n: usize) -> std::io::Result<()> {
         self.state
             .lock()
             .unwrap()
-            .write_str(&*format!("\x1b[{}B", n))
+            .write_str(&format!("\x1b[{}B", n))
     }
 
     fn move_cursor_right(&self, n: usize) -> std::io::Result<()> {
         self.state
             .lock()
             .unwrap()
-            .write_str(&*format!("\x1b[{}C", n))
+            .write_str(&format!("\x1b[{}C", n))
     }
 
     fn move_cursor_left(&self, n: usize) -> std::io::Result<()> {
         self.state
             .lock()
             .unwrap()
-            .write_str(&*format!("\x1b[{}D", n))
+            .write_str(&format!("\x1b[{}D", n))
     }
 
     fn write_line(&self, s: &str) -> std::io::Result<()> {
@@ -130,7 +130,7 @@ impl InMemoryTermState {
 
 impl Debug for InMemoryTermState {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("InMemoryTermState").finish_non_exhaustive()
+        f.debug_struct("InMemoryTermState").finish()
     }
 }
 
@@ -2,9 +2,9 @@ use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::io::{self, IoSliceMut};
 use std::iter::FusedIterator;
-#[cfg(feature = "tokio")]
+#[cfg(any(feature = "tokio", feature = "futures", feature = "futures-io"))]
 use std::pin::Pin;
-#[cfg(feature = "tokio")]
+#[cfg(any(feature = "tokio", feature = "futures", feature = "futures-io"))]
 use std::task::{Context, Poll};
 use std::time::Duration;
 
@@ -12,7 +12,7 @@ use std::time::Duration;
 use tokio::io::{ReadBuf, SeekFrom};
 
 use crate::progress_bar::ProgressBar;
-use crate::style::ProgressStyle;
+use crate::style::{ProgressFinish, ProgressStyle};
 
 /// Wraps an iterator to display its progress.
 pub trait ProgressIterator
@@ -54,6 +54,26 @@ where
         let bar = ProgressBar::new(len).with_style(style);
         self.progress_with(bar)
     }
+
+    /// Wrap an iterator with a progress bar, and set its style and message.
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressIterator, ProgressStyle};
+    /// let style = ProgressStyle::default_bar().template("{msg} {wide_bar} {pos}/{len}");
+    /// for _ in (0..1000).progress_styled(style, "processing") {
+    ///     // ...
+    /// }
+    /// ```
+    fn progress_styled(
+        self,
+        style: ProgressStyle,
+        message: impl Into<Cow<'static, str>>,
+    ) -> ProgressBarIter<Self>
+    where
+        Self: ExactSizeIterator,
+    {
+        self.progress_with_style(style).with_message(message)
+    }
 }
 
 /// Wraps an iterator to display its progress.
@@ -61,6 +81,11 @@ where
 pub struct ProgressBarIter<T> {
     pub(crate) it: T,
     pub progress: ProgressBar,
+    /// Whether seeking counts as progress, see [`ProgressBarIter::with_seek_progress()`]
+    pub(crate) seek_progress: bool,
+    /// Whether reaching the end finishes the progress bar, see
+    /// [`ProgressBarIter::with_finish_at_end()`]
+    pub(crate) finish_at_end: bool,
 }
 
 impl<T> ProgressBarIter<T> {
@@ -103,6 +128,175 @@ impl<T> ProgressBarIter<T> {
         self.progress = self.progress.with_elapsed(elapsed);
         self
     }
+
+    /// Builder-like function for setting how the underlying progress bar finishes.
+    ///
+    /// The progress bar is finished when the wrapped iterator or stream is exhausted, and with
+    /// [`ProgressBarIter::with_finish_at_end()`] when a wrapped reader reaches the end of its
+    /// input or a wrapped writer is closed.
+    ///
+    /// See [ProgressStyle::on_finish].
+    pub fn with_finish(mut self, finish: ProgressFinish) -> ProgressBarIter<T> {
+        self.progress = self.progress.with_finish(finish);
+        self
+    }
+
+    /// Builder-like function for setting whether seeking the wrapped object counts as progress.
+    ///
+    /// Seeking always moves the position of the progress bar to the new offset, so that it
+    /// matches the offset in the wrapped object. By default, the distance moved counts as
+    /// progress like bytes read or written do. If disabled, it doesn't count towards the rate
+    /// and ETA, which then only reflect the bytes actually transferred, e.g. for parsers that
+    /// skip large sections of a file.
+    ///
+    /// ```rust,no_run
+    /// # use std::fs::File;
+    /// # use std::io::{Read, Seek, SeekFrom};
+    /// # use indicatif::ProgressBar;
+    /// let file = File::open("archive")?;
+    /// let pb = ProgressBar::new(file.metadata()?.len());
+    /// let mut reader = pb.wrap_read(file).with_seek_progress(false);
+    /// reader.seek(SeekFrom::Start(1 << 20))?;
+    /// reader.read_to_end(&mut Vec::new())?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn with_seek_progress(mut self, seek_progress: bool) -> ProgressBarIter<T> {
+        self.seek_progress = seek_progress;
+        self
+    }
+
+    /// Builder-like function for setting whether reaching the end finishes the progress bar.
+    ///
+    /// If enabled, the progress bar is finished using its [`ProgressFinish`] behavior when a
+    /// wrapped reader reaches the end of its input, or a wrapped writer or sink is shut down or
+    /// closed. This is disabled by default, as a progress bar shared by several readers must
+    /// only finish after the last of them. Without a known length, the position is left as is.
+    ///
+    /// ```rust,no_run
+    /// # use std::fs::File;
+    /// # use std::io;
+    /// # use indicatif::ProgressBar;
+    /// let file = File::open("input")?;
+    /// let pb = ProgressBar::new(file.metadata()?.len());
+    /// let mut reader = pb.wrap_read(file).with_finish_at_end(true);
+    /// io::copy(&mut reader, &mut io::sink())?;
+    /// assert!(pb.is_finished());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn with_finish_at_end(mut self, finish_at_end: bool) -> ProgressBarIter<T> {
+        self.finish_at_end = finish_at_end;
+        self
+    }
+
+    /// Finishes the progress bar once the end of the wrapped object is reached, if enabled
+    fn finish_at_end(&self) {
+        if self.finish_at_end && !self.progress.is_finished() {
+            self.progress.finish_at_end();
+        }
+    }
+
+    /// Moves the position of the progress bar to the offset the wrapped object was seeked to
+    fn seeked(&self, pos: u64) {
+        match self.seek_progress {
+            true => self.progress.set_position(pos),
+            false => self.progress.skip_to(pos),
+        }
+    }
+}
+
+impl<R: io::Read> ProgressBarIter<R> {
+    /// Copies the rest of the wrapped reader to `writer`, like [`io::copy()`]
+    ///
+    /// The bytes are counted as they are written, so the wrapped reader is handed to
+    /// [`io::copy()`] unchanged and keeps its fast paths, e.g. copying straight from the buffer
+    /// of a `BufReader`, and vectored writes are passed through. The progress bar is finished
+    /// once the copy completes if [`ProgressBarIter::with_finish_at_end()`] is enabled.
+    ///
+    /// ```rust,no_run
+    /// # use std::fs::File;
+    /// # use std::io::BufReader;
+    /// # use indicatif::ProgressBar;
+    /// let source = BufReader::new(File::open("source")?);
+    /// let pb = ProgressBar::new(source.get_ref().metadata()?.len());
+    /// pb.wrap_read(source).copy_to(&mut File::create("destination")?)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn copy_to<W: io::Write + ?Sized>(&mut self, writer: &mut W) -> io::Result<u64> {
+        let mut counted = ProgressBarIter {
+            it: writer,
+            progress: self.progress.clone(),
+            seek_progress: true,
+            finish_at_end: false,
+        };
+        let copied = io::copy(&mut self.it, &mut counted)?;
+        self.finish_at_end();
+        Ok(copied)
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<S> ProgressBarIter<S> {
+    /// Makes a wrapped stream of byte chunks advance the progress bar by the length of each
+    /// chunk instead of by 1 per item.
+    ///
+    /// ```rust,no_run
+    /// # use futures::stream::{self, StreamExt};
+    /// # use indicatif::ProgressBar;
+    /// # async fn test() {
+    /// let chunks = stream::iter(vec![vec![0u8; 512], vec![0u8; 512]]);
+    /// let pb = ProgressBar::new(1024);
+    /// let mut stream = pb.wrap_stream(chunks).bytes_mode();
+    /// while let Some(chunk) = stream.next().await {
+    ///     // ...
+    /// }
+    /// # }
+    /// ```
+    pub fn bytes_mode(self) -> ProgressBarIter<BytesMode<S>> {
+        ProgressBarIter {
+            it: BytesMode(self.it),
+            progress: self.progress,
+            seek_progress: self.seek_progress,
+            finish_at_end: self.finish_at_end,
+        }
+    }
+}
+
+/// A stream of byte chunks that advances its progress bar by the length of each chunk.
+///
+/// Created by [`ProgressBarIter::bytes_mode()`].
+#[cfg(feature = "futures")]
+#[derive(Debug)]
+pub struct BytesMode<S>(S);
+
+/// An iterator over the lines of a reader that advances its progress bar by the bytes consumed.
+///
+/// Created by [`ProgressBar::wrap_lines()`].
+#[derive(Debug)]
+pub struct Lines<R>(pub(crate) R);
+
+impl<R: io::BufRead> Iterator for ProgressBarIter<Lines<R>> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.it.0.read_line(&mut line) {
+            Ok(0) => {
+                self.finish_at_end();
+                None
+            }
+            Ok(n) => {
+                self.progress.inc(n as u64);
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Some(Ok(line))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
 }
 
 impl<S, T: Iterator<Item = S>> Iterator for ProgressBarIter<T> {
@@ -147,19 +341,41 @@ impl<R: io::Read> io::Read for ProgressBarIter<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let inc = self.it.read(buf)?;
         self.progress.inc(inc as u64);
+        if inc == 0 && !buf.is_empty() {
+            self.finish_at_end();
+        }
         Ok(inc)
     }
 
     fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
         let inc = self.it.read_vectored(bufs)?;
         self.progress.inc(inc as u64);
+        if inc == 0 && bufs.iter().any(|buf| !buf.is_empty()) {
+            self.finish_at_end();
+        }
         Ok(inc)
     }
 
+    // Delegate so that the wrapped reader's own implementation is used, counting the bytes
+    // appended to `buf` even if an error occurs halfway.
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let start = buf.len();
+        let result = self.it.read_to_end(buf);
+        self.progress.inc((buf.len() - start) as u64);
+        if result.is_ok() {
+            self.finish_at_end();
+        }
+        result
+    }
+
     fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
-        let inc = self.it.read_to_string(buf)?;
-        self.progress.inc(inc as u64);
-        Ok(inc)
+        let start = buf.len();
+        let result = self.it.read_to_string(buf);
+        self.progress.inc((buf.len() - start) as u64);
+        if result.is_ok() {
+            self.finish_at_end();
+        }
+        result
     }
 
     fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
@@ -171,7 +387,11 @@ impl<R: io::Read> io::Read for ProgressBarIter<R> {
 
 impl<R: io::BufRead> io::BufRead for ProgressBarIter<R> {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
-        self.it.fill_buf()
+        let buf = self.it.fill_buf()?;
+        if buf.is_empty() && self.finish_at_end && !self.progress.is_finished() {
+            self.progress.finish_at_end();
+        }
+        Ok(buf)
     }
 
     fn consume(&mut self, amt: usize) {
@@ -183,7 +403,7 @@ impl<R: io::BufRead> io::BufRead for ProgressBarIter<R> {
 impl<S: io::Seek> io::Seek for ProgressBarIter<S> {
     fn seek(&mut self, f: io::SeekFrom) -> io::Result<u64> {
         self.it.seek(f).map(|pos| {
-            self.progress.set_position(pos);
+            self.seeked(pos);
             pos
         })
     }
@@ -209,12 +429,35 @@ impl<W: tokio::io::AsyncWrite + Unpin> tokio::io::AsyncWrite for ProgressBarIter
         })
     }
 
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.it)
+            .poll_write_vectored(cx, bufs)
+            .map(|poll| {
+                poll.map(|inc| {
+                    self.progress.inc(inc as u64);
+                    inc
+                })
+            })
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.it.is_write_vectored()
+    }
+
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         Pin::new(&mut self.it).poll_flush(cx)
     }
 
     fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.it).poll_shutdown(cx)
+        let result = Pin::new(&mut self.it).poll_shutdown(cx);
+        if let Poll::Ready(Ok(())) = &result {
+            self.finish_at_end();
+        }
+        result
     }
 }
 
@@ -226,8 +469,13 @@ impl<W: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for ProgressBarIter<W
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
         let prev_len = buf.filled().len() as u64;
+        let eof_possible = buf.remaining() > 0;
         if let Poll::Ready(e) = Pin::new(&mut self.it).poll_read(cx, buf) {
-            self.progress.inc(buf.filled().len() as u64 - prev_len);
+            let inc = buf.filled().len() as u64 - prev_len;
+            self.progress.inc(inc);
+            if e.is_ok() && inc == 0 && eof_possible {
+                self.finish_at_end();
+            }
             Poll::Ready(e)
         } else {
             Poll::Pending
@@ -242,7 +490,12 @@ impl<W: tokio::io::AsyncSeek + Unpin> tokio::io::AsyncSeek for ProgressBarIter<W
     }
 
     fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
-        Pin::new(&mut self.it).poll_complete(cx)
+        let this = &mut *self;
+        let result = Pin::new(&mut this.it).poll_complete(cx);
+        if let Poll::Ready(Ok(pos)) = &result {
+            this.seeked(*pos);
+        }
+        result
     }
 }
 
@@ -264,6 +517,190 @@ impl<W: tokio::io::AsyncBufRead + Unpin + tokio::io::AsyncRead> tokio::io::Async
     }
 }
 
+#[cfg(feature = "futures-io")]
+impl<R: futures_io::AsyncRead + Unpin> futures_io::AsyncRead for ProgressBarIter<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let eof_possible = !buf.is_empty();
+        let result = Pin::new(&mut this.it).poll_read(cx, buf);
+        if let Poll::Ready(Ok(inc)) = &result {
+            this.progress.inc(*inc as u64);
+            if *inc == 0 && eof_possible {
+                this.finish_at_end();
+            }
+        }
+        result
+    }
+
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let eof_possible = bufs.iter().any(|buf| !buf.is_empty());
+        let result = Pin::new(&mut this.it).poll_read_vectored(cx, bufs);
+        if let Poll::Ready(Ok(inc)) = &result {
+            this.progress.inc(*inc as u64);
+            if *inc == 0 && eof_possible {
+                this.finish_at_end();
+            }
+        }
+        result
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W: futures_io::AsyncWrite + Unpin> futures_io::AsyncWrite for ProgressBarIter<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let result = Pin::new(&mut this.it).poll_write(cx, buf);
+        if let Poll::Ready(Ok(inc)) = &result {
+            this.progress.inc(*inc as u64);
+        }
+        result
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let result = Pin::new(&mut this.it).poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(inc)) = &result {
+            this.progress.inc(*inc as u64);
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.it).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let result = Pin::new(&mut self.it).poll_close(cx);
+        if let Poll::Ready(Ok(())) = &result {
+            self.finish_at_end();
+        }
+        result
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R: futures_io::AsyncBufRead + Unpin> futures_io::AsyncBufRead for ProgressBarIter<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.it).poll_fill_buf(cx);
+        if let Poll::Ready(Ok(buf)) = &result {
+            if buf.is_empty() && this.finish_at_end && !this.progress.is_finished() {
+                this.progress.finish_at_end();
+            }
+        }
+        result
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        Pin::new(&mut self.it).consume(amt);
+        self.progress.inc(amt as u64);
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<S: futures_io::AsyncSeek + Unpin> futures_io::AsyncSeek for ProgressBarIter<S> {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: io::SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let this = &mut *self;
+        let result = Pin::new(&mut this.it).poll_seek(cx, pos);
+        if let Poll::Ready(Ok(pos)) = &result {
+            this.seeked(*pos);
+        }
+        result
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<S: futures_core::Stream + Unpin> futures_core::Stream for ProgressBarIter<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let item = Pin::new(&mut this.it).poll_next(cx);
+        match &item {
+            Poll::Ready(Some(_)) => this.progress.inc(1),
+            Poll::Ready(None) if !this.progress.is_finished() => this.progress.finish_using_style(),
+            _ => {}
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.size_hint()
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<S> futures_core::Stream for ProgressBarIter<BytesMode<S>>
+where
+    S: futures_core::Stream + Unpin,
+    S::Item: AsRef<[u8]>,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let item = Pin::new(&mut this.it.0).poll_next(cx);
+        match &item {
+            Poll::Ready(Some(chunk)) => this.progress.inc(chunk.as_ref().len() as u64),
+            Poll::Ready(None) if !this.progress.is_finished() => this.progress.finish_using_style(),
+            _ => {}
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.it.0.size_hint()
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<S: futures_sink::Sink<I> + Unpin, I> futures_sink::Sink<I> for ProgressBarIter<S> {
+    type Error = S::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        Pin::new(&mut self.it).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: I) -> Result<(), S::Error> {
+        Pin::new(&mut self.it).start_send(item)?;
+        self.progress.inc(1);
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        Pin::new(&mut self.it).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        let result = Pin::new(&mut self.it).poll_close(cx);
+        if let Poll::Ready(Ok(())) = &result {
+            self.finish_at_end();
+        }
+        result
+    }
+}
+
 impl<W: io::Write> io::Write for ProgressBarIter<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.it.write(buf).map(|inc| {
@@ -284,31 +721,38 @@ impl<W: io::Write> io::Write for ProgressBarIter<W> {
     }
 
     fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.it.write_all(buf).map(|()| {
-            self.progress.inc(buf.len() as u64);
-        })
+        self.it
+            .write_all(buf)
+            .map(|()| self.progress.inc(buf.len() as u64))
     }
 
-    // write_fmt can not be captured with reasonable effort.
-    // as it uses write_all internally by default that should not be a problem.
-    // fn write_fmt(&mut self, fmt: fmt::Arguments) -> io::Result<()>;
+    // The default implementations of `write_fmt` and `write_all_vectored` go through
+    // `write_all` and `write_vectored` respectively, so they are counted as well.
+    // `is_write_vectored` is not stable yet and can not be forwarded.
 }
 
 impl<S, T: Iterator<Item = S>> ProgressIterator for T {
     fn progress_with(self, progress: ProgressBar) -> ProgressBarIter<Self> {
-        ProgressBarIter { it: self, progress }
+        ProgressBarIter {
+            it: self,
+            progress,
+            seek_progress: true,
+            finish_at_end: false,
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::io;
+
     use crate::iter::{ProgressBarIter, ProgressIterator};
     use crate::progress_bar::ProgressBar;
-    use crate::ProgressStyle;
+    use crate::{ProgressDrawTarget, ProgressStyle};
 
     #[test]
     fn it_can_wrap_an_iterator() {
-        let v = vec![1, 2, 3];
+        let v = [1, 2, 3];
         let wrap = |it: ProgressBarIter<_>| {
             assert_eq!(it.map(|x| x * 2).collect::<Vec<_>>(), vec![2, 4, 6]);
         };
@@ -323,5 +767,185 @@ mod test {
             let style = ProgressStyle::default_bar().template("{wide_bar:.red} {percent}/100%");
             v.iter().progress_with_style(style)
         });
+        wrap({
+            let style = ProgressStyle::default_bar().template("{msg} {wide_bar}");
+            let it = v.iter().progress_styled(style, "doubling");
+            assert_eq!(it.progress.message(), "doubling");
+            it
+        });
+    }
+
+    #[test]
+    fn it_can_wrap_lines() {
+        let pb = ProgressBar::hidden();
+        let lines = pb
+            .wrap_lines(&b"foo\r\nbar\n\nbaz"[..])
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines, vec!["foo", "bar", "", "baz"]);
+        assert_eq!(pb.position(), 13);
+    }
+
+    #[test]
+    fn it_counts_all_write_paths() {
+        use std::io::{IoSlice, Write};
+
+        let pb = ProgressBar::hidden();
+        let mut writer = pb.wrap_write(Vec::new());
+        writer.write_all(b"hello").unwrap();
+        let n = writer
+            .write_vectored(&[IoSlice::new(b" "), IoSlice::new(b"wor")])
+            .unwrap();
+        assert_eq!(n, 4);
+        write!(writer, "ld {}", 42).unwrap();
+        assert_eq!(writer.it, b"hello world 42");
+        assert_eq!(pb.position(), 14);
+    }
+
+    #[test]
+    fn reader_finishes_at_eof() {
+        let pb = ProgressBar::with_draw_target(100, ProgressDrawTarget::hidden());
+        io::copy(&mut pb.wrap_read(&b"abc"[..]), &mut io::sink()).unwrap();
+        assert!(!pb.is_finished());
+
+        let mut reader = pb.wrap_read(&b"abc"[..]).with_finish_at_end(true);
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert!(pb.is_finished());
+        assert_eq!(pb.position(), 100);
+
+        // Without a length, the position isn't moved to the end
+        let pb = ProgressBar::hidden();
+        let mut reader = pb.wrap_read(&b"abc"[..]).with_finish_at_end(true);
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert!(pb.is_finished());
+        assert_eq!(pb.position(), 3);
+    }
+
+    #[test]
+    fn reader_follows_seeks() {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let pb = ProgressBar::hidden();
+        let mut reader = pb.wrap_read(io::Cursor::new(vec![0; 100]));
+        reader.seek(SeekFrom::Start(40)).unwrap();
+        assert_eq!(pb.position(), 40);
+
+        let mut reader = reader.with_seek_progress(false);
+        reader.seek(SeekFrom::Current(-30)).unwrap();
+        reader.read_exact(&mut [0; 5]).unwrap();
+        assert_eq!(pb.position(), 15);
+    }
+
+    #[test]
+    fn reader_counts_all_read_paths() {
+        use std::io::{IoSliceMut, Read};
+
+        let pb = ProgressBar::hidden();
+        let mut reader = pb.wrap_read(&b"hello world"[..]).with_finish_at_end(true);
+        let (mut a, mut b) = ([0; 2], [0; 3]);
+        let n = reader
+            .read_vectored(&mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)])
+            .unwrap();
+        assert_eq!((n, pb.position()), (5, 5));
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b" world");
+        assert_eq!(pb.position(), 11);
+        assert!(pb.is_finished());
+
+        let pb = ProgressBar::hidden();
+        let mut copied = Vec::new();
+        let n = pb
+            .wrap_read(io::BufReader::new(&b"abcdef"[..]))
+            .with_finish_at_end(true)
+            .copy_to(&mut copied)
+            .unwrap();
+        assert_eq!((n, copied.as_slice()), (6, &b"abcdef"[..]));
+        assert_eq!(pb.position(), 6);
+        assert!(pb.is_finished());
+    }
+
+    #[cfg(feature = "futures-io")]
+    #[test]
+    fn it_can_wrap_futures_io() {
+        use futures::executor::block_on;
+        use futures::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, Cursor};
+
+        let pb = ProgressBar::hidden();
+        let mut reader = pb.wrap_futures_read(Cursor::new(b"abc\ndef".to_vec()));
+        let mut line = String::new();
+        block_on(reader.read_line(&mut line)).unwrap();
+        assert_eq!(pb.position(), 4);
+
+        block_on(reader.seek(io::SeekFrom::Start(1))).unwrap();
+        assert_eq!(pb.position(), 1);
+
+        let mut rest = Vec::new();
+        block_on(reader.read_to_end(&mut rest)).unwrap();
+        assert_eq!(rest, b"bc\ndef");
+        assert_eq!(pb.position(), 7);
+    }
+
+    #[cfg(feature = "futures-io")]
+    #[test]
+    fn futures_io_finishes_at_end() {
+        use futures::executor::block_on;
+        use futures::io::{AsyncReadExt, AsyncWriteExt, Cursor};
+
+        let pb = ProgressBar::hidden();
+        let mut reader = pb
+            .wrap_futures_read(Cursor::new(b"abc".to_vec()))
+            .with_finish_at_end(true);
+        block_on(reader.read_to_end(&mut Vec::new())).unwrap();
+        assert!(pb.is_finished());
+        assert_eq!(pb.position(), 3);
+
+        let pb = ProgressBar::hidden();
+        let mut writer = pb
+            .wrap_futures_write(Cursor::new(Vec::new()))
+            .with_finish_at_end(true);
+        block_on(writer.write_all(b"abc")).unwrap();
+        assert!(!pb.is_finished());
+        block_on(writer.close()).unwrap();
+        assert!(pb.is_finished());
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn it_can_wrap_a_stream() {
+        use crate::ProgressFinish;
+        use futures::executor::block_on;
+        use futures::stream::{self, StreamExt};
+
+        let style = ProgressStyle::default_bar().on_finish(ProgressFinish::AtCurrentPos);
+        let pb = ProgressBar::hidden().with_style(style.clone());
+        let items = block_on(
+            pb.wrap_stream(stream::iter(vec![1, 2, 3]))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(items, vec![1, 2, 3]);
+        assert_eq!(pb.position(), 3);
+
+        let pb = ProgressBar::hidden().with_style(style);
+        let chunks = stream::iter(vec![&b"abc"[..], &b"de"[..]]);
+        block_on(pb.wrap_stream(chunks).bytes_mode().for_each(|_| async {}));
+        assert_eq!(pb.position(), 5);
+        assert!(pb.is_finished());
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn it_can_wrap_a_sink() {
+        use futures::executor::block_on;
+        use futures::sink::SinkExt;
+
+        let pb = ProgressBar::hidden();
+        let mut sink = pb.wrap_sink(Vec::new()).with_finish_at_end(true);
+        block_on(sink.feed(1)).unwrap();
+        block_on(sink.feed(2)).unwrap();
+        assert_eq!(pb.position(), 2);
+        assert!(!pb.is_finished());
+        block_on(sink.close()).unwrap();
+        assert!(pb.is_finished());
     }
 }
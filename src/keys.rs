@@ -0,0 +1,329 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::mem::MaybeUninit;
+use std::os::unix::io::AsRawFd;
+use std::str;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::progress_bar::{ProgressBar, WeakProgressBar};
+
+/// How long the listener waits for a key before checking whether it should stop, in milliseconds
+const POLL_TIMEOUT: i32 = 100;
+
+/// Keys handled by a [`KeyListener`], see [`ProgressBar::listen_keys()`]
+///
+/// The default bindings pause with `p` and cancel with `q`.
+pub struct KeyBindings {
+    bindings: Vec<(char, Action)>,
+}
+
+enum Action {
+    Pause,
+    Cancel,
+    Callback(Box<dyn Fn(&ProgressBar) + Send>),
+}
+
+impl KeyBindings {
+    /// Creates key bindings without any keys
+    pub fn new() -> KeyBindings {
+        KeyBindings {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Toggles [`ProgressBar::is_paused()`] when `key` is pressed
+    pub fn pause(mut self, key: char) -> KeyBindings {
+        self.bindings.push((key, Action::Pause));
+        self
+    }
+
+    /// Abandons the progress bar when `key` is pressed, see [`ProgressBar::abandon()`]
+    pub fn cancel(mut self, key: char) -> KeyBindings {
+        self.bindings.push((key, Action::Cancel));
+        self
+    }
+
+    /// Calls `f` with the progress bar when `key` is pressed
+    ///
+    /// **Note:** `f` is called from the listener thread.
+    pub fn on_key(mut self, key: char, f: impl Fn(&ProgressBar) + Send + 'static) -> KeyBindings {
+        self.bindings.push((key, Action::Callback(Box::new(f))));
+        self
+    }
+
+    fn handle(&self, pb: &ProgressBar, key: char) {
+        for (_, action) in self.bindings.iter().filter(|(k, _)| *k == key) {
+            match action {
+                Action::Pause => pb.set_paused(!pb.is_paused()),
+                Action::Cancel => pb.abandon(),
+                Action::Callback(f) => f(pb),
+            }
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings::new().pause('p').cancel('q')
+    }
+}
+
+impl fmt::Debug for KeyBindings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys = self.bindings.iter().map(|(key, _)| key);
+        f.debug_struct("KeyBindings")
+            .field("keys", &keys.collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Reads key presses from the terminal for a progress bar, see [`ProgressBar::listen_keys()`]
+///
+/// While listening, the terminal doesn't wait for a newline before passing on input and doesn't
+/// echo it. The listener stops when it's dropped or the progress bar is finished, restoring the
+/// terminal.
+///
+/// **Note:** The terminal is only restored by the listener itself. If the process is killed by a
+/// signal (e.g. `Ctrl+C`), calls [`std::process::exit()`] or aborts on a panic, the listener isn't
+/// dropped and the terminal is left without echo and line editing, until it's reset (e.g. with
+/// `stty sane` or `reset`). Applications handling these cases should drop the listener first,
+/// e.g. from their `Ctrl+C` handler.
+pub struct KeyListener {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    tty: File,
+    /// Terminal settings before listening
+    original: libc::termios,
+    /// Held while reading keys, or while the application reads input itself
+    input: Mutex<()>,
+    /// Set while the application waits for `input`, so the listener lets it go
+    suspending: AtomicBool,
+    stop: AtomicBool,
+}
+
+impl KeyListener {
+    pub(crate) fn new(pb: &ProgressBar, bindings: KeyBindings) -> io::Result<KeyListener> {
+        let tty = File::open("/dev/tty")?;
+        let original = termios(&tty)?;
+        let shared = Arc::new(Shared {
+            tty,
+            original,
+            input: Mutex::new(()),
+            suspending: AtomicBool::new(false),
+            stop: AtomicBool::new(false),
+        });
+        shared.listen()?;
+
+        let thread = {
+            let (shared, pb) = (shared.clone(), pb.downgrade());
+            thread::spawn(move || shared.run(pb, bindings))
+        };
+        Ok(KeyListener {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Restores the terminal while `f` runs, so the application can read input itself
+    ///
+    /// Keys pressed meanwhile are left to the application.
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        self.shared.suspending.store(true, Ordering::SeqCst);
+        let _input = self.shared.input.lock().unwrap();
+        self.shared.suspending.store(false, Ordering::SeqCst);
+        let _ = self.shared.restore();
+        let ret = f();
+        if !self.shared.stop.load(Ordering::SeqCst) {
+            let _ = self.shared.listen();
+        }
+        ret
+    }
+}
+
+impl fmt::Debug for KeyListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyListener")
+            .field("tty", &self.shared.tty)
+            .finish()
+    }
+}
+
+impl Drop for KeyListener {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let _ = self.shared.restore();
+    }
+}
+
+impl Shared {
+    fn run(&self, pb: WeakProgressBar, bindings: KeyBindings) {
+        while !self.stop.load(Ordering::SeqCst) {
+            match pb.upgrade() {
+                Some(pb) if !pb.is_finished() => {}
+                _ => break,
+            }
+            if self.suspending.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+
+            let keys = {
+                let _input = self.input.lock().unwrap();
+                match self.read_keys() {
+                    Ok(keys) => keys,
+                    Err(_) => break,
+                }
+            };
+
+            if let Some(pb) = pb.upgrade() {
+                for key in keys {
+                    bindings.handle(&pb, key);
+                }
+            }
+        }
+
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.restore();
+    }
+
+    /// Waits a little for keys, returning the keys pressed if any
+    fn read_keys(&self) -> io::Result<Vec<char>> {
+        let mut fd = libc::pollfd {
+            fd: self.tty.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `fd` is a single valid `pollfd`
+        match unsafe { libc::poll(&mut fd, 1, POLL_TIMEOUT) } {
+            n if n < 0 => return Err(io::Error::last_os_error()),
+            0 => return Ok(Vec::new()),
+            _ => {}
+        }
+
+        // Escape sequences and characters arrive in a single read, keep room to complete the
+        // last character if the read split it
+        let mut buf = [0; 32];
+        let mut n = match (&self.tty).read(&mut buf[..28])? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => n,
+        };
+        if let Some(start) = (n.saturating_sub(3)..n).rev().find(|&i| buf[i] >= 0xc0) {
+            let missing = (start + utf8_len(buf[start])).saturating_sub(n);
+            (&self.tty).read_exact(&mut buf[n..n + missing])?;
+            n += missing;
+        }
+        Ok(decode_keys(&buf[..n]))
+    }
+
+    /// Passes on each key as soon as it's pressed, without echoing it
+    fn listen(&self) -> io::Result<()> {
+        let mut termios = self.original;
+        termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+        set_termios(&self.tty, &termios)
+    }
+
+    fn restore(&self) -> io::Result<()> {
+        set_termios(&self.tty, &self.original)
+    }
+}
+
+/// Decodes the keys in `bytes`, skipping the escape sequences of special keys such as arrows
+fn decode_keys(bytes: &[u8]) -> Vec<char> {
+    let mut keys = Vec::new();
+    let mut rest = bytes;
+    while let Some(&first) = rest.first() {
+        let len = match (first, rest.get(1)) {
+            // CSI sequences end with a byte in `@`..=`~`, after their parameters
+            (0x1b, Some(b'[')) => rest[2..]
+                .iter()
+                .position(|b| (0x40..=0x7e).contains(b))
+                .map_or(rest.len(), |end| end + 3),
+            // SS3 sequences, e.g. F1 to F4
+            (0x1b, Some(b'O')) => Ord::min(3, rest.len()),
+            // Alt and a key
+            (0x1b, Some(&next)) => 1 + utf8_len(next),
+            _ => {
+                let len = utf8_len(first);
+                if let Some(key) = rest.get(..len).and_then(|b| str::from_utf8(b).ok()) {
+                    keys.extend(key.chars());
+                }
+                len
+            }
+        };
+        rest = rest.get(len..).unwrap_or_default();
+    }
+    keys
+}
+
+/// Returns the length of the UTF-8 character starting with `first`
+fn utf8_len(first: u8) -> usize {
+    match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
+
+fn termios(tty: &File) -> io::Result<libc::termios> {
+    let mut termios = MaybeUninit::uninit();
+    // SAFETY: `tcgetattr` initializes `termios` if it succeeds
+    match unsafe { libc::tcgetattr(tty.as_raw_fd(), termios.as_mut_ptr()) } {
+        0 => Ok(unsafe { termios.assume_init() }),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn set_termios(tty: &File, termios: &libc::termios) -> io::Result<()> {
+    // SAFETY: `termios` is a valid reference
+    match unsafe { libc::tcsetattr(tty.as_raw_fd(), libc::TCSANOW, termios) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_bindings() {
+        let pb = ProgressBar::hidden();
+        let pressed = Arc::new(AtomicBool::new(false));
+        let bindings = KeyBindings::default().on_key('x', {
+            let pressed = pressed.clone();
+            move |_| pressed.store(true, Ordering::SeqCst)
+        });
+
+        bindings.handle(&pb, 'p');
+        assert!(pb.is_paused());
+        bindings.handle(&pb, 'x');
+        assert!(pressed.load(Ordering::SeqCst));
+        bindings.handle(&pb, 'p');
+        assert!(!pb.is_paused());
+        bindings.handle(&pb, 'q');
+        assert!(pb.is_finished());
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(decode_keys(b"pq"), ['p', 'q']);
+        assert_eq!(decode_keys("é日".as_bytes()), ['é', '日']);
+        // Arrows, F1, F5 and Alt+x are skipped
+        assert_eq!(decode_keys(b"\x1b[Ap\x1bOP\x1b[15~\x1bxq"), ['p', 'q']);
+        assert_eq!(decode_keys(b"\x1b"), ['\x1b']);
+        assert_eq!(decode_keys(b"\xffp\x1b["), ['p']);
+    }
+}
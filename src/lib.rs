@@ -24,6 +24,7 @@
 //!   * [`BinaryBytes`](struct.BinaryBytes.html) for formatting bytes using ISO/IEC prefixes
 //!   * [`HumanDuration`](struct.HumanDuration.html) for formatting durations
 //!   * [`HumanCount`](struct.HumanCount.html) for formatting large counts
+//!   * [`Locale`](struct.Locale.html) for formatting numbers and durations in other languages
 //!
 //! # Progress Bars and Spinners
 //!
@@ -83,8 +84,9 @@
 //!
 //! See the [`ProgressIterator`](trait.ProgressIterator.html) trait for more
 //! methods to configure the number of elements in the iterator or change
-//! the progress bar style. Indicatif also has optional support for parallel
-//! iterators with [Rayon](https://github.com/rayon-rs/rayon). In your
+//! the progress bar style, like `progress_styled(style, message)`. Indicatif
+//! also has optional support for parallel iterators with
+//! [Rayon](https://github.com/rayon-rs/rayon). In your
 //! `Cargo.toml`, use the "rayon" feature:
 //!
 //! ```toml
@@ -132,9 +134,10 @@
 //! /STYLE          an optional dot separated alternative style string
 //! ```
 //!
-//! For the style component see [`Style::from_dotted_str`](https://docs.rs/console/0.7.5/console/struct.Style.html#method.from_dotted_str)
+//! For the style component see [`Style::from_dotted_str`](crate::Style::from_dotted_str)
 //! for more information.  Indicatif uses the `console` base crate for all
-//! colorization and formatting options.
+//! colorization and formatting options, or a built-in implementation of the same styles without
+//! the `console` feature.
 //!
 //! Some examples for templates:
 //!
@@ -146,6 +149,21 @@
 //! as primary style color and blue as alternative style color.
 //! Alternative styles are currently only used for progress bars.
 //!
+//! Literal braces are written as `{{` and `}}`. Text between `{raw}` and `{/raw}` is used as is,
+//! without looking for placeholders, which is handy for JSON-like output:
+//!
+//! ```text
+//! {raw}{"progress": {/raw}{pos}{raw}}{/raw}
+//! ```
+//!
+//! Literal text and placeholders without a style of their own can be styled by wrapping them in
+//! `{color:STYLE}` and `{/color}`. Styles don't count towards the width of the line, so the
+//! space left for `wide_bar` and `wide_msg` is the same with and without colors:
+//!
+//! ```text
+//! {color:green.bold}Downloaded {pos} files{/color} {wide_bar}
+//! ```
+//!
 //! Example configuration:
 //!
 //! ```rust
@@ -161,32 +179,75 @@
 //! * `bar`: renders a progress bar. By default 20 characters wide.  The
 //!   style string is used to color the elapsed part, the alternative
 //!   style is used for the bar that is yet to render.
-//! * `wide_bar`: like `bar` but always fills the remaining space. It should not be used with
-//! `wide_msg`.
+//! * `wide_bar`: like `bar` but always fills the remaining space.
+//! * `aux_bar`: like `bar`, for the secondary quantity set with
+//!   [`ProgressBar::set_aux_position()`] and [`ProgressBar::set_aux_length()`].
+//! * `aux_pos` / `aux_len`: render the position / length of the secondary quantity as integers.
+//! * `aux_bytes` / `aux_total_bytes`: render the position / length of the secondary quantity as
+//!   bytes.
 //! * `spinner`: renders the spinner (current tick string).
 //! * `prefix`: renders the prefix set on the progress bar.
+//! * `thread`: renders the name of the thread that last updated the progress bar.
+//! * `task_id`: renders the task id set with [`ProgressBar::set_task_id()`], or the name of the
+//!   thread that last updated the progress bar if none is set.
 //! * `msg`: renders the currently set message on the progress bar.
-//! * `wide_msg`: like `msg` but always fills the remaining space and truncates. It should not be used
-//! with `wide_bar`.
+//! * `wide_msg`: like `msg` but always fills the remaining space and truncates.
 //! * `pos`: renders the current position of the bar as integer
 //! * `human_pos`: renders the current position of the bar as an integer, with commas as the
-//! thousands separator.
+//!   thousands separator.
 //! * `len`: renders the total length of the bar as integer
 //! * `human_len`: renders the total length of the bar as an integer, with commas as the thousands
-//! separator.
+//!   separator.
+//! * `si_pos` / `si_len`: render the current position / total length of the bar with SI
+//!   prefixes, i.e. `1.2k`, `3.4M`, etc.
 //! * `bytes`: renders the current position of the bar as bytes.
 //! * `percent`: renders the current position of the bar as a percentage of the total length.
+//!   This goes past 100 only if [`ProgressStyle::overflow()`] is set.
 //! * `total_bytes`: renders the total length of the bar as bytes.
 //! * `elapsed_precise`: renders the elapsed time as `HH:MM:SS`.
 //! * `elapsed`: renders the elapsed time as `42s`, `1m` etc.
 //! * `per_sec`: renders the speed in steps per second.
+//! * `si_per_sec`: renders the speed in steps per second with SI prefixes.
 //! * `bytes_per_sec`: renders the speed in bytes per second.
 //! * `binary_bytes_per_sec`: renders the speed in bytes per second using
 //!   power-of-two units, i.e. `MiB`, `KiB`, etc.
+//!
+//!   The speed keys are averaged over the most recent steps. `{bytes_per_sec:instant}` shows the
+//!   speed of the most recent step instead, and `{bytes_per_sec:avg}` the average speed since the
+//!   start. Further options follow after another colon, as in `{per_sec:avg:>10}`.
+//! * `sparkline`: the speed in each of the most recent seconds, as a sparkline like `▁▂▅▇█▇`.
+//!   The width sets the number of seconds shown, 10 by default and at most 120, as in
+//!   `{sparkline:30}`.
 //! * `eta_precise`: the remaining time (like `elapsed_precise`).
 //! * `eta`: the remaining time (like `elapsed`).
+//! * `eta_low` and `eta_high`: the optimistic and pessimistic remaining time (like `eta`), as in
+//!   `{eta_low}–{eta_high}`. See `ProgressBar::set_eta_confidence` for the width of the range.
+//! * `eta_adaptive`: the remaining time, in hours and minutes when far out, then in seconds and
+//!   in tenths of a second for the final seconds.
 //! * `duration_precise`: the extrapolated total duration (like `elapsed_precise`).
 //! * `duration`: the extrapolated total duration time (like `elapsed`).
+//! * `total_duration`: the elapsed time plus the remaining time (like `elapsed`). Unlike
+//!   `duration`, this is the elapsed time once the progress bar is finished.
+//! * `start_time`: the clock time the progress bar started at, as `HH:MM:SS`.
+//! * `end_time_estimate`: the clock time the progress bar is expected to finish at, as
+//!   `HH:MM:SS`. Clock times are local unless set otherwise with `ProgressStyle::clock`.
+//! * `overshoot`: how long the progress bar has been running past its expected duration (see
+//!   `ProgressBar::set_expected_duration`), like `+1m`. Renders nothing within the expected
+//!   duration.
+//! * `last_lap`: the label and duration of the most recent lap (see `ProgressBar::lap`), like
+//!   `phase 1: 3m`.
+//! * `active`, `finished`, `total_bars`: the number of unfinished, finished and all progress bars
+//!   in the `MultiProgress` the bar is part of, not counting its total bar (see
+//!   `MultiProgress::total_bar`). Renders nothing for progress bars outside a `MultiProgress`.
+//! * `group_percent`, `group_eta`: the completion in percent of all progress bars in the
+//!   `MultiProgress` the bar is part of, and the time until the last of them is expected to
+//!   finish. Renders nothing for progress bars outside a `MultiProgress`.
+//! * `parent_percent`: the completion in percent of the parent of a progress bar added with
+//!   `MultiProgress::add_with_level`. Renders nothing for progress bars without a parent.
+//!
+//! A line can contain several wide elements, which then share the remaining space. For wide
+//! elements the width is used as a weight, so `{wide_msg:1} {wide_bar:2}` gives the bar twice as
+//! much space as the message.
 
 //!
 //! The design of the progress bar can be altered with the integrated
@@ -209,36 +270,119 @@
 //!
 //! # Feature Flags
 //!
+//! * `console` (enabled by default): uses [`console`](https://docs.rs/console) to style text and
+//!   to query terminals. Without it, [`Style`], [`Color`] and [`Term`] are small built-in
+//!   replacements writing plain ANSI escape codes, for minimal builds: terminals aren't queried,
+//!   their size is read from `COLUMNS` and `LINES`, and they are only detected through `TERM`.
+//!   The legacy Windows console isn't supported then. `improved_unicode` enables this feature
 //! * `rayon`: adds rayon support
+//! * `futures`: adds support for wrapping [`Stream`](https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html)s
+//!   and [`Sink`](https://docs.rs/futures-sink/0.3/futures_sink/trait.Sink.html)s
+//! * `futures-io`: adds support for wrapping the `AsyncRead`, `AsyncWrite`, `AsyncBufRead` and
+//!   `AsyncSeek` traits of [`futures-io`](https://docs.rs/futures-io/0.3), as used by `async-std`
 //! * `improved_unicode`: adds improved unicode support (graphemes, better width calculation)
+//! * `local-time`: shows the clock time keys like `start_time` in the local time zone on Unix,
+//!   using `libc`. Without it, they are in UTC.
+//! * `serde`: implements `Serialize` and `Deserialize` for [`ProgressStyle`], e.g. to load
+//!   styles from configuration files, and adds `ProgressBar::as_serde()` to serialize snapshots
+//!   of progress bars
+//! * `systemd`: adds `SystemdStatus`, reporting progress as the status of a systemd service
+//!   (Unix only)
+//! * `sharded-state`: keeps the position in an atomic, so that `ProgressBar::inc()` and
+//!   `ProgressBar::set_position()` never wait for the internal lock. If another thread holds it,
+//!   that thread applies and draws the change before releasing it. Other updates still take the
+//!   lock. This is only a partial solution to lock contention: the state stays behind a `Mutex`,
+//!   and `inc()` and `set_position()` still try to take it to draw, so it still shows up in
+//!   profiles of heavily contended progress bars
+//! * `keyboard`: adds `ProgressBar::listen_keys()`, pausing or cancelling progress bars when
+//!   keys are pressed (Unix only). The terminal is left without echo if the process exits
+//!   without dropping the listener, e.g. on `Ctrl+C`
+//! * `metrics`: exports progress bars with a task id (see `ProgressBar::set_task_id()`) as the
+//!   gauges `indicatif_position`, `indicatif_length` and `indicatif_rate` of the
+//!   [`metrics`](https://docs.rs/metrics) facade, labelled with `bar` set to the task id. The
+//!   gauges are updated whenever the progress bar is drawn, and reset to 0 once it's finished or
+//!   removed from its `MultiProgress`. This feature requires Rust 1.71.1, the minimum supported
+//!   Rust version of `metrics` 0.24
+//! * `wasm`: reads the time from the JS runtime on `wasm32-unknown-unknown`, where `std::time`
+//!   panics, using [`web-time`](https://docs.rs/web-time). There are no threads there, so steady
+//!   ticks are disabled; draw with `ProgressDrawTarget::callback()`. This feature requires
+//!   Rust 1.60, the minimum supported Rust version of `web-time`
 
 #![warn(unreachable_pub)]
 
+mod ansi;
+mod benchmark;
+mod callback;
+mod compat;
 mod draw_target;
+#[cfg(not(feature = "console"))]
+mod fallback;
 mod format;
+mod graphics;
 #[cfg(feature = "in_memory")]
 mod in_memory;
 mod iter;
+#[cfg(all(unix, feature = "keyboard"))]
+mod keys;
 mod multi;
+mod preset;
 mod progress_bar;
 #[cfg(feature = "rayon")]
 mod rayon;
+mod recording;
+mod registry;
+#[cfg(feature = "unstable-renderer")]
+pub mod renderer;
+#[cfg(feature = "serde")]
+mod snapshot;
 mod state;
 mod style;
+#[cfg(all(unix, feature = "systemd"))]
+mod systemd;
 mod term_like;
+mod theme;
+mod ticker;
+mod time;
+mod walk;
 
-pub use crate::draw_target::ProgressDrawTarget;
+pub use crate::ansi::AnsiTerm;
+pub use crate::benchmark::{Benchmark, BenchmarkReport};
+pub use crate::compat::{Color, Style, StyledObject, Term};
+pub use crate::draw_target::{
+    set_global_draw_enabled, set_global_refresh_multiplier, ColorChoice, DrawCounter, DrawStrategy,
+    Filter, ProgressDrawTarget,
+};
 pub use crate::format::{
-    BinaryBytes, DecimalBytes, FormattedDuration, HumanBytes, HumanCount, HumanDuration,
+    AdaptiveDuration, BinaryBytes, Clock, DecimalBytes, FormattedDuration, HumanBytes, HumanCount,
+    HumanDuration, Locale, SiNumber, UnitNames,
 };
+pub use crate::graphics::{BarCells, BarGraphics, SixelBar};
 #[cfg(feature = "in_memory")]
 pub use crate::in_memory::InMemoryTerm;
-pub use crate::iter::{ProgressBarIter, ProgressIterator};
-pub use crate::multi::{MultiProgress, MultiProgressAlignment};
-pub use crate::progress_bar::{ProgressBar, WeakProgressBar};
-pub use crate::state::ProgressState;
-pub use crate::style::{ProgressFinish, ProgressStyle};
+#[cfg(feature = "futures")]
+pub use crate::iter::BytesMode;
+pub use crate::iter::{Lines, ProgressBarIter, ProgressIterator};
+#[cfg(all(unix, feature = "keyboard"))]
+pub use crate::keys::{KeyBindings, KeyListener};
+pub use crate::multi::{
+    BarComparator, BarInfo, MultiProgress, MultiProgressAlignment, MultiProgressEvent, SortPolicy,
+};
+pub use crate::preset::{Preset, PresetOptions};
+pub use crate::progress_bar::{PositionHandle, ProgressBar, WeakProgressBar};
+pub use crate::recording::{ProgressRecording, Sample};
+pub use crate::registry::{active_bars, enable_registry};
+#[cfg(feature = "serde")]
+pub use crate::snapshot::ProgressSnapshot;
+pub use crate::state::{Direction, Notify, ProgressState, Scale};
+pub use crate::style::{
+    pad_str, truncate_str, Alignment, Column, LineOverflow, ProgressFinish, ProgressStyle,
+    StyleError,
+};
+#[cfg(all(unix, feature = "systemd"))]
+pub use crate::systemd::SystemdStatus;
 pub use crate::term_like::TermLike;
+pub use crate::theme::{Background, Theme};
+pub use crate::walk::{WalkIter, WalkProgress, WalkReader};
 
 #[cfg(feature = "rayon")]
 pub use crate::rayon::ParallelProgressIterator;
@@ -1,9 +1,23 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
 use std::io;
-use std::sync::{Arc, RwLock};
-use std::time::Instant;
+use std::mem;
+use std::sync::{Arc, Mutex, RwLock, TryLockError, Weak};
+use std::time::Duration;
 
-use crate::draw_target::{DrawStateWrapper, ProgressDrawState, ProgressDrawTarget};
-use crate::progress_bar::ProgressBar;
+use crate::compat::{strip_ansi_codes, Style};
+
+use crate::draw_target::{
+    DrawStateWrapper, DrawStrategy, ProgressDrawState, ProgressDrawTarget, SystemProgress,
+};
+use crate::progress_bar::{ProgressBar, WeakProgressBar};
+use crate::state::{BarCounts, GroupProgress, ProgressState};
+use crate::ticker::{next_frame, ticker, Scheduled};
+use crate::time::Instant;
+
+/// How often the total bar is redrawn if the draw target has no refresh rate
+const TOTAL_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Manages multiple progress bars from different threads
 #[derive(Debug)]
@@ -29,24 +43,119 @@ impl MultiProgress {
 
     /// Creates a new multi progress object with the given draw target.
     pub fn with_draw_target(draw_target: ProgressDrawTarget) -> MultiProgress {
-        MultiProgress {
-            state: Arc::new(RwLock::new(MultiProgressState::new(draw_target))),
-        }
+        let state = Arc::new(RwLock::new(MultiProgressState::new(draw_target)));
+        state.write().unwrap().this = Arc::downgrade(&state);
+        MultiProgress { state }
     }
 
     /// Sets a different draw target for the multiprogress bar.
+    ///
+    /// The progress bars are cleared from the previous draw target and redrawn with their current
+    /// state on the new one, so the draw target can be switched while they are running, e.g. from
+    /// [`ProgressDrawTarget::hidden()`] to [`ProgressDrawTarget::stderr()`] when the verbosity is
+    /// raised.
     pub fn set_draw_target(&self, target: ProgressDrawTarget) {
-        let mut state = self.state.write().unwrap();
-        state.draw_target.disconnect(Instant::now());
-        state.draw_target = target;
+        let now = Instant::now();
+        let bars = {
+            let mut state = self.state.write().unwrap();
+            if state.strategy == DrawStrategy::Scroll {
+                let _ = state.draw_target.release_scroll_region();
+            }
+            let _ = state.clear(now);
+            state.draw_target = target;
+            state.bars.clone()
+        };
+
+        // Lines of progress bars are rendered for the width of the draw target, so render them
+        // again instead of reusing them. This takes the lock of each progress bar, which must not
+        // be taken while holding the lock of the `MultiProgress`.
+        for bar in bars.iter().filter_map(WeakProgressBar::upgrade) {
+            bar.redraw(now);
+        }
+        let _ = self.state.write().unwrap().draw(true, now);
     }
 
     /// Set whether we should try to move the cursor when possible instead of clearing lines.
     ///
     /// This can reduce flickering, but do not enable it if you intend to change the number of
-    /// progress bars.
+    /// progress bars. This is a shorthand for [`MultiProgress::set_draw_strategy()`] with
+    /// [`DrawStrategy::CursorMovement`] or [`DrawStrategy::ClearAndRedraw`].
     pub fn set_move_cursor(&self, move_cursor: bool) {
-        self.state.write().unwrap().move_cursor = move_cursor;
+        self.set_draw_strategy(match move_cursor {
+            true => DrawStrategy::CursorMovement,
+            false => DrawStrategy::ClearAndRedraw,
+        });
+    }
+
+    /// Sets how previously drawn lines are replaced when redrawing.
+    ///
+    /// Terminals and multiplexers (e.g. tmux, mosh) differ in which strategy flickers least.
+    /// [`DrawStrategy::Scroll`] pins the progress bars to the bottom of the terminal until the
+    /// strategy is changed or the `MultiProgress` is dropped.
+    pub fn set_draw_strategy(&self, strategy: DrawStrategy) {
+        let mut state = self.state.write().unwrap();
+        if state.strategy == DrawStrategy::Scroll && strategy != DrawStrategy::Scroll {
+            let _ = state.draw_target.release_scroll_region();
+        }
+        state.strategy = strategy;
+    }
+
+    /// Dims progress bars that have been finished for at least `after`.
+    ///
+    /// This makes finished progress bars that are retained on screen distinguishable from active
+    /// ones. The styling of dimmed bars is removed. The multi progress is redrawn once the first
+    /// progress bar is due to be dimmed, even if none of the progress bars is updated. Pass `None`
+    /// to disable it (this is the default).
+    pub fn set_dim_finished(&self, after: Option<Duration>) {
+        self.state.write().unwrap().dim_finished = after;
+    }
+
+    /// Renders the progress bars on the terminal's alternate screen buffer.
+    ///
+    /// Like full-screen terminal applications, this keeps the progress bars out of the
+    /// scrollback. While the alternate screen is used, lines printed with
+    /// [`ProgressBar::println()`] are queued, and written to the primary screen once it is
+    /// restored. That happens when this option is disabled, on [`MultiProgress::clear()`], once
+    /// all progress bars are finished, or when the `MultiProgress` is dropped. In all cases the
+    /// option is disabled afterwards, so progress bars that are still drawn end up on the primary
+    /// screen. This has no effect on draw targets that aren't terminals.
+    pub fn set_alternate_screen(&self, enabled: bool) {
+        let mut state = self.state.write().unwrap();
+        if state.alternate_screen == enabled {
+            return;
+        }
+
+        state.alternate_screen = enabled;
+        let _ = match enabled {
+            true => state.draw_target.enter_alternate_screen(),
+            false => state.leave_alternate_screen(),
+        };
+        drop(state);
+        let _ = self.state.write().unwrap().draw(true, Instant::now());
+    }
+
+    /// Sets the order in which the progress bars are drawn.
+    ///
+    /// The order is applied when drawing, so it doesn't change where progress bars are inserted.
+    /// Bars added with [`MultiProgress::add_with_level()`] are kept below their parent bar.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::{MultiProgress, SortPolicy};
+    /// let mp = MultiProgress::new();
+    /// mp.set_sort(SortPolicy::MostRecentlyUpdatedFirst);
+    /// ```
+    pub fn set_sort(&self, sort: SortPolicy) {
+        self.state.write().unwrap().sort = sort;
+    }
+
+    /// Limits the width of the progress bars to `width` columns
+    ///
+    /// The progress bars are rendered as if the terminal was `width` columns wide, unless the
+    /// terminal is narrower, which keeps the output consistent across terminals. `None` removes
+    /// the limit. Unlike [`ProgressDrawTarget::with_width()`], this is kept when the draw target is
+    /// changed.
+    pub fn set_width(&self, width: impl Into<Option<usize>>) {
+        self.state.write().unwrap().max_width = width.into();
     }
 
     /// Set alignment flag
@@ -63,6 +172,29 @@ impl MultiProgress {
         self.internalize(InsertLocation::End, pb)
     }
 
+    /// Adds a progress bar at the given tree level.
+    ///
+    /// Bars with a level greater than 0 are rendered as children of the closest preceding bar
+    /// with a lower level, with indentation guides (`├─`, `└─`) drawn in front of them. The
+    /// guides are recomputed on every draw, so they stay correct when bars are added or removed.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::{MultiProgress, ProgressBar};
+    /// let mp = MultiProgress::new();
+    /// let root = mp.add(ProgressBar::new(2));
+    /// let child1 = mp.add_with_level(ProgressBar::new(10), 1);
+    /// let child2 = mp.add_with_level(ProgressBar::new(10), 1);
+    /// ```
+    pub fn add_with_level(&self, pb: ProgressBar, level: usize) -> ProgressBar {
+        let idx = self
+            .state
+            .write()
+            .unwrap()
+            .insert_with_level(InsertLocation::End, level);
+        self.attach(idx, &pb);
+        pb
+    }
+
     /// Inserts a progress bar.
     ///
     /// The progress bar inserted at position `index` will have the draw
@@ -94,7 +226,12 @@ impl MultiProgress {
     /// remote draw target that is intercepted by the multi progress
     /// object overriding custom `ProgressDrawTarget` settings.
     pub fn insert_before(&self, before: &ProgressBar, pb: ProgressBar) -> ProgressBar {
-        self.internalize(InsertLocation::Before(before), pb)
+        // Looked up before locking the state, as progress bars must not be locked while holding it
+        let location = match before.index() {
+            Some(idx) => InsertLocation::Before(idx, before),
+            None => InsertLocation::End,
+        };
+        self.internalize(location, pb)
     }
 
     /// Inserts a progress bar after an existing one.
@@ -103,7 +240,27 @@ impl MultiProgress {
     /// remote draw target that is intercepted by the multi progress
     /// object overriding custom `ProgressDrawTarget` settings.
     pub fn insert_after(&self, after: &ProgressBar, pb: ProgressBar) -> ProgressBar {
-        self.internalize(InsertLocation::After(after), pb)
+        let location = match after.index() {
+            Some(idx) => InsertLocation::After(idx, after),
+            None => InsertLocation::End,
+        };
+        self.internalize(location, pb)
+    }
+
+    /// Adds a progress bar showing the total progress of all other progress bars.
+    ///
+    /// The position and length of the returned bar are the sums of the positions and lengths of
+    /// the other bars in this `MultiProgress`. Bars that get removed keep contributing their last
+    /// reported progress, so the totals don't go backwards. The returned bar is redrawn with the
+    /// new totals whenever the progress of another bar changes, at most once per refresh interval
+    /// of the draw target (or every 100ms without a refresh rate). On `wasm32` targets the totals
+    /// are only picked up when the returned bar is drawn.
+    ///
+    /// Only one total bar is tracked; calling this again makes the new bar the total bar.
+    pub fn total_bar(&self) -> ProgressBar {
+        let pb = self.add(ProgressBar::new(0));
+        self.state.write().unwrap().total_idx = pb.index();
+        pb
     }
 
     /// Removes a progress bar.
@@ -112,28 +269,312 @@ impl MultiProgress {
     /// by the methods `MultiProgress::insert` or `MultiProgress::add`.
     /// If the passed progress bar does not satisfy the condition above,
     /// the `remove` method does nothing.
+    ///
+    /// The lines of the progress bar are cleared right away, and its slot is reused by progress
+    /// bars added later. The removed progress bar is hidden, so it never draws over the progress
+    /// bar taking its slot; set another draw target to show it again.
     pub fn remove(&self, pb: &ProgressBar) {
-        let idx = match &pb.state().draw_target.remote() {
+        // The progress bar stays locked until its draw target is replaced, so it can't draw into
+        // its slot once the slot is freed
+        let mut bar = pb.state();
+        let idx = match bar.draw_target.remote() {
             Some((state, idx)) => {
                 // Check that this progress bar is owned by the current MultiProgress.
                 assert!(Arc::ptr_eq(&self.state, state));
-                *idx
+                idx
             }
             _ => return,
         };
 
-        self.state.write().unwrap().remove_idx(idx);
+        #[cfg(feature = "metrics")]
+        bar.state.clear_metrics();
+        let events = {
+            let mut state = self.state.write().unwrap();
+            state.remove_idx(idx);
+            bar.draw_target = ProgressDrawTarget::hidden();
+            let _ = state.draw(true, Instant::now());
+            state.events.clone()
+        };
+        drop(bar);
+        events.dispatch();
     }
 
     fn internalize(&self, location: InsertLocation, pb: ProgressBar) -> ProgressBar {
         let idx = self.state.write().unwrap().insert(location);
-        pb.set_draw_target(ProgressDrawTarget::new_remote(self.state.clone(), idx));
+        self.attach(idx, &pb);
         pb
     }
 
+    fn attach(&self, idx: usize, pb: &ProgressBar) {
+        let events = {
+            let mut state = self.state.write().unwrap();
+            state.bars[idx] = pb.downgrade();
+            state.all_done = false;
+            state.emit(MultiProgressEvent::Added(pb.downgrade()));
+            state.events.clone()
+        };
+        pb.set_draw_target(ProgressDrawTarget::new_remote(self.state.clone(), idx));
+        events.dispatch();
+    }
+
+    /// Returns the number of progress bars in this `MultiProgress`, including its total bar
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().ordering.len()
+    }
+
+    /// Returns `true` if no progress bars are in this `MultiProgress`
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns weak references to the progress bars in this `MultiProgress`, in display order
+    ///
+    /// This is a snapshot: progress bars added or removed later are not reflected in the
+    /// returned iterator.
+    pub fn iter(&self) -> impl Iterator<Item = WeakProgressBar> {
+        let state = self.state.read().unwrap();
+        let bars = state
+            .ordering
+            .iter()
+            .map(|&idx| state.bars[idx].clone())
+            .collect::<Vec<_>>();
+        bars.into_iter()
+    }
+
+    /// Returns `true` if all progress bars in this `MultiProgress` are finished
+    ///
+    /// Progress bars that have been dropped count as finished. The total bar (see
+    /// [`MultiProgress::total_bar()`]) is not taken into account.
+    pub fn is_done(&self) -> bool {
+        let total = self.state.read().unwrap().total_idx;
+        // Collected first, as progress bars must not be locked while holding the state lock
+        let bars = self.iter().collect::<Vec<_>>();
+        bars.iter()
+            .filter_map(WeakProgressBar::upgrade)
+            .filter(|pb| pb.index() != total)
+            .all(|pb| pb.is_finished())
+    }
+
+    /// Clears the progress bars from the terminal
+    ///
+    /// If they are rendered on the alternate screen (see
+    /// [`MultiProgress::set_alternate_screen()`]), the primary screen is restored instead.
     pub fn clear(&self) -> io::Result<()> {
         self.state.write().unwrap().clear(Instant::now())
     }
+
+    /// Prints a log line above all progress bars
+    ///
+    /// If the draw target is hidden, this does nothing, like [`ProgressBar::println()`].
+    pub fn println<I: AsRef<str>>(&self, msg: I) -> io::Result<()> {
+        let mut state = self.state.write().unwrap();
+        state
+            .queue
+            .lock()
+            .unwrap()
+            .extend(msg.as_ref().lines().map(Into::into));
+        state.draw(true, Instant::now())
+    }
+
+    /// Hides all progress bars temporarily, executes `f`, then redraws them
+    ///
+    /// Useful for external code that writes to the standard output. Unlike
+    /// [`ProgressBar::suspend()`], the progress bars aren't locked while `f` runs, so other
+    /// threads can keep updating them. Lines they print with [`ProgressBar::println()`]
+    /// meanwhile are buffered, and printed in order once the progress bars are redrawn.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::{MultiProgress, ProgressBar};
+    /// let mp = MultiProgress::new();
+    /// let pb = mp.add(ProgressBar::new(3));
+    /// mp.suspend(|| {
+    ///     println!("Log message");
+    /// });
+    /// ```
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        let suspended = self.state.write().unwrap().suspend(Instant::now());
+        let ret = f();
+        self.state
+            .write()
+            .unwrap()
+            .resume(suspended, Instant::now());
+        ret
+    }
+
+    /// Calls `f` whenever a progress bar is added, finished or removed, and when all progress
+    /// bars are finished
+    ///
+    /// This allows e.g. to stop waiting for the progress bars once they are all done, instead of
+    /// polling [`MultiProgress::is_done()`].
+    ///
+    /// `f` is called once the internal locks of the `MultiProgress` and of the progress bar the
+    /// event is about are released, so it may call back into either of them.
+    ///
+    /// ```rust
+    /// # use std::sync::mpsc;
+    /// # use indicatif::{MultiProgress, MultiProgressEvent, ProgressBar, ProgressDrawTarget};
+    /// let mp = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+    /// let (tx, rx) = mpsc::sync_channel(1);
+    /// mp.on_event(move |event| {
+    ///     if let MultiProgressEvent::AllDone = event {
+    ///         let _ = tx.try_send(());
+    ///     }
+    /// });
+    /// let pb = mp.add(ProgressBar::new(10));
+    /// std::thread::spawn(move || pb.finish());
+    /// rx.recv().unwrap();
+    /// ```
+    pub fn on_event(&self, f: impl Fn(&MultiProgressEvent) + Send + Sync + 'static) {
+        let events = self.state.read().unwrap().events.clone();
+        events.handlers.lock().unwrap().push(Arc::new(f));
+    }
+}
+
+/// A change of the progress bars in a [`MultiProgress`], see [`MultiProgress::on_event()`]
+#[derive(Clone, Debug)]
+pub enum MultiProgressEvent {
+    /// A progress bar was added
+    Added(WeakProgressBar),
+    /// A progress bar was finished
+    Finished(WeakProgressBar),
+    /// A progress bar was removed
+    Removed(WeakProgressBar),
+    /// All progress bars are finished, apart from the total bar (see
+    /// [`MultiProgress::total_bar()`])
+    AllDone,
+}
+
+type EventHandler = Arc<dyn Fn(&MultiProgressEvent) + Send + Sync>;
+
+/// Events of a [`MultiProgress`] waiting to be passed to the functions registered with
+/// [`MultiProgress::on_event()`]
+///
+/// Events are queued while the `MultiProgress` and the progress bar are locked and dispatched
+/// once both locks are released, so that the functions can call back into them.
+#[derive(Default)]
+pub(crate) struct EventQueue {
+    handlers: Mutex<Vec<EventHandler>>,
+    pending: Mutex<VecDeque<MultiProgressEvent>>,
+    /// Held while dispatching, so that the events are passed on in order
+    dispatching: Mutex<()>,
+}
+
+impl EventQueue {
+    fn push(&self, event: MultiProgressEvent) {
+        if !self.handlers.lock().unwrap().is_empty() {
+            self.pending.lock().unwrap().push_back(event);
+        }
+    }
+
+    /// Passes the pending events on, must not be called while holding any lock of the
+    /// `MultiProgress` or its progress bars.
+    pub(crate) fn dispatch(&self) {
+        loop {
+            {
+                let _dispatching = match self.dispatching.try_lock() {
+                    Ok(guard) => guard,
+                    Err(TryLockError::Poisoned(err)) => err.into_inner(),
+                    // Another thread, or a function calling back into the `MultiProgress`, is
+                    // dispatching already and will pass on the new events
+                    Err(TryLockError::WouldBlock) => return,
+                };
+                loop {
+                    let event = match self.pending.lock().unwrap().pop_front() {
+                        Some(event) => event,
+                        None => break,
+                    };
+                    let handlers = self.handlers.lock().unwrap().clone();
+                    for f in &handlers {
+                        f(&event);
+                    }
+                }
+            }
+
+            // Events queued after the last check by a thread that gave up on dispatching
+            if self.pending.lock().unwrap().is_empty() {
+                return;
+            }
+        }
+    }
+}
+
+/// Work left over by a progress bar of a multi progress, done once the progress bar is unlocked
+pub(crate) struct AfterUnlock {
+    pub(crate) state: Arc<RwLock<MultiProgressState>>,
+    pub(crate) events: Arc<EventQueue>,
+    pub(crate) skipped: Arc<Mutex<Vec<SkippedDraw>>>,
+}
+
+impl AfterUnlock {
+    /// Draws the frames of the progress bars whose draws were skipped, then dispatches the
+    /// pending events
+    pub(crate) fn run(self) {
+        let pending = !self.skipped.lock().unwrap().is_empty();
+        // Otherwise whoever is drawing the multi progress draws them once it's done
+        if pending {
+            if let Ok(mut state) = self.state.try_write() {
+                let _ = state.draw(true, Instant::now());
+            }
+        }
+        self.events.dispatch();
+    }
+}
+
+/// A frame of a progress bar of a multi progress, laid out while another thread was drawing the
+/// multi progress
+///
+/// The frame is drawn by that thread once it's done, see [`MultiProgressState::draw()`].
+#[derive(Debug)]
+pub(crate) struct SkippedDraw {
+    pub(crate) idx: usize,
+    pub(crate) draw_state: ProgressDrawState,
+    /// The progress recorded by [`MultiProgressState::sync_progress()`] when drawing
+    progress: BarProgress,
+}
+
+impl SkippedDraw {
+    pub(crate) fn new(idx: usize) -> Self {
+        Self {
+            idx,
+            draw_state: ProgressDrawState::new(Vec::new(), false),
+            progress: BarProgress::default(),
+        }
+    }
+
+    pub(crate) fn record_progress(&mut self, state: &ProgressState) {
+        self.progress = BarProgress::of(state);
+    }
+}
+
+/// The progress of a progress bar a multi progress keeps track of
+#[derive(Debug, Default)]
+struct BarProgress {
+    pos: u64,
+    len: u64,
+    fraction: f32,
+    eta: Duration,
+    finished: bool,
+}
+
+impl BarProgress {
+    fn of(state: &ProgressState) -> Self {
+        Self {
+            pos: state.pos,
+            len: state.len,
+            fraction: state.fraction(),
+            eta: state.eta(),
+            finished: state.is_finished(),
+        }
+    }
+}
+
+impl fmt::Debug for EventQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventQueue")
+            .field("handlers", &self.handlers.lock().unwrap().len())
+            .field("pending", &self.pending.lock().unwrap().len())
+            .finish()
+    }
 }
 
 #[derive(Debug)]
@@ -145,14 +586,57 @@ pub(crate) struct MultiProgressState {
     free_set: Vec<usize>,
     /// Indices to the `draw_states` to maintain correct visual order
     ordering: Vec<usize>,
+    /// Tree level of each progress bar, indexed like `draw_states`
+    levels: Vec<usize>,
+    /// Last reported `(pos, len)` of each progress bar, indexed like `draw_states`
+    progress: Vec<(u64, u64)>,
+    /// Last reported completion and ETA of each progress bar, indexed like `draw_states`
+    estimates: Vec<(f32, Duration)>,
+    /// Whether each progress bar is finished, indexed like `draw_states`
+    finished: Vec<bool>,
+    /// Weak references to the progress bars, indexed like `draw_states`
+    bars: Vec<WeakProgressBar>,
+    /// When the progress of each bar last changed, indexed like `draw_states`
+    updated: Vec<Option<Instant>>,
+    /// Order in which the progress bars are drawn
+    sort: SortPolicy,
+    /// Accumulated `(pos, len)` of removed progress bars
+    removed_progress: (u64, u64),
+    /// Index of the progress bar showing the total progress, if any
+    total_idx: Option<usize>,
+    /// Time after which finished progress bars are dimmed
+    dim_finished: Option<Duration>,
     /// Target for draw operation for MultiProgress
     draw_target: ProgressDrawTarget,
-    /// Whether or not to just move cursor instead of clearing lines
-    move_cursor: bool,
+    /// How previously drawn lines are replaced
+    strategy: DrawStrategy,
     /// Controls how the multi progress is aligned if some of its progress bars get removed, default is `Top`
     alignment: MultiProgressAlignment,
+    /// Maximum width of the progress bars, if narrower than the draw target
+    max_width: Option<usize>,
     /// Orphaned lines are carried over across draw operations
     orphan_lines: Vec<String>,
+    /// Whether the progress bars are rendered on the alternate screen
+    alternate_screen: bool,
+    /// Lines printed while on the alternate screen, written to the primary screen later
+    queued_lines: Vec<String>,
+    /// Lines printed by progress bars, written on the next draw
+    ///
+    /// The mutex is only held to add lines or take them all, never while drawing, so printing
+    /// doesn't wait for the thread drawing the multi progress.
+    pub(crate) queue: Arc<Mutex<Vec<String>>>,
+    /// Frames of the progress bars whose draws were skipped while another thread was drawing
+    ///
+    /// Like `queue`, the mutex is never held while drawing.
+    pub(crate) skipped: Arc<Mutex<Vec<SkippedDraw>>>,
+    /// Events about added, finished and removed progress bars, dispatched once unlocked
+    pub(crate) events: Arc<EventQueue>,
+    /// Whether [`MultiProgressEvent::AllDone`] was emitted since a progress bar was last added
+    all_done: bool,
+    /// Whether drawing is suspended, printed lines are kept in `queue` meanwhile
+    pub(crate) suspended: bool,
+    /// The multi progress itself, to schedule draws with the ticker
+    this: Weak<RwLock<MultiProgressState>>,
 }
 
 impl MultiProgressState {
@@ -161,39 +645,253 @@ impl MultiProgressState {
             draw_states: vec![],
             free_set: vec![],
             ordering: vec![],
+            levels: vec![],
+            progress: vec![],
+            estimates: vec![],
+            finished: vec![],
+            bars: vec![],
+            updated: vec![],
+            sort: SortPolicy::default(),
+            removed_progress: (0, 0),
+            total_idx: None,
+            dim_finished: None,
             draw_target,
-            move_cursor: false,
+            strategy: DrawStrategy::default(),
             alignment: Default::default(),
+            max_width: None,
             orphan_lines: Vec::new(),
+            alternate_screen: false,
+            queued_lines: Vec::new(),
+            queue: Arc::default(),
+            skipped: Arc::default(),
+            events: Arc::default(),
+            all_done: false,
+            suspended: false,
+            this: Weak::new(),
         }
     }
 
-    pub(crate) fn draw(&mut self, mut force_draw: bool, now: Instant) -> io::Result<()> {
+    /// Stops drawing the progress bars for good, once drawing them failed too often
+    pub(crate) fn hide(&mut self) {
+        self.draw_target = ProgressDrawTarget::hidden();
+    }
+
+    /// Clears the progress bars and stops drawing them until [`MultiProgressState::resume()`]
+    ///
+    /// Returns whether drawing was already suspended.
+    pub(crate) fn suspend(&mut self, now: Instant) -> bool {
+        if !self.suspended {
+            let _ = self.clear(now);
+        }
+        mem::replace(&mut self.suspended, true)
+    }
+
+    /// Restores the suspension state returned by [`MultiProgressState::suspend()`], and redraws
+    /// the progress bars and the lines printed meanwhile if drawing is no longer suspended
+    pub(crate) fn resume(&mut self, suspended: bool, now: Instant) {
+        self.suspended = suspended;
+        if !suspended {
+            let _ = self.draw(true, now);
+        }
+    }
+
+    fn emit(&self, event: MultiProgressEvent) {
+        self.events.push(event);
+    }
+
+    /// Records whether the progress bar at `idx` is finished, emitting the matching events.
+    pub(crate) fn set_finished(&mut self, idx: usize, finished: bool) {
+        let ignored = self.total_idx == Some(idx) || self.free_set.contains(&idx);
+        if ignored || self.finished[idx] == finished {
+            return;
+        }
+
+        self.finished[idx] = finished;
+        match finished {
+            true => {
+                self.emit(MultiProgressEvent::Finished(self.bars[idx].clone()));
+                self.check_all_done();
+            }
+            false => self.all_done = false,
+        }
+    }
+
+    fn check_all_done(&mut self) {
+        let total = self.total_idx;
+        let done = self
+            .ordering
+            .iter()
+            .filter(|&&i| Some(i) != total)
+            .all(|&i| self.finished[i]);
+        if done && !self.all_done {
+            self.all_done = true;
+            self.emit(MultiProgressEvent::AllDone);
+        }
+    }
+
+    fn leave_alternate_screen(&mut self) -> io::Result<()> {
+        self.queued_lines.append(&mut self.orphan_lines);
+        let lines = mem::take(&mut self.queued_lines);
+        self.draw_target.leave_alternate_screen(&lines)
+    }
+
+    /// Draws the progress bars, along with the frames of those whose draws were skipped while
+    /// the multi progress was locked
+    ///
+    /// The skipped frames are drawn right away, even if the draw target is rate limited, so
+    /// that they don't stay stale until the next update.
+    pub(crate) fn draw(&mut self, force_draw: bool, now: Instant) -> io::Result<()> {
+        let force_draw = self.apply_skipped(now) || force_draw;
+        self.draw_bars(force_draw, now)?;
+        // Progress bars updated while this thread was drawing
+        if self.apply_skipped(now) {
+            self.draw_bars(true, now)?;
+        }
+        // A draw dropped to keep draws aligned to frames is done at the start of the next frame
+        if let Some(at) = self.draw_target.take_pending_frame() {
+            ticker().schedule_draw(Scheduled::Multi(self.this.clone()), at);
+        }
+        Ok(())
+    }
+
+    /// Takes over the frames of the progress bars whose draws were skipped, returns whether
+    /// there were any
+    fn apply_skipped(&mut self, now: Instant) -> bool {
+        let skipped = mem::take(&mut *self.skipped.lock().unwrap());
+        let applied = !skipped.is_empty();
+        for frame in skipped {
+            let idx = frame.idx;
+            // Removed while the frame was laid out
+            if self.free_set.contains(&idx) {
+                continue;
+            }
+            if self.total_idx != Some(idx) {
+                self.record_progress(idx, &frame.progress, now);
+            }
+            let mut draw_state = self.draw_state(idx);
+            draw_state.lines = frame.draw_state.lines;
+            draw_state.finished_at = frame.draw_state.finished_at;
+            draw_state.progress = frame.draw_state.progress;
+        }
+        applied
+    }
+
+    fn draw_bars(&mut self, mut force_draw: bool, now: Instant) -> io::Result<()> {
+        // Keep printed lines queued, so they are written in order once drawing resumes
+        if self.suspended {
+            return Ok(());
+        }
+
+        let queued = mem::take(&mut *self.queue.lock().unwrap());
         // the rest from here is only drawing, we can skip it.
         if self.draw_target.is_hidden() {
             return Ok(());
         }
 
+        self.orphan_lines.extend(queued);
+
+        // The final frame is drawn on the primary screen, so it stays in the scrollback
+        if self.alternate_screen && self.all_done {
+            self.alternate_screen = false;
+            self.leave_alternate_screen()?;
+        }
+        if self.alternate_screen {
+            self.queued_lines.append(&mut self.orphan_lines);
+        }
+
         let orphan_lines_count = self.orphan_lines.len();
         force_draw |= orphan_lines_count > 0;
         let mut drawable = match self.draw_target.drawable(force_draw, now) {
             Some(drawable) => drawable,
             None => return Ok(()),
         };
+        let dim_style = match drawable.colors() {
+            Some(colors) => Style::new().dim().force_styling(colors),
+            None => Style::new().dim(),
+        };
 
         let mut draw_state = drawable.state();
         draw_state.orphan_lines = orphan_lines_count;
+        draw_state.strategy = self.strategy;
+        draw_state.alignment = self.alignment;
 
         // Make orphaned lines appear at the top, so they can be properly forgotten.
         draw_state.lines.append(&mut self.orphan_lines);
 
-        for index in self.ordering.iter() {
-            if let Some(state) = &self.draw_states[*index] {
-                draw_state.lines.extend_from_slice(&state.lines[..]);
+        let (draw_states, levels) = (&self.draw_states, &self.levels);
+        let mut visible = self
+            .ordering
+            .iter()
+            .filter_map(|&idx| match &draw_states[idx] {
+                Some(state) if !state.lines.is_empty() => Some((idx, state, levels[idx])),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let (progress, finished, updated) = (&self.progress, &self.finished, &self.updated);
+        sort_visible(&self.sort, &mut visible, |idx| BarInfo {
+            position: progress[idx].0,
+            length: progress[idx].1,
+            finished: finished[idx],
+            last_update: updated[idx],
+        });
+        let visible = visible
+            .into_iter()
+            .map(|(_, state, level)| (state, level))
+            .collect::<Vec<_>>();
+        let levels = visible.iter().map(|(_, level)| *level).collect::<Vec<_>>();
+        draw_state.progress = match self.total_idx.and_then(|idx| draw_states[idx].as_ref()) {
+            Some(total) => total.progress,
+            None => SystemProgress::average(visible.iter().map(|(state, _)| state.progress)),
+        };
+        // The first time a progress bar that isn't dimmed yet is due to be dimmed
+        let mut next_dim = None::<Instant>;
+        for (i, (state, level)) in visible.iter().enumerate() {
+            let dim = match (self.dim_finished, state.finished_at) {
+                (Some(after), Some(finished_at)) => {
+                    let at = finished_at + after;
+                    if at > now {
+                        next_dim = Some(next_dim.map_or(at, |next| next.min(at)));
+                    }
+                    at <= now
+                }
+                _ => false,
+            };
+            let lines = state.lines.iter().map(|line| match dim {
+                true => dim_style.apply_to(strip_ansi_codes(line)).to_string(),
+                false => line.clone(),
+            });
+
+            if *level == 0 {
+                draw_state.lines.extend(lines);
+                continue;
+            }
+
+            let mut guides = String::new();
+            for ancestor in 1..*level {
+                guides.push_str(match has_sibling_after(&levels, i, ancestor) {
+                    true => "│  ",
+                    false => "   ",
+                });
+            }
+
+            let last = !has_sibling_after(&levels, i, *level);
+            for (n, line) in lines.enumerate() {
+                let guide = match (n, last) {
+                    (0, false) => "├─ ",
+                    (0, true) => "└─ ",
+                    (_, false) => "│  ",
+                    (_, true) => "   ",
+                };
+                draw_state
+                    .lines
+                    .push(format!("{}{}{}", guides, guide, line));
             }
         }
 
         drop(draw_state);
+        if let Some(at) = next_dim {
+            ticker().schedule_draw(Scheduled::Multi(self.this.clone()), at);
+        }
         drawable.draw()
     }
 
@@ -204,7 +902,7 @@ impl MultiProgressState {
             Some(inner) => {
                 *inner = Some(ProgressDrawState::new(Vec::new(), false));
                 let state = inner.as_mut().unwrap();
-                state.move_cursor = self.move_cursor;
+                state.strategy = self.strategy;
                 state.alignment = self.alignment;
                 state
             }
@@ -214,18 +912,139 @@ impl MultiProgressState {
         DrawStateWrapper::for_multi(state, orphans)
     }
 
-    pub(crate) fn width(&self) -> usize {
-        self.draw_target.width()
+    /// Records the progress of the bar at `idx`, or updates it with the totals if it's the total
+    /// bar.
+    pub(crate) fn sync_progress(&mut self, idx: usize, state: &mut ProgressState, now: Instant) {
+        if self.total_idx != Some(idx) {
+            self.record_progress(idx, &BarProgress::of(state), now);
+        }
+        state.bar_counts = Some(self.bar_counts());
+        state.group = Some(self.group_progress(idx));
+        if self.total_idx != Some(idx) {
+            return;
+        }
+
+        let (mut pos, mut len) = self.removed_progress;
+        for &i in self.ordering.iter().filter(|&&i| i != idx) {
+            pos = pos.saturating_add(self.progress[i].0);
+            len = len.saturating_add(self.progress[i].1);
+        }
+
+        state.update(now, |state| {
+            state.pos = pos;
+            state.len = len;
+        });
+    }
+
+    fn record_progress(&mut self, idx: usize, progress: &BarProgress, now: Instant) {
+        if self.progress[idx] != (progress.pos, progress.len) {
+            self.progress[idx] = (progress.pos, progress.len);
+            self.updated[idx] = Some(now);
+            self.schedule_total_draw(now);
+        }
+        self.estimates[idx] = (progress.fraction, progress.eta);
+        self.set_finished(idx, progress.finished);
+    }
+
+    /// Redraws the total bar with the new totals, once the refresh rate allows it
+    fn schedule_total_draw(&self, now: Instant) {
+        let total = match self.total_idx {
+            Some(idx) => self.bars[idx].clone(),
+            None => return,
+        };
+
+        let interval = self
+            .draw_target
+            .refresh_interval(now)
+            .unwrap_or(TOTAL_REFRESH_INTERVAL);
+        ticker().schedule_draw(Scheduled::Bar(total), next_frame(now, interval));
+    }
+
+    /// Counts the active and finished progress bars, excluding the total bar.
+    fn bar_counts(&self) -> BarCounts {
+        let bars = self.ordering.iter().filter(|&&i| self.total_idx != Some(i));
+        let (total, finished) = bars.fold((0, 0), |(total, finished), &i| {
+            (total + 1, finished + usize::from(self.finished[i]))
+        });
+        BarCounts {
+            active: total - finished,
+            finished,
+            total,
+        }
+    }
+
+    /// Summarizes the progress of all bars, excluding the total bar, and of the parent of the
+    /// progress bar at `idx`.
+    fn group_progress(&self, idx: usize) -> GroupProgress {
+        let level = self.levels[idx];
+        let parent = match self.ordering.iter().position(|&i| i == idx) {
+            Some(at) if level > 0 => self.ordering[..at]
+                .iter()
+                .rev()
+                .find(|&&i| self.levels[i] < level),
+            _ => None,
+        };
+
+        let (mut pos, mut len) = self.removed_progress;
+        let mut eta = Duration::new(0, 0);
+        for &i in self.ordering.iter().filter(|&&i| self.total_idx != Some(i)) {
+            pos = pos.saturating_add(self.progress[i].0);
+            len = len.saturating_add(self.progress[i].1);
+            if !self.finished[i] {
+                eta = eta.max(self.estimates[i].1);
+            }
+        }
+
+        GroupProgress {
+            parent_fraction: parent.map(|&i| self.estimates[i].0),
+            fraction: match len {
+                0 => 1.0,
+                len => pos.min(len) as f32 / len as f32,
+            },
+            eta,
+        }
+    }
+
+    /// Returns the width available to the progress bar at `idx`, excluding its tree guides.
+    pub(crate) fn width(&self, idx: usize) -> usize {
+        let indent = self.levels.get(idx).copied().unwrap_or(0) * 3;
+        let width = self.draw_target.width();
+        let width = self.max_width.map_or(width, |max| width.min(max));
+        width.saturating_sub(indent)
+    }
+
+    pub(crate) fn colors(&self) -> Option<bool> {
+        self.draw_target.colors()
+    }
+
+    pub(crate) fn ring_bell(&self) {
+        self.draw_target.ring_bell()
     }
 
     fn insert(&mut self, location: InsertLocation) -> usize {
+        self.insert_with_level(location, 0)
+    }
+
+    fn insert_with_level(&mut self, location: InsertLocation, level: usize) -> usize {
         let idx = match self.free_set.pop() {
             Some(idx) => {
                 self.draw_states[idx] = None;
+                self.levels[idx] = level;
+                self.progress[idx] = (0, 0);
+                self.estimates[idx] = (0.0, Duration::new(0, 0));
+                self.finished[idx] = false;
+                self.bars[idx] = WeakProgressBar::new();
+                self.updated[idx] = None;
                 idx
             }
             None => {
                 self.draw_states.push(None);
+                self.levels.push(level);
+                self.progress.push((0, 0));
+                self.estimates.push((0.0, Duration::new(0, 0)));
+                self.finished.push(false);
+                self.bars.push(WeakProgressBar::new());
+                self.updated.push(None);
                 self.draw_states.len() - 1
             }
         };
@@ -240,15 +1059,18 @@ impl MultiProgressState {
                 let pos = self.ordering.len().saturating_sub(pos);
                 self.ordering.insert(pos, idx);
             }
-            InsertLocation::After(after) => {
-                let after_idx = after.index().unwrap();
-                let pos = self.ordering.iter().position(|i| *i == after_idx).unwrap();
-                self.ordering.insert(pos + 1, idx);
-            }
-            InsertLocation::Before(before) => {
-                let before_idx = before.index().unwrap();
-                let pos = self.ordering.iter().position(|i| *i == before_idx).unwrap();
-                self.ordering.insert(pos, idx);
+            // The anchor may have been removed since its index was looked up, and its slot
+            // reused, then the progress bar is added at the end
+            InsertLocation::After(anchor, pb) | InsertLocation::Before(anchor, pb) => {
+                let pos = match self.bars[anchor].is(pb) {
+                    true => self.ordering.iter().position(|&i| i == anchor),
+                    false => None,
+                };
+                match (pos, location) {
+                    (Some(pos), InsertLocation::After(..)) => self.ordering.insert(pos + 1, idx),
+                    (Some(pos), _) => self.ordering.insert(pos, idx),
+                    (None, _) => self.ordering.push(idx),
+                }
             }
         }
 
@@ -261,6 +1083,12 @@ impl MultiProgressState {
     }
 
     fn clear(&mut self, now: Instant) -> io::Result<()> {
+        // The primary screen doesn't show the progress bars, so there is nothing else to clear
+        if self.alternate_screen {
+            self.alternate_screen = false;
+            return self.leave_alternate_screen();
+        }
+
         match self.draw_target.drawable(true, now) {
             Some(drawable) => drawable.clear(),
             None => Ok(()),
@@ -273,13 +1101,32 @@ impl MultiProgressState {
         }
 
         self.draw_states[idx].take();
+        self.skipped
+            .lock()
+            .unwrap()
+            .retain(|frame| frame.idx != idx);
+        let bar = mem::take(&mut self.bars[idx]);
         self.free_set.push(idx);
+        let is_total = self.total_idx == Some(idx);
+        match is_total {
+            true => self.total_idx = None,
+            false => {
+                let (pos, len) = self.progress[idx];
+                self.removed_progress.0 = self.removed_progress.0.saturating_add(pos);
+                self.removed_progress.1 = self.removed_progress.1.saturating_add(len);
+            }
+        }
         self.ordering.retain(|&x| x != idx);
 
         assert!(
             self.len() == self.ordering.len(),
             "Draw state is inconsistent"
         );
+
+        if !is_total {
+            self.emit(MultiProgressEvent::Removed(bar));
+            self.check_all_done();
+        }
     }
 
     fn len(&self) -> usize {
@@ -287,6 +1134,61 @@ impl MultiProgressState {
     }
 }
 
+impl Drop for MultiProgressState {
+    fn drop(&mut self) {
+        self.suspended = false;
+        if !self.queue.lock().unwrap().is_empty() {
+            let _ = self.draw(true, Instant::now());
+        }
+        if self.alternate_screen {
+            let _ = self.leave_alternate_screen();
+        }
+        let _ = self.draw_target.release_scroll_region();
+    }
+}
+
+/// Reorders the visible `(idx, _, level)` entries according to the sort policy, moving child bars
+/// along with their parent.
+fn sort_visible<T>(
+    sort: &SortPolicy,
+    visible: &mut Vec<(usize, T, usize)>,
+    info: impl Fn(usize) -> BarInfo,
+) {
+    let compare: &dyn Fn(&BarInfo, &BarInfo) -> Ordering = match sort {
+        SortPolicy::Insertion => return,
+        SortPolicy::MostRecentlyUpdatedFirst => &|a, b| b.last_update.cmp(&a.last_update),
+        SortPolicy::ByProgressDescending => &|a, b| {
+            let fraction = |info: &BarInfo| match info.length {
+                0 => 0.0,
+                len => info.position as f64 / len as f64,
+            };
+            fraction(b)
+                .partial_cmp(&fraction(a))
+                .unwrap_or(Ordering::Equal)
+        },
+        SortPolicy::Custom(compare) => &**compare,
+    };
+
+    let mut groups: Vec<Vec<(usize, T, usize)>> = Vec::new();
+    for entry in visible.drain(..) {
+        match groups.last_mut() {
+            Some(group) if entry.2 > 0 => group.push(entry),
+            _ => groups.push(vec![entry]),
+        }
+    }
+
+    groups.sort_by(|a, b| compare(&info(a[0].0), &info(b[0].0)));
+    visible.extend(groups.into_iter().flatten());
+}
+
+/// Returns true if a bar at `level` follows position `i` before the tree returns to a lower level.
+fn has_sibling_after(levels: &[usize], i: usize, level: usize) -> bool {
+    levels[i + 1..]
+        .iter()
+        .take_while(|&&l| l >= level)
+        .any(|&l| l == level)
+}
+
 /// Vertical alignment of a multi progress.
 ///
 /// The alignment controls how the multi progress is aligned if some of its progress bars get removed.
@@ -315,17 +1217,127 @@ impl Default for MultiProgressAlignment {
     }
 }
 
+/// Order in which the progress bars of a [`MultiProgress`] are drawn.
+#[derive(Clone)]
+pub enum SortPolicy {
+    /// Draw the progress bars in the order they were inserted (the default)
+    Insertion,
+    /// Draw the progress bars whose position or length changed most recently first
+    MostRecentlyUpdatedFirst,
+    /// Draw the progress bars that are furthest along first
+    ByProgressDescending,
+    /// Sort the progress bars with a custom comparator
+    Custom(BarComparator),
+}
+
+/// Compares two progress bars for [`SortPolicy::Custom`]
+pub type BarComparator = Arc<dyn Fn(&BarInfo, &BarInfo) -> Ordering + Send + Sync>;
+
+impl Default for SortPolicy {
+    fn default() -> Self {
+        Self::Insertion
+    }
+}
+
+impl fmt::Debug for SortPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Insertion => f.write_str("Insertion"),
+            Self::MostRecentlyUpdatedFirst => f.write_str("MostRecentlyUpdatedFirst"),
+            Self::ByProgressDescending => f.write_str("ByProgressDescending"),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// The progress of a bar in a [`MultiProgress`], passed to [`SortPolicy::Custom`] comparators.
+#[derive(Clone, Copy, Debug)]
+pub struct BarInfo {
+    /// Last drawn position of the progress bar
+    pub position: u64,
+    /// Last drawn length of the progress bar
+    pub length: u64,
+    /// Whether the progress bar is finished
+    pub finished: bool,
+    /// When the position or length last changed, `None` if it never did
+    pub last_update: Option<Instant>,
+}
+
+#[derive(Clone, Copy)]
 enum InsertLocation<'a> {
     End,
     Index(usize),
     IndexFromBack(usize),
-    After(&'a ProgressBar),
-    Before(&'a ProgressBar),
+    /// After the progress bar, which had the given index when it was looked up
+    After(usize, &'a ProgressBar),
+    /// Before the progress bar, which had the given index when it was looked up
+    Before(usize, &'a ProgressBar),
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{MultiProgress, ProgressBar, ProgressDrawTarget};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::{sort_visible, BarInfo, MultiProgressEvent, SortPolicy};
+    use crate::term_like::tests::Recorder;
+    use crate::{
+        ColorChoice, DrawStrategy, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle,
+    };
+
+    #[test]
+    fn lifecycle_events() {
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        mp.on_event({
+            let events = events.clone();
+            move |event| {
+                let name = match event {
+                    MultiProgressEvent::Added(_) => "added",
+                    MultiProgressEvent::Finished(_) => "finished",
+                    MultiProgressEvent::Removed(_) => "removed",
+                    MultiProgressEvent::AllDone => "all done",
+                };
+                events.lock().unwrap().push(name);
+            }
+        });
+
+        let pb1 = mp.add(ProgressBar::new(10));
+        let pb2 = mp.add(ProgressBar::new(10));
+        pb1.finish();
+        pb1.finish();
+        mp.remove(&pb2);
+        let pb3 = mp.add(ProgressBar::new(10));
+        pb3.abandon();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "added", "added", "finished", "removed", "all done", "added", "finished",
+                "all done"
+            ]
+        );
+    }
+
+    #[test]
+    fn event_handlers_can_call_back() {
+        let mp = Arc::new(MultiProgress::with_draw_target(ProgressDrawTarget::hidden()));
+        let positions = Arc::new(std::sync::Mutex::new(Vec::new()));
+        mp.on_event({
+            let (mp, positions) = (Arc::downgrade(&mp), positions.clone());
+            move |event| {
+                if let MultiProgressEvent::Finished(bar) = event {
+                    let (mp, bar) = (mp.upgrade().unwrap(), bar.upgrade().unwrap());
+                    positions.lock().unwrap().push((mp.len(), bar.position()));
+                }
+            }
+        });
+
+        let pb = mp.add(ProgressBar::new(10));
+        pb.inc(3);
+        pb.finish_at_current_pos();
+        assert_eq!(*positions.lock().unwrap(), [(1, 3)]);
+    }
 
     #[test]
     fn test_draw_delta_deadlock() {
@@ -401,9 +1413,10 @@ mod tests {
             _ => unreachable!(),
         }
 
+        // removed progress bars no longer refer to their slot
         assert_eq!(p0.index().unwrap(), 0);
-        assert_eq!(p1.index().unwrap(), 1);
-        assert_eq!(p2.index().unwrap(), 2);
+        assert_eq!(p1.index(), None);
+        assert_eq!(p2.index(), None);
         assert_eq!(p3.index().unwrap(), 3);
     }
 
@@ -483,6 +1496,414 @@ mod tests {
         assert_eq!(p6.index().unwrap(), 6);
     }
 
+    #[test]
+    fn multi_progress_tree_guides() {
+        use super::has_sibling_after;
+
+        let levels = [0, 1, 2, 2, 1, 2, 0];
+        assert!(has_sibling_after(&levels, 1, 1));
+        assert!(has_sibling_after(&levels, 2, 2));
+        assert!(!has_sibling_after(&levels, 3, 2));
+        assert!(!has_sibling_after(&levels, 4, 1));
+        assert!(!has_sibling_after(&levels, 5, 2));
+        assert!(has_sibling_after(&levels, 0, 0));
+    }
+
+    #[test]
+    fn multi_progress_total_bar() {
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let total = mp.total_bar();
+        total.set_draw_delta(0);
+        let p0 = mp.add(ProgressBar::new(10));
+        let p1 = mp.add(ProgressBar::new(20));
+        p0.set_draw_delta(0);
+        p1.set_draw_delta(0);
+
+        p0.inc(3);
+        p1.inc(5);
+        total.tick();
+        assert_eq!((total.position(), total.length()), (8, 30));
+
+        p0.inc(2);
+        mp.remove(&p1);
+        total.tick();
+        assert_eq!((total.position(), total.length()), (10, 30));
+    }
+
+    #[test]
+    fn total_bar_follows_updates() {
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let total = mp.total_bar();
+        let pb = mp.add(ProgressBar::new(10));
+        pb.set_draw_delta(0);
+
+        pb.inc(4);
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!((total.position(), total.length()), (4, 10));
+        assert_eq!(total.state().state.steady_tick, 0);
+    }
+
+    #[test]
+    fn multi_progress_bar_counts() {
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let total = mp.total_bar();
+        total.set_draw_delta(0);
+        total.set_style(
+            ProgressStyle::default_spinner()
+                .template("{active}/{total_bars} running, {finished} done"),
+        );
+        let bars = (0..3)
+            .map(|_| mp.add(ProgressBar::new(10)))
+            .collect::<Vec<_>>();
+        bars[0].finish();
+        total.inc(0);
+
+        let idx = total.index().unwrap();
+        let state = mp.state.read().unwrap();
+        let lines = &state.draw_states[idx].as_ref().unwrap().lines;
+        assert_eq!(lines[0], "2/3 running, 1 done");
+    }
+
+    #[test]
+    fn multi_progress_group_keys() {
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let parent = mp.add(ProgressBar::new(4));
+        let child = mp.add_with_level(ProgressBar::new(4), 1);
+        child.set_draw_delta(0);
+        child.set_style(
+            ProgressStyle::default_bar().template("{percent} {parent_percent} {group_percent}"),
+        );
+        let other = mp.add(ProgressBar::new(2));
+        other.set_style(ProgressStyle::default_bar().template("[{parent_percent}]"));
+
+        parent.set_position(1);
+        other.inc(2);
+        other.finish();
+        child.inc(3);
+
+        let state = mp.state.read().unwrap();
+        let lines = |pb: &ProgressBar| {
+            &state.draw_states[pb.index().unwrap()]
+                .as_ref()
+                .unwrap()
+                .lines
+        };
+        assert_eq!(lines(&child)[0], "75 25 60");
+        assert_eq!(lines(&other)[0], "[]");
+    }
+
+    #[test]
+    fn multi_progress_membership() {
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        assert!(mp.is_empty());
+        assert!(mp.is_done());
+
+        let p0 = mp.add(ProgressBar::new(1));
+        let p1 = mp.insert(0, ProgressBar::new(1));
+        let total = mp.total_bar();
+        assert_eq!(mp.len(), 3);
+        let bars = mp
+            .iter()
+            .map(|pb| pb.upgrade().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(bars[0].index(), p1.index());
+        assert_eq!(bars[1].index(), p0.index());
+        assert_eq!(bars[2].index(), total.index());
+
+        p0.finish();
+        assert!(!mp.is_done());
+        mp.remove(&p1);
+        assert_eq!(mp.len(), 2);
+        assert!(mp.is_done());
+    }
+
+    #[test]
+    fn multi_progress_sort() {
+        let now = Instant::now();
+        let info = |idx: usize| BarInfo {
+            position: [2, 8, 5, 0][idx],
+            length: 10,
+            finished: false,
+            last_update: [Some(now + Duration::from_secs(2)), None, Some(now), None][idx],
+        };
+        // the bar at index 1 has a child
+        let mut visible = vec![(0, (), 0), (1, (), 0), (3, (), 1), (2, (), 0)];
+        let mut order = |sort: SortPolicy| {
+            sort_visible(&sort, &mut visible, info);
+            visible.iter().map(|&(idx, _, _)| idx).collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(SortPolicy::ByProgressDescending), [1, 3, 2, 0]);
+        assert_eq!(order(SortPolicy::MostRecentlyUpdatedFirst), [0, 2, 1, 3]);
+        let ascending = Arc::new(|a: &BarInfo, b: &BarInfo| a.position.cmp(&b.position));
+        assert_eq!(order(SortPolicy::Custom(ascending)), [0, 2, 1, 3]);
+        assert_eq!(order(SortPolicy::Insertion), [0, 2, 1, 3]);
+    }
+
+    #[test]
+    fn multi_progress_draw_failures() {
+        use crate::term_like::tests::BrokenPipe;
+
+        let mp =
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(BrokenPipe)));
+        let pb1 = mp.add(ProgressBar::new(10));
+        let pb2 = mp.add(ProgressBar::new(10));
+        pb1.set_draw_failure_limit(2);
+        pb1.redraw(Instant::now());
+        assert!(!mp.state.read().unwrap().draw_target.is_hidden());
+        pb1.redraw(Instant::now());
+        assert!(pb1.last_draw_error().is_some());
+
+        // The bars stay in the MultiProgress, which stops drawing
+        assert!(mp.state.read().unwrap().draw_target.is_hidden());
+        assert_eq!(mp.state.read().unwrap().len(), 2);
+        assert_eq!(pb1.index(), Some(0));
+        pb2.set_position(5);
+        pb2.redraw(Instant::now());
+        assert!(pb2.last_draw_error().is_none());
+        pb1.finish();
+        pb2.finish();
+        assert!(mp.state.read().unwrap().all_done);
+    }
+
+    #[test]
+    fn multi_progress_scroll_strategy() {
+        let recorder = Arc::new(Recorder::default());
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(
+            recorder.clone(),
+        )));
+        mp.set_draw_strategy(DrawStrategy::Scroll);
+        let pb = mp.add(ProgressBar::new(10));
+        pb.set_style(ProgressStyle::default_bar().template("{pos}/{len}"));
+        pb.inc(1);
+        pb.println("log line");
+        pb.inc(1);
+
+        let written = recorder.0.lock().unwrap().concat();
+        assert!(written.starts_with("\n\x1b[1A\x1b7\x1b[10;1H\x1b[2K\x1b[1;9r\x1b[10;1H\x1b[2K"));
+        assert!(written.contains("log line\n\x1b7\x1b[10;1H\x1b[2K1/10\x1b8"));
+        drop(written);
+
+        pb.finish();
+        drop(pb);
+        drop(mp);
+        let written = recorder.0.lock().unwrap().concat();
+        assert!(written.ends_with("10/10\x1b8\x1b[r\x1b[10;1H\n"));
+
+        // Raw mode terminals get CRLF line endings
+        let recorder = Arc::new(Recorder::default());
+        let target =
+            ProgressDrawTarget::term_like(Box::new(recorder.clone())).with_crlf_line_endings();
+        let mp = MultiProgress::with_draw_target(target);
+        mp.set_draw_strategy(DrawStrategy::Scroll);
+        let pb = mp.add(ProgressBar::new(10));
+        pb.set_style(ProgressStyle::default_bar().template("{pos}/{len}"));
+        pb.inc(1);
+        pb.println("log line");
+        pb.finish();
+        drop(pb);
+        drop(mp);
+        let written = recorder.0.lock().unwrap().concat();
+        assert!(written.starts_with("\r\n\x1b[1A\x1b7"));
+        assert!(written.contains("log line\r\n\x1b7"));
+        assert!(written.ends_with("\x1b[r\x1b[10;1H\r\n"));
+    }
+
+    #[test]
+    fn multi_progress_width() {
+        let recorder = Arc::new(Recorder::default());
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(
+            recorder.clone(),
+        )));
+        mp.set_width(10);
+        let pb = mp.add(ProgressBar::new(10));
+        pb.set_style(ProgressStyle::default_bar().template("{wide_bar}"));
+        pb.tick();
+        assert!(recorder.0.lock().unwrap().iter().any(|s| s == "░░░░░░░░░░"));
+
+        // Wider than the terminal
+        mp.set_width(30);
+        pb.finish();
+        assert!(recorder.0.lock().unwrap().iter().any(|s| s.len() == 20 * 3));
+
+        let recorder = Arc::new(Recorder::default());
+        let target = ProgressDrawTarget::term_like(Box::new(recorder.clone())).with_width(5);
+        let pb = ProgressBar::with_draw_target(10, target);
+        pb.set_style(ProgressStyle::default_bar().template("{wide_bar}"));
+        pb.tick();
+        assert!(recorder.0.lock().unwrap().iter().any(|s| s == "░░░░░"));
+    }
+
+    #[test]
+    fn multi_progress_println_does_not_block() {
+        let recorder = Arc::new(Recorder::default());
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(
+            recorder.clone(),
+        )));
+        let pb = mp.add(ProgressBar::new(10));
+
+        // Another thread drawing the multi progress holds the lock
+        let guard = mp.state.write().unwrap();
+        pb.println("log line");
+        pb.inc(1);
+        drop(guard);
+        assert!(!recorder.0.lock().unwrap().iter().any(|s| s.contains("log")));
+
+        pb.inc(1);
+        assert!(recorder.0.lock().unwrap().iter().any(|s| s == "log line\n"));
+    }
+
+    #[test]
+    fn finished_bars_are_dimmed() {
+        for &(colors, dimmed) in &[
+            (ColorChoice::Always, "\x1b[2mdone\x1b[0m"),
+            (ColorChoice::Never, "done"),
+        ] {
+            let recorder = Arc::new(Recorder::default());
+            let target = ProgressDrawTarget::term_like(Box::new(recorder.clone()));
+            let mp = MultiProgress::with_draw_target(target.with_colors(colors));
+            mp.set_dim_finished(Some(Duration::from_millis(20)));
+            let style = ProgressStyle::default_bar().template("{msg:.green}");
+            let pb = mp.add(ProgressBar::new(10).with_style(style));
+            pb.finish_with_message("done");
+            let last = || {
+                let lines = recorder.0.lock().unwrap();
+                lines
+                    .iter()
+                    .rev()
+                    .find(|l| !l.trim().is_empty())
+                    .cloned()
+                    .unwrap()
+            };
+            if colors == ColorChoice::Always {
+                assert_ne!(last().trim_end(), dimmed);
+            }
+
+            // Dimmed without any further updates
+            thread::sleep(Duration::from_millis(200));
+            assert_eq!(last().trim_end(), dimmed);
+        }
+    }
+
+    #[test]
+    fn skipped_draws_are_caught_up() {
+        let recorder = Arc::new(Recorder::default());
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(
+            recorder.clone(),
+        )));
+        let style = ProgressStyle::default_bar().template("{pos}/{len}");
+        let pb1 = mp.add(ProgressBar::new(10).with_style(style.clone()));
+        let pb2 = mp.add(ProgressBar::new(10).with_style(style));
+        let drawn = |text| recorder.0.lock().unwrap().iter().any(|s| s.contains(text));
+
+        // Another thread drawing the multi progress holds the lock, and draws the skipped
+        // frame once it's done
+        let mut guard = mp.state.write().unwrap();
+        pb1.set_position(7);
+        assert!(!drawn("7/10"));
+        guard.draw(false, Instant::now()).unwrap();
+        drop(guard);
+        assert!(drawn("7/10"));
+
+        // Also when the multi progress is drawn without any progress bar being updated
+        let guard = mp.state.write().unwrap();
+        pb2.set_position(5);
+        drop(guard);
+        assert!(!drawn("5/10"));
+        mp.println("log line").unwrap();
+        assert!(drawn("5/10"));
+    }
+
+    #[test]
+    fn multi_progress_alternate_screen() {
+        let recorder = Arc::new(Recorder::default());
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(
+            recorder.clone(),
+        )));
+        let pb = mp.add(ProgressBar::new(10));
+        mp.set_alternate_screen(true);
+        pb.println("log line");
+        pb.inc(1);
+        assert!(!recorder.0.lock().unwrap().iter().any(|s| s.contains("log")));
+
+        drop(pb);
+        drop(mp);
+        let written = recorder.0.lock().unwrap();
+        let leave = written.iter().position(|s| s == "\x1b[?1049l").unwrap();
+        assert_eq!(written[leave + 1], "log line\n");
+    }
+
+    #[test]
+    fn alternate_screen_restored() {
+        let recorder = Arc::new(Recorder::default());
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(
+            recorder.clone(),
+        )));
+        let pb = mp.add(ProgressBar::new(10));
+        mp.set_alternate_screen(true);
+        mp.clear().unwrap();
+        let leaves = || {
+            let written = recorder.0.lock().unwrap();
+            written.iter().filter(|s| *s == "\x1b[?1049l").count()
+        };
+        assert_eq!(leaves(), 1);
+
+        mp.set_alternate_screen(true);
+        pb.finish();
+        assert_eq!(leaves(), 2);
+        pb.set_message("still drawn");
+        assert_eq!(leaves(), 2);
+    }
+
+    #[test]
+    fn switch_draw_target() {
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let p0 = mp.add(
+            ProgressBar::new(10)
+                .with_style(ProgressStyle::default_bar().template("{pos}/{len} {msg}")),
+        );
+        let p1 = mp.add(
+            ProgressBar::new(5).with_style(ProgressStyle::default_bar().template("{wide_bar}")),
+        );
+        p0.set_position(3);
+        p0.set_message("running");
+        p1.set_position(5);
+
+        let frames = Arc::new(std::sync::Mutex::new(Vec::new()));
+        mp.set_draw_target(ProgressDrawTarget::callback(16, {
+            let frames = frames.clone();
+            move |lines: &[String]| frames.lock().unwrap().push(lines.to_vec())
+        }));
+
+        let last = frames.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last, ["3/10 running", "████████████████"]);
+    }
+
+    #[test]
+    fn println_while_suspended() {
+        let frames = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::callback(20, {
+            let frames = frames.clone();
+            move |lines: &[String]| frames.lock().unwrap().push(lines.to_vec())
+        }));
+        let style = ProgressStyle::default_bar().template("{pos}/{len}");
+        let p0 = mp.add(ProgressBar::new(10).with_style(style.clone()));
+        let p1 = mp.add(ProgressBar::new(10).with_style(style));
+        p0.tick();
+
+        mp.suspend(|| {
+            frames.lock().unwrap().clear();
+            p0.println("first");
+            p1.inc(1);
+            mp.println("second").unwrap();
+            p1.suspend(|| p0.println("third"));
+            assert!(frames.lock().unwrap().is_empty());
+        });
+
+        let last = frames.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last, ["first", "second", "third", "0/10", "1/10"]);
+    }
+
     #[test]
     fn multi_progress_multiple_remove() {
         let mp = MultiProgress::new();
@@ -502,7 +1923,65 @@ mod tests {
         assert_eq!(state.free_set.last(), Some(&0));
 
         assert_eq!(state.ordering, vec![1]);
-        assert_eq!(p0.index().unwrap(), 0);
+        assert_eq!(p0.index(), None);
         assert_eq!(p1.index().unwrap(), 1);
     }
+
+    #[test]
+    fn removed_bars_dont_draw() {
+        let frames = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mp = MultiProgress::with_draw_target(ProgressDrawTarget::callback(20, {
+            let frames = frames.clone();
+            move |lines: &[String]| frames.lock().unwrap().push(lines.to_vec())
+        }));
+        let style = ProgressStyle::default_bar().template("{msg}");
+        let p0 = mp.add(ProgressBar::new(1).with_style(style.clone()));
+        let p1 = mp.add(ProgressBar::new(1).with_style(style.clone()));
+        p0.set_message("removed");
+        p1.set_message("kept");
+
+        mp.remove(&p0);
+        let last = frames.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last, ["kept"]);
+
+        // The new progress bar takes the slot of the removed one, which must not draw over it
+        let p2 = mp.insert_before(&p1, ProgressBar::new(1).with_style(style));
+        p2.set_message("added");
+        p0.set_message("ghost");
+        let last = frames.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last, ["added", "kept"]);
+        assert!(p0.is_hidden());
+    }
+
+    #[test]
+    fn concurrent_add_and_remove() {
+        let mp = Arc::new(MultiProgress::with_draw_target(ProgressDrawTarget::hidden()));
+        let threads = (0..4)
+            .map(|_| {
+                let mp = mp.clone();
+                std::thread::spawn(move || {
+                    let anchor = mp.add(ProgressBar::new(10));
+                    for _ in 0..100 {
+                        let pb = mp.insert_after(&anchor, ProgressBar::new(10));
+                        pb.inc(1);
+                        mp.remove(&pb);
+                    }
+                    anchor
+                })
+            })
+            .collect::<Vec<_>>();
+        let anchors = threads
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .collect::<Vec<_>>();
+
+        let mut indices = anchors
+            .iter()
+            .map(|pb| pb.index().unwrap())
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        let mut ordering = mp.state.read().unwrap().ordering.clone();
+        ordering.sort_unstable();
+        assert_eq!(indices, ordering);
+    }
 }
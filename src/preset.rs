@@ -0,0 +1,161 @@
+use crate::style::ProgressStyle;
+
+/// Ready-made progress bar styles for common tasks
+///
+/// See [`ProgressStyle::preset()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Preset {
+    /// Downloads: bytes, speed and ETA
+    Download,
+    /// Package installation: a right-aligned action prefix, count and message
+    Install,
+    /// Compilation in the style of cargo: `Building [====>   ] 12/40: crate`
+    Compile,
+    /// File transfers: message, percentage, sizes in binary units, speed and ETA
+    Transfer,
+    /// Test runners: elapsed time, count and the name of the running test
+    TestRunner,
+}
+
+/// Options applied to a [`Preset`]
+///
+/// See [`ProgressStyle::preset_with()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PresetOptions {
+    colors: bool,
+    ascii: bool,
+    bar_width: Option<u16>,
+}
+
+impl Default for PresetOptions {
+    fn default() -> Self {
+        Self {
+            colors: true,
+            ascii: false,
+            bar_width: None,
+        }
+    }
+}
+
+impl PresetOptions {
+    /// Creates the default options: colored, using Unicode characters and a bar filling the
+    /// available width.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the preset uses colors
+    pub fn colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Sets whether the bar and spinner only use ASCII characters
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    /// Sets a fixed width for the bar, instead of filling the available width
+    pub fn bar_width(mut self, width: u16) -> Self {
+        self.bar_width = Some(width);
+        self
+    }
+}
+
+impl ProgressStyle {
+    /// Returns a ready-made style for a common task
+    ///
+    /// ```rust
+    /// # use indicatif::{Preset, ProgressBar, ProgressStyle};
+    /// let pb = ProgressBar::new(4096);
+    /// pb.set_style(ProgressStyle::preset(Preset::Download));
+    /// ```
+    pub fn preset(preset: Preset) -> ProgressStyle {
+        Self::preset_with(preset, PresetOptions::default())
+    }
+
+    /// Returns a ready-made style for a common task, adjusted by `options`
+    ///
+    /// ```rust
+    /// # use indicatif::{Preset, PresetOptions, ProgressStyle};
+    /// let style = ProgressStyle::preset_with(
+    ///     Preset::Compile,
+    ///     PresetOptions::new().colors(false).ascii(true).bar_width(30),
+    /// );
+    /// ```
+    pub fn preset_with(preset: Preset, options: PresetOptions) -> ProgressStyle {
+        let key = |name: &str, style: &str| match options.colors && !style.is_empty() {
+            true => format!("{{{}:.{}}}", name, style),
+            false => format!("{{{}}}", name),
+        };
+        let bar = |style: &str| match (options.bar_width, options.colors) {
+            (Some(width), true) => format!("{{bar:{}.{}}}", width, style),
+            (Some(width), false) => format!("{{bar:{}}}", width),
+            (None, true) => format!("{{wide_bar:.{}}}", style),
+            (None, false) => "{wide_bar}".to_owned(),
+        };
+        let prefix = match options.colors {
+            true => "{prefix:>12.cyan.bold}",
+            false => "{prefix:>12}",
+        };
+
+        let template = match preset {
+            Preset::Download => format!(
+                "{} [{}] [{}] {{bytes}}/{{total_bytes}} ({{bytes_per_sec}}, {{eta}})",
+                key("spinner", "green"),
+                key("elapsed_precise", ""),
+                bar("cyan/blue"),
+            ),
+            Preset::Install => format!("{} [{}] {{pos}}/{{len}} {{msg}}", prefix, bar("green")),
+            Preset::Compile => format!("{} [{}] {{pos}}/{{len}}: {{msg}}", prefix, bar("cyan")),
+            Preset::Transfer => format!(
+                "{{msg}} {} {{percent:>3}}% {{binary_bytes}}/{{binary_total_bytes}} \
+                 {{binary_bytes_per_sec}} {}",
+                bar("magenta/blue"),
+                key("eta", "dim"),
+            ),
+            Preset::TestRunner => format!(
+                "{} [{}] {} {{pos}}/{{len}} {{msg}}",
+                key("spinner", "green"),
+                key("elapsed", "dim"),
+                bar("green/red"),
+            ),
+        };
+
+        let style = ProgressStyle::default_bar().template(&template);
+        let style = match preset {
+            Preset::Compile => style.progress_chars("=> "),
+            _ => style,
+        };
+        match options.ascii {
+            true => style.progress_chars("#>-").tick_chars(r"-\|/ "),
+            false => style,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ProgressState;
+
+    #[test]
+    fn compile_preset() {
+        let mut state = ProgressState::new(4);
+        state.pos = 2;
+        state.prefix = "Building".into();
+        state.message = "indicatif".into();
+        let options = PresetOptions::new().colors(false).bar_width(8);
+        let mut lines = Vec::new();
+        ProgressStyle::preset_with(Preset::Compile, options)
+            .format_state(&state, &mut lines, 80, None);
+        assert_eq!(lines[0], "    Building [====>   ] 2/4: indicatif");
+
+        lines.clear();
+        ProgressStyle::preset_with(Preset::Compile, options.ascii(true))
+            .format_state(&state, &mut lines, 80, None);
+        assert_eq!(lines[0], "    Building [####>---] 2/4: indicatif");
+    }
+}
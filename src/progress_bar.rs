@@ -1,16 +1,34 @@
 use std::borrow::Cow;
 use std::fmt;
 use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::MutexGuard;
-use std::sync::{Arc, Mutex, Weak};
+use std::sync::{Arc, Mutex, TryLockError, Weak};
 use std::thread;
-use std::time::{Duration, Instant};
-
-use crate::draw_target::ProgressDrawTarget;
-use crate::state::{BarState, Limit, ProgressState, Status};
-use crate::style::ProgressStyle;
+use std::time::Duration;
+
+use crate::compat::StyledObject;
+
+use crate::draw_target::{DrawStrategy, ProgressDrawTarget};
+use crate::iter::Lines;
+use crate::recording::ProgressRecording;
+use crate::registry;
+use crate::state::{
+    copy_error, saturating_add, BarState, Deadline, Direction, DrawErrors, Limit, Notify,
+    ProgressState, Scale, Status, Throughput, UserFraction,
+};
+use crate::style::{ProgressFinish, ProgressStyle};
+use crate::ticker::{ticker, Scheduled};
+use crate::time::Instant;
 use crate::{ProgressBarIter, ProgressIterator};
 
+/// Frames per second of [`ProgressBar::finish_animated()`]
+const ANIMATION_FPS: f64 = 30.0;
+
 /// A progress bar or spinner
 ///
 /// The progress bar is an [`Arc`] around its internal state. When the progress bar is cloned it
@@ -18,6 +36,11 @@ use crate::{ProgressBarIter, ProgressIterator};
 #[derive(Clone)]
 pub struct ProgressBar {
     state: Arc<Mutex<BarState>>,
+    /// Ticks that couldn't be applied because the state was locked
+    pending_ticks: Arc<AtomicU64>,
+    /// Position updated without locking the state, with the `sharded-state` feature
+    #[cfg(feature = "sharded-state")]
+    pos: Arc<AtomicU64>,
 }
 
 impl fmt::Debug for ProgressBar {
@@ -46,41 +69,82 @@ impl ProgressBar {
 
     /// Creates a new progress bar with a given length and draw target
     pub fn with_draw_target(len: u64, draw_target: ProgressDrawTarget) -> ProgressBar {
-        ProgressBar {
+        let pb = ProgressBar {
             state: Arc::new(Mutex::new(BarState {
                 draw_target,
                 state: ProgressState::new(len),
+                draw_errors: DrawErrors::default(),
             })),
+            pending_ticks: Arc::default(),
+            #[cfg(feature = "sharded-state")]
+            pos: Arc::default(),
+        };
+        #[cfg(feature = "sharded-state")]
+        {
+            pb.state().state.shared_pos = Some(pb.pos.clone());
+        }
+        registry::register(&pb);
+        pb
+    }
+
+    /// Creates a new progress bar resuming a partially completed task
+    ///
+    /// The bar starts at `pos` with `elapsed` already accounted for. The ETA estimation is
+    /// seeded with the average rate of the previous session, so `{eta}` and `{per_sec}` are
+    /// sensible from the first draw instead of assuming `pos` was reached instantly.
+    pub fn resume(len: u64, pos: u64, elapsed: Duration) -> ProgressBar {
+        let pb = ProgressBar::new(len);
+        {
+            let mut state = pb.state();
+            let now = Instant::now();
+            state.state.set_pos_unsynced(pos);
+            // Kept aside rather than moving the start back, as the monotonic clock may be younger
+            // than `elapsed`, e.g. after a reboot
+            state.state.resumed_elapsed = elapsed;
+            state.state.est.resume(pos, elapsed, now);
         }
+        pb
     }
 
     /// A convenience builder-like function for a progress bar with a given style
     pub fn with_style(self, style: ProgressStyle) -> ProgressBar {
-        self.state.lock().unwrap().state.style = style;
+        self.state().state.style = style;
+        self
+    }
+
+    /// A convenience builder-like function for a progress bar with a given finish behavior
+    ///
+    /// See [`ProgressStyle::on_finish()`].
+    pub fn with_finish(self, finish: ProgressFinish) -> ProgressBar {
+        self.state().state.style.set_on_finish(finish);
         self
     }
 
     /// A convenience builder-like function for a progress bar with a given prefix
     pub fn with_prefix(self, prefix: impl Into<Cow<'static, str>>) -> ProgressBar {
-        self.state.lock().unwrap().state.prefix = prefix.into();
+        self.state().state.prefix = prefix.into();
         self
     }
 
     /// A convenience builder-like function for a progress bar with a given message
     pub fn with_message(self, message: impl Into<Cow<'static, str>>) -> ProgressBar {
-        self.state.lock().unwrap().state.message = message.into();
+        self.state().state.set_message(message.into());
         self
     }
 
     /// A convenience builder-like function for a progress bar with a given position
     pub fn with_position(self, pos: u64) -> ProgressBar {
-        self.state.lock().unwrap().state.pos = pos;
+        self.state().state.set_pos_unsynced(pos);
         self
     }
 
     /// A convenience builder-like function for a progress bar with a given elapsed time
     pub fn with_elapsed(self, elapsed: Duration) -> ProgressBar {
-        self.state.lock().unwrap().state.started = Instant::now() - elapsed;
+        {
+            let state = &mut self.state().state;
+            state.started = Instant::now() - elapsed;
+            state.lap_started = state.started;
+        }
         self
     }
 
@@ -97,55 +161,107 @@ impl ProgressBar {
     ///
     /// This does not redraw the bar. Call [`ProgressBar::tick()`] to force it.
     pub fn set_style(&self, style: ProgressStyle) {
-        self.state.lock().unwrap().state.style = style;
+        self.state().state.style = style;
+    }
+
+    /// Sets a notification to emit when the progress bar finishes
+    ///
+    /// The notification is emitted whenever the progress bar goes from unfinished to finished,
+    /// after the final state has been drawn.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::{Notify, ProgressBar};
+    /// let pb = ProgressBar::new(100);
+    /// pb.notify_on_finish(Notify::Bell);
+    /// ```
+    pub fn notify_on_finish(&self, notify: Notify) {
+        self.state().state.notify = Some(notify);
+    }
+
+    /// Calls `f` once when the progress bar finishes
+    ///
+    /// `f` is called with the final state when the progress bar goes from unfinished to finished
+    /// for the first time, no matter how it is finished (including when it is dropped and
+    /// finished according to its [`ProgressFinish`] behavior). Several functions can be
+    /// registered, they are called in order.
+    ///
+    /// **Note:** The progress bar's internal lock is held while `f` is called, so it must not call
+    /// back into the progress bar.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(100);
+    /// pb.on_finish(|state| eprintln!("done {} items in {:?}", state.pos, state.elapsed()));
+    /// pb.finish();
+    /// ```
+    ///
+    /// [`ProgressFinish`]: crate::ProgressFinish
+    pub fn on_finish(&self, f: impl FnOnce(&ProgressState) + Send + 'static) {
+        let mut state = self.state();
+        state.state.finish_hooks.push(Box::new(f));
+    }
+
+    /// Records the progress of the progress bar over time
+    ///
+    /// The returned [`ProgressRecording`] is sampled as the progress bar is updated, and can be
+    /// exported as CSV or JSON once the work is done, e.g. to chart the throughput of a long
+    /// running operation. Calling this again starts a new recording.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::hidden();
+    /// let recording = pb.record();
+    /// pb.inc(1);
+    /// pb.finish();
+    /// recording.write_csv(std::io::stdout()).unwrap();
+    /// ```
+    pub fn record(&self) -> ProgressRecording {
+        let recording = ProgressRecording::default();
+        self.state().state.recording = Some(recording.clone());
+        recording
+    }
+
+    /// Records the progress of the progress bar, and saves it to `path` when it finishes
+    ///
+    /// The recording is saved as JSON if `path` has a `json` extension, and as CSV otherwise.
+    /// Errors while saving are ignored, use [`ProgressBar::record()`] and
+    /// [`ProgressRecording::save()`] to handle them.
+    pub fn record_to(&self, path: impl Into<PathBuf>) {
+        let recording = self.record();
+        let path = path.into();
+        self.on_finish(move |_| {
+            let _ = recording.save(path);
+        });
     }
 
-    /// Spawns a background thread to tick the progress bar
+    /// Ticks the progress bar in the background
     ///
-    /// When this is enabled a background thread will regularly tick the progress bar in the given
-    /// interval (in milliseconds). This is useful to advance progress bars that are very slow by
-    /// themselves.
+    /// When this is enabled the progress bar is ticked regularly in the given interval (in
+    /// milliseconds). This is useful to advance progress bars that are very slow by themselves.
+    /// All progress bars with steady ticks share a single background thread.
     ///
     /// When steady ticks are enabled, calling [`ProgressBar::tick()`] on a progress bar does not
     /// have any effect.
+    ///
+    /// **Note:** `wasm32` targets have no threads, this does nothing there. Call
+    /// [`ProgressBar::tick()`] from a timer of the JS runtime instead.
     pub fn enable_steady_tick(&self, ms: u64) {
-        let mut state = self.state.lock().unwrap();
-        state.state.steady_tick = ms;
-        if state.state.tick_thread.is_some() {
+        if cfg!(target_arch = "wasm32") {
             return;
         }
 
-        // Using a weak pointer is required to prevent a potential deadlock. See issue #133
-        let state_arc = Arc::downgrade(&self.state);
-        state.state.tick_thread = Some(thread::spawn(move || Self::steady_tick(state_arc, ms)));
+        let mut state = self.state();
+        state.state.steady_tick = ms;
+        match ms {
+            0 => ticker().unregister(&Arc::downgrade(&self.state)),
+            ms => ticker().register(Arc::downgrade(&self.state), Duration::from_millis(ms)),
+        }
 
         ::std::mem::drop(state);
         // use the side effect of tick to force the bar to tick.
         self.tick();
     }
 
-    fn steady_tick(state_arc: Weak<Mutex<BarState>>, mut ms: u64) {
-        loop {
-            thread::sleep(Duration::from_millis(ms));
-            if let Some(state_arc) = state_arc.upgrade() {
-                let mut state = state_arc.lock().unwrap();
-                if state.state.is_finished() || state.state.steady_tick == 0 {
-                    state.state.steady_tick = 0;
-                    state.state.tick_thread = None;
-                    break;
-                }
-                if state.state.tick != 0 {
-                    state.state.tick = state.state.tick.saturating_add(1);
-                }
-                ms = state.state.steady_tick;
-
-                state.draw(false, Instant::now()).ok();
-            } else {
-                break;
-            }
-        }
-    }
-
     /// Undoes [`ProgressBar::enable_steady_tick()`]
     pub fn disable_steady_tick(&self) {
         self.enable_steady_tick(0);
@@ -172,7 +288,7 @@ impl ProgressBar {
     ///
     /// Note that `ProgressDrawTarget` may impose additional buffering of redraws.
     pub fn set_draw_delta(&self, gap: u64) {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state();
         state.state.draw_limit = Limit::Units(gap);
     }
 
@@ -196,24 +312,210 @@ impl ProgressBar {
     ///
     /// Note that the [`ProgressDrawTarget`] may impose additional buffering of redraws.
     pub fn set_draw_rate(&self, n: u64) {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state();
         let interval = Duration::from_nanos(1_000_000_000 / n);
         state.state.draw_limit = Limit::Rate(interval);
     }
 
+    /// Sets whether the progress bar is drawn at 100% before being cleared
+    ///
+    /// By default, [`ProgressBar::finish_and_clear()`] (and [`ProgressFinish::AndClear`]) remove
+    /// the progress bar right away, so the last frame shown may be well below 100% because of
+    /// rate limiting. When enabled, a final frame at 100% is drawn before the bar is cleared.
+    ///
+    /// [`ProgressFinish::AndClear`]: crate::ProgressFinish::AndClear
+    pub fn set_draw_final_frame(&self, enabled: bool) {
+        self.state().state.draw_final_frame = enabled;
+    }
+
+    /// Sets the direction in which the progress bar is displayed to move
+    ///
+    /// With [`Direction::Decreasing`], the position keys (`{pos}`, `{bytes}`, ...) and
+    /// `{percent}` count down from the length to 0 and the bar drains instead of filling up. This
+    /// is only a matter of display: the progress bar is still advanced with
+    /// [`ProgressBar::inc()`] and the ETA is computed as usual.
+    ///
+    /// ```rust
+    /// # use indicatif::{Direction, ProgressBar};
+    /// let pb = ProgressBar::new(10);
+    /// pb.set_direction(Direction::Decreasing);
+    /// pb.inc(3); // displays 7
+    /// ```
+    pub fn set_direction(&self, direction: Direction) {
+        self.state().state.direction = direction;
+    }
+
+    /// Sets the time the progress bar is expected to take
+    ///
+    /// While the progress is behind the share of the expected duration that has elapsed, the bar
+    /// shows a marker (see [`ProgressStyle::budget_marker()`]) where it is expected to be. Once
+    /// the expected duration is exceeded, `{overshoot}` shows by how much, like `+1m`.
+    ///
+    /// ```rust,no_run
+    /// # use std::time::Duration;
+    /// # use indicatif::{ProgressBar, ProgressStyle};
+    /// let pb = ProgressBar::new(100)
+    ///     .with_style(ProgressStyle::default_bar().template("{bar:40} {pos}/{len} {overshoot}"));
+    /// pb.set_expected_duration(Duration::from_secs(60));
+    /// ```
+    pub fn set_expected_duration(&self, expected: Duration) {
+        self.state().state.expected_duration = Some(expected);
+    }
+
+    /// Makes the progress bar track wall-clock time until `deadline` instead of its position
+    ///
+    /// The bar, `{percent}` and `{eta}` show the time passed since this call relative to the time
+    /// left until `deadline`, which is useful for timeouts, cooldowns and rate-limited waits. A
+    /// steady tick of 100 ms is enabled if none is set, so the bar keeps moving on its own.
+    ///
+    /// ```rust,no_run
+    /// # use std::time::{Duration, Instant};
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(0);
+    /// pb.set_deadline(Instant::now() + Duration::from_secs(30));
+    /// ```
+    pub fn set_deadline(&self, deadline: Instant) {
+        let mut state = self.state();
+        state.state.deadline = Some(Deadline {
+            start: Instant::now(),
+            end: deadline,
+        });
+        let steady_tick = state.state.steady_tick;
+        drop(state);
+        if steady_tick == 0 {
+            self.enable_steady_tick(100);
+        }
+    }
+
+    /// Sets the completion of the progress bar directly, as a number between 0 and 1
+    ///
+    /// This is an alternative to the position and length for work that can estimate how far along
+    /// it is, but not count steps, like a solver converging. Once set, the fraction drives the
+    /// bar, `{percent}` and `{eta}`, which is based on the average rate since the first call.
+    /// Values outside of 0 to 1 are clamped and NaN is ignored. [`ProgressBar::reset()`] goes back
+    /// to the position.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new_spinner();
+    /// pb.set_fraction(0.25);
+    /// assert_eq!(pb.with_state(|state| state.fraction()), 0.25);
+    /// ```
+    pub fn set_fraction(&self, fraction: f64) {
+        if fraction.is_nan() {
+            return;
+        }
+        let fraction = fraction.clamp(0.0, 1.0);
+        let now = Instant::now();
+        self.update_and_draw(now, |state| match &mut state.user_fraction {
+            Some(current) => current.value = fraction,
+            None => state.user_fraction = Some(UserFraction::new(fraction, now)),
+        })
+    }
+
+    /// Divides the progress bar into `count` chunks that can be completed in any order
+    ///
+    /// This sets the length to `count` and the position to 0. Chunks are then marked as done
+    /// with [`ProgressBar::complete_chunk()`], which advances the position by one, and `{bar}`
+    /// and `{wide_bar}` render which chunks are done rather than a linear fill. This suits
+    /// parallel or torrent-style downloads, in which ranges complete out of order.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(0);
+    /// pb.set_chunks(64);
+    /// pb.complete_chunk(42);
+    /// pb.complete_chunk(3);
+    /// assert_eq!(pb.position(), 2);
+    /// ```
+    pub fn set_chunks(&self, count: usize) {
+        self.update_and_draw(Instant::now(), |state| {
+            state.chunks = Some(vec![false; count]);
+            state.len = count as u64;
+            state.pos = 0;
+        })
+    }
+
+    /// Marks the chunk at `idx` as done
+    ///
+    /// Chunks that are already done, or out of range, are ignored. See
+    /// [`ProgressBar::set_chunks()`].
+    pub fn complete_chunk(&self, idx: usize) {
+        self.update_and_draw(Instant::now(), |state| {
+            let done = match state.chunks.as_mut().and_then(|chunks| chunks.get_mut(idx)) {
+                Some(done) if !*done => done,
+                _ => return,
+            };
+            *done = true;
+            state.pos = state.pos.saturating_add(1);
+        })
+    }
+
+    /// Hands the state of the progress bar to `renderer` whenever it would be drawn
+    ///
+    /// This is unstable, see the [`renderer`](crate::renderer) module.
+    #[cfg(feature = "unstable-renderer")]
+    pub fn set_renderer(&self, renderer: impl crate::renderer::Renderer + 'static) {
+        self.state().state.renderer = Some(Box::new(renderer));
+    }
+
     /// Manually ticks the spinner or progress bar
     ///
-    /// This automatically happens on any other change to a progress bar.
+    /// This automatically happens on any other change to a progress bar. Finished progress bars
+    /// keep their final frame, so ticking them has no effect.
     pub fn tick(&self) {
-        self.update_and_draw(Instant::now(), |state| {
+        let mut state = self.state();
+        if state.state.is_finished() {
+            return;
+        }
+
+        let ticks = 1 + self.pending_ticks.swap(0, Ordering::Relaxed);
+        state.update_and_draw(Instant::now(), |state| {
             if state.steady_tick == 0 || state.tick == 0 {
-                state.tick = state.tick.saturating_add(1);
+                state.tick = state.tick.saturating_add(ticks);
+            }
+        });
+    }
+
+    /// Ticks the spinner or progress bar without waiting for other threads using it
+    ///
+    /// If another thread is currently updating or drawing the progress bar, the tick is recorded
+    /// and applied by that thread once it's done instead of waiting for the internal lock. This
+    /// makes it safe to call from async tasks, which shouldn't block the executor thread they run
+    /// on.
+    pub fn tick_and_yield(&self) {
+        let mut state = match self.try_state() {
+            Some(state) => state,
+            None => {
+                self.pending_ticks.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        };
+
+        if state.state.is_finished() {
+            return;
+        }
+        let ticks = 1 + self.pending_ticks.swap(0, Ordering::Relaxed);
+        state.update_and_draw(Instant::now(), |state| {
+            if state.steady_tick == 0 || state.tick == 0 {
+                state.tick = state.tick.saturating_add(ticks);
             }
         });
     }
 
     /// Advances the position of the progress bar by `delta`
     pub fn inc(&self, delta: u64) {
+        #[cfg(feature = "sharded-state")]
+        {
+            let _ = self
+                .pos
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |pos| {
+                    Some(pos.saturating_add(delta))
+                });
+            self.pending_ticks.fetch_add(1, Ordering::Relaxed);
+            self.try_sync();
+        }
+        #[cfg(not(feature = "sharded-state"))]
         self.update_and_draw(Instant::now(), |state| {
             state.pos = state.pos.saturating_add(delta);
             if state.steady_tick == 0 || state.tick == 0 {
@@ -222,14 +524,83 @@ impl ProgressBar {
         })
     }
 
+    /// Advances the position of the progress bar by `delta`, accounting `weight` units of work
+    ///
+    /// Once [`ProgressBar::set_remaining_work()`] has been called, the ETA is based on the rate at
+    /// which weighted work is done instead of the number of items. This is useful when items
+    /// have very different costs, e.g. when processing files of very different sizes.
+    pub fn inc_weighted(&self, delta: u64, weight: u64) {
+        let now = Instant::now();
+        self.update_and_draw(now, |state| {
+            state.pos = state.pos.saturating_add(delta);
+            state.record_work(weight, now);
+            if state.steady_tick == 0 || state.tick == 0 {
+                state.tick = state.tick.saturating_add(1);
+            }
+        })
+    }
+
+    /// Sets the number of weighted work units left, basing the ETA on weighted work
+    ///
+    /// See [`ProgressBar::inc_weighted()`].
+    pub fn set_remaining_work(&self, units: u64) {
+        self.update_and_draw(Instant::now(), |state| {
+            state.remaining_work = Some(units);
+        })
+    }
+
     /// A quick convenience check if the progress bar is hidden
     pub fn is_hidden(&self) -> bool {
-        self.state.lock().unwrap().draw_target.is_hidden()
+        self.state().draw_target.is_hidden()
     }
 
     /// Indicates that the progress bar finished
     pub fn is_finished(&self) -> bool {
-        self.state.lock().unwrap().state.is_finished()
+        self.state().state.is_finished()
+    }
+
+    /// Marks the progress bar as paused, or as running again
+    ///
+    /// The progress bar keeps drawing while paused; the flag tells the code doing the work to
+    /// hold off, e.g. when the user pressed the pause key of a `KeyListener` (see the `keyboard`
+    /// feature).
+    pub fn set_paused(&self, paused: bool) {
+        self.state().state.paused = paused;
+    }
+
+    /// Indicates whether the progress bar is paused, see [`ProgressBar::set_paused()`]
+    pub fn is_paused(&self) -> bool {
+        self.state().state.paused
+    }
+
+    /// Starts reading key presses from the terminal, handling them with `bindings`
+    ///
+    /// Keys are read from a background thread until the returned listener is dropped or the
+    /// progress bar is finished. Use [`KeyListener::suspend()`] to read input from the terminal
+    /// in the meantime. Fails if the process has no controlling terminal.
+    ///
+    /// **Note:** The terminal is only restored when the listener stops, it's left without echo if
+    /// the process exits without dropping it, see [`KeyListener`].
+    ///
+    /// ```rust,no_run
+    /// # use std::{thread, time::Duration};
+    /// # use indicatif::{KeyBindings, ProgressBar};
+    /// let pb = ProgressBar::new(100);
+    /// let _keys = pb.listen_keys(KeyBindings::default())?;
+    /// while !pb.is_finished() {
+    ///     if !pb.is_paused() {
+    ///         pb.inc(1);
+    ///     }
+    ///     thread::sleep(Duration::from_millis(100));
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// [`KeyListener`]: crate::KeyListener
+    /// [`KeyListener::suspend()`]: crate::KeyListener::suspend
+    #[cfg(all(unix, feature = "keyboard"))]
+    pub fn listen_keys(&self, bindings: crate::KeyBindings) -> io::Result<crate::KeyListener> {
+        crate::KeyListener::new(self, bindings)
     }
 
     /// Print a log line above the progress bar
@@ -244,18 +615,28 @@ impl ProgressBar {
     /// [`suspend`]: ProgressBar::suspend
     /// [`MultiProgress`]: crate::MultiProgress
     pub fn println<I: AsRef<str>>(&self, msg: I) {
-        let state = &mut *self.state.lock().unwrap();
-        let draw_lines = state.state.should_render() && !state.draw_target.is_hidden();
-        let (draw_target, state) = (&mut state.draw_target, &state.state);
+        let bar = &mut *self.state();
+        // In a multi progress only the lines are written, the progress bar keeps the lines it was
+        // last drawn with, so whether it should be rendered doesn't matter here
+        if bar.draw_target.queue_lines(msg.as_ref(), Instant::now()) {
+            return;
+        }
+
+        let draw_lines = bar.state.should_render() && !bar.draw_target.is_hidden();
+        let (draw_target, state) = (&mut bar.draw_target, &bar.state);
         let width = draw_target.width();
+        let colors = draw_target.colors();
 
         let mut drawable = match draw_target.drawable(true, Instant::now()) {
             Some(drawable) => drawable,
             None => return,
         };
 
+        let graphics = drawable.graphics();
         let mut draw_state = drawable.state();
-        draw_state.move_cursor = false;
+        if draw_state.strategy == DrawStrategy::CursorMovement {
+            draw_state.strategy = DrawStrategy::ClearAndRedraw;
+        }
         draw_state.alignment = Default::default();
 
         draw_state
@@ -263,17 +644,31 @@ impl ProgressBar {
             .extend(msg.as_ref().lines().map(Into::into));
         draw_state.orphan_lines = draw_state.lines.len();
         if draw_lines {
-            state
-                .style
-                .format_state(state, &mut draw_state.lines, width);
+            let draw_state = &mut *draw_state;
+            state.style.format_state_with(
+                state,
+                &mut draw_state.lines,
+                &mut draw_state.spare_lines,
+                width,
+                colors,
+                graphics.as_deref(),
+            );
         }
 
         drop(draw_state);
-        let _ = drawable.draw();
+        let result = drawable.draw();
+        let _ = bar.record_draw(result);
     }
 
     /// Sets the position of the progress bar
     pub fn set_position(&self, pos: u64) {
+        #[cfg(feature = "sharded-state")]
+        {
+            self.pos.store(pos, Ordering::Relaxed);
+            self.pending_ticks.fetch_add(1, Ordering::Relaxed);
+            self.try_sync();
+        }
+        #[cfg(not(feature = "sharded-state"))]
         self.update_and_draw(Instant::now(), |state| {
             state.pos = pos;
             if state.steady_tick == 0 || state.tick == 0 {
@@ -289,6 +684,77 @@ impl ProgressBar {
         })
     }
 
+    /// Sets the position of a secondary quantity tracked by the same progress bar
+    ///
+    /// The secondary quantity is rendered by the `{aux_bar}`, `{aux_pos}` and `{aux_len}` keys
+    /// (and `{aux_bytes}` and `{aux_total_bytes}`), e.g. to show the bytes copied next to the
+    /// files copied on the same line. It doesn't affect the ETA, the rate or when the progress
+    /// bar is finished.
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressBar, ProgressStyle};
+    /// let pb = ProgressBar::new(10).with_style(ProgressStyle::default_bar().template(
+    ///     "files {pos}/{len} {bar:20} bytes {aux_bytes}/{aux_total_bytes} {aux_bar:20}",
+    /// ));
+    /// pb.set_aux_length(8_000_000_000);
+    /// pb.inc(3);
+    /// pb.set_aux_position(1_200_000_000);
+    /// ```
+    pub fn set_aux_position(&self, pos: u64) {
+        self.update_and_draw(Instant::now(), |state| state.aux_pos = pos)
+    }
+
+    /// Advances the position of the secondary quantity, see [`ProgressBar::set_aux_position()`]
+    pub fn inc_aux(&self, delta: u64) {
+        self.update_and_draw(Instant::now(), |state| {
+            state.aux_pos = state.aux_pos.saturating_add(delta)
+        })
+    }
+
+    /// Sets the length of the secondary quantity, see [`ProgressBar::set_aux_position()`]
+    pub fn set_aux_length(&self, len: u64) {
+        self.update_and_draw(Instant::now(), |state| state.aux_len = len)
+    }
+
+    /// Sets the range of positions covered by the bar
+    ///
+    /// The bar is empty at `min` and full at `max`, which becomes the length. The position keys
+    /// still show the position itself. This is useful when the progress doesn't start at 0, e.g.
+    /// for a counter that started at an arbitrary value.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::hidden();
+    /// pb.set_range(1000, 2000);
+    /// pb.set_position(1250);
+    /// assert_eq!(pb.with_state(|state| state.fraction()), 0.25);
+    /// ```
+    pub fn set_range(&self, min: u64, max: u64) {
+        self.update_and_draw(Instant::now(), |state| {
+            state.min = min;
+            state.len = max;
+        })
+    }
+
+    /// Sets how the position maps to the fill of the bar and to `{percent}`
+    ///
+    /// With [`Scale::Logarithmic`], early progress fills the bar faster, which suits work whose
+    /// progress slows down exponentially, such as optimizations converging to a target.
+    pub fn set_scale(&self, scale: Scale) {
+        self.update_and_draw(Instant::now(), |state| state.scale = scale)
+    }
+
+    /// Sets how wide the range of [`ProgressBar::eta_range()`] and the `{eta_low}` and
+    /// `{eta_high}` keys is, in standard deviations of the recent time per step
+    ///
+    /// The default of 1 shows the likely range for steadily progressing work; use larger values
+    /// to be more cautious with highly variable workloads.
+    pub fn set_eta_confidence(&self, deviations: f64) {
+        self.update_and_draw(Instant::now(), |state| {
+            state.eta_confidence = deviations.max(0.0)
+        })
+    }
+
     /// Increase the length of the progress bar
     pub fn inc_length(&self, delta: u64) {
         self.update_and_draw(Instant::now(), |state| {
@@ -296,6 +762,27 @@ impl ProgressBar {
         })
     }
 
+    /// Sets the id of the task tracked by the progress bar
+    ///
+    /// The id is rendered by the `{task_id}` placeholder, which shows the name of the thread that
+    /// last updated the progress bar until an id is set. This is useful for the bars of a worker
+    /// pool, whose template can then be shared by all workers.
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressBar, ProgressStyle};
+    /// let style = ProgressStyle::default_bar().template("[{task_id}] {wide_bar} {pos}/{len}");
+    /// for worker in 0..4 {
+    ///     let pb = ProgressBar::new(100).with_style(style.clone());
+    ///     pb.set_task_id(format!("worker {}", worker));
+    /// }
+    /// ```
+    pub fn set_task_id(&self, id: impl Into<Cow<'static, str>>) {
+        let id = id.into();
+        self.update_and_draw(Instant::now(), |state| {
+            state.task_id = Some(id);
+        })
+    }
+
     /// Sets the current prefix of the progress bar
     ///
     /// For the prefix to be visible, the `{prefix}` placeholder must be present in the template
@@ -317,17 +804,95 @@ impl ProgressBar {
     pub fn set_message(&self, msg: impl Into<Cow<'static, str>>) {
         let msg = msg.into();
         self.update_and_draw(Instant::now(), |state| {
-            state.message = msg;
+            state.set_message(msg);
             if state.steady_tick == 0 || state.tick == 0 {
                 state.tick = state.tick.saturating_add(1);
             }
         })
     }
 
+    /// Sets the current message of the progress bar to a styled value
+    ///
+    /// The message keeps its colors when the draw target emits colors, and is shown without them
+    /// otherwise. Like any message, it is measured by its visible width, so the escape codes
+    /// don't count towards the width of the line.
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressBar, Style};
+    /// let pb = ProgressBar::new(100);
+    /// pb.set_styled_message(Style::new().yellow().apply_to("warning"));
+    /// ```
+    pub fn set_styled_message<D: fmt::Display>(&self, msg: StyledObject<D>) {
+        self.set_message(msg.force_styling(true).to_string());
+    }
+
+    /// Sets the current prefix of the progress bar to a styled value
+    ///
+    /// See [`ProgressBar::set_styled_message()`].
+    pub fn set_styled_prefix<D: fmt::Display>(&self, prefix: StyledObject<D>) {
+        self.set_prefix(prefix.force_styling(true).to_string());
+    }
+
+    /// Sets a function that provides the message of the progress bar
+    ///
+    /// Unlike [`ProgressBar::set_message()`], the message is only built when the progress bar is
+    /// actually drawn, so expensive messages don't slow down hot loops in which most updates are
+    /// skipped by rate limiting. The function is called with the progress bar's internal lock
+    /// held, so it must not call back into the progress bar. Setting a message replaces the
+    /// function.
+    ///
+    /// ```rust,no_run
+    /// # use std::sync::atomic::{AtomicU64, Ordering};
+    /// # use std::sync::Arc;
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(1_000_000);
+    /// let bytes = Arc::new(AtomicU64::new(0));
+    /// let counter = bytes.clone();
+    /// pb.set_message_fn(move || format!("{} bytes", counter.load(Ordering::Relaxed)));
+    /// for _ in 0..1_000_000 {
+    ///     bytes.fetch_add(42, Ordering::Relaxed);
+    ///     pb.inc(1);
+    /// }
+    /// ```
+    pub fn set_message_fn(&self, f: impl Fn() -> String + Send + 'static) {
+        self.update_and_draw(Instant::now(), |state| {
+            state.message_fn = Some(Box::new(f));
+        })
+    }
+
+    /// Returns a handle to update the position of the progress bar without locking
+    ///
+    /// The handle only uses atomic operations, so it can be used where taking a lock is not an
+    /// option, like in signal handlers or realtime audio callbacks. The progress bar picks up the
+    /// position stored through the handle whenever it is updated or drawn, so it should be drawn
+    /// from elsewhere, e.g. with [`ProgressBar::enable_steady_tick()`]. Updates through the
+    /// progress bar itself are reflected in the handle.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::hidden();
+    /// let handle = pb.position_handle();
+    /// handle.fetch_add(3);
+    /// pb.inc(1);
+    /// assert_eq!(handle.load(), 4);
+    /// assert_eq!(pb.position(), 4);
+    /// ```
+    pub fn position_handle(&self) -> PositionHandle {
+        let state = &mut self.state().state;
+        let pos = state.pos;
+        let shared = state
+            .shared_pos
+            .get_or_insert_with(|| Arc::new(AtomicU64::new(pos)));
+        PositionHandle(shared.clone())
+    }
+
     /// Creates a new weak reference to this `ProgressBar`
     pub fn downgrade(&self) -> WeakProgressBar {
         WeakProgressBar {
             state: Arc::downgrade(&self.state),
+            pending_ticks: Arc::downgrade(&self.pending_ticks),
+            #[cfg(feature = "sharded-state")]
+            pos: Arc::downgrade(&self.pos),
         }
     }
 
@@ -338,6 +903,7 @@ impl ProgressBar {
     pub fn reset_eta(&self) {
         self.update_and_draw(Instant::now(), |state| {
             state.est.reset(state.pos);
+            state.work_est.reset(state.work_done);
         });
     }
 
@@ -346,7 +912,39 @@ impl ProgressBar {
         let now = Instant::now();
         self.update_and_draw(now, |state| {
             state.started = now;
+            state.resumed_elapsed = Duration::new(0, 0);
+            state.lap_started = now;
+        });
+    }
+
+    /// Records a lap with the given label and returns its duration
+    ///
+    /// A lap lasts from the previous lap (or the start of the progress bar) until now. The most
+    /// recent lap can be shown using the `{last_lap}` template key, and all laps are available
+    /// through [`ProgressBar::laps()`].
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::{HumanDuration, ProgressBar};
+    /// let pb = ProgressBar::new(100);
+    /// // ... phase 1 ...
+    /// let took = pb.lap("phase 1");
+    /// pb.println(format!("phase 1 took {}", HumanDuration(took)));
+    /// ```
+    pub fn lap(&self, label: impl Into<Cow<'static, str>>) -> Duration {
+        let label = label.into();
+        let now = Instant::now();
+        let mut duration = Duration::new(0, 0);
+        self.update_and_draw(now, |state| {
+            duration = now - state.lap_started;
+            state.lap_started = now;
+            state.laps.push((label, duration));
         });
+        duration
+    }
+
+    /// Returns the labels and durations of all recorded laps, oldest first
+    pub fn laps(&self) -> Vec<(Cow<'static, str>, Duration)> {
+        self.state().state.laps.clone()
     }
 
     /// Resets all of the progress bar state
@@ -354,23 +952,26 @@ impl ProgressBar {
         self.reset_eta();
         self.reset_elapsed();
         self.update_and_draw(Instant::now(), |state| {
+            state.laps.clear();
+            state.lap_started = state.started;
             state.pos = 0;
+            state.aux_pos = 0;
             state.last_draw = None;
             state.status = Status::InProgress;
+            state.finished_at = None;
+            state.throughput = Throughput::new(state.started);
+            state.user_fraction = None;
         });
     }
 
     /// Finishes the progress bar and leaves the current message
     pub fn finish(&self) {
-        self.state.lock().unwrap().finish(Instant::now());
+        self.state().finish(Instant::now());
     }
 
     /// Finishes the progress bar at current position and leaves the current message
     pub fn finish_at_current_pos(&self) {
-        self.state
-            .lock()
-            .unwrap()
-            .finish_at_current_pos(Instant::now());
+        self.state().finish_at_current_pos(Instant::now());
     }
 
     /// Finishes the progress bar and sets a message
@@ -378,20 +979,17 @@ impl ProgressBar {
     /// For the message to be visible, the `{msg}` placeholder must be present in the template (see
     /// [`ProgressStyle`]).
     pub fn finish_with_message(&self, msg: impl Into<Cow<'static, str>>) {
-        self.state
-            .lock()
-            .unwrap()
-            .finish_with_message(msg, Instant::now());
+        self.state().finish_with_message(msg, Instant::now());
     }
 
     /// Finishes the progress bar and completely clears it
     pub fn finish_and_clear(&self) {
-        self.state.lock().unwrap().finish_and_clear(Instant::now());
+        self.state().finish_and_clear(Instant::now());
     }
 
     /// Finishes the progress bar and leaves the current message and progress
     pub fn abandon(&self) {
-        self.state.lock().unwrap().abandon(Instant::now());
+        self.state().abandon(Instant::now());
     }
 
     /// Finishes the progress bar and sets a message, and leaves the current progress
@@ -399,20 +997,62 @@ impl ProgressBar {
     /// For the message to be visible, the `{msg}` placeholder must be present in the template (see
     /// [`ProgressStyle`]).
     pub fn abandon_with_message(&self, msg: impl Into<Cow<'static, str>>) {
-        self.state
-            .lock()
-            .unwrap()
-            .abandon_with_message(msg, Instant::now());
+        self.state().abandon_with_message(msg, Instant::now());
     }
 
     /// Finishes the progress bar using the behavior stored in the [`ProgressStyle`]
     ///
     /// See [`ProgressStyle::on_finish()`].
     pub fn finish_using_style(&self) {
-        self.state
-            .lock()
-            .unwrap()
-            .finish_using_style(Instant::now());
+        self.state().finish_using_style(Instant::now());
+    }
+
+    /// Finishes the progress bar at the end of a wrapped reader or writer, see
+    /// [`ProgressBarIter::with_finish_at_end()`]
+    pub(crate) fn finish_at_end(&self) {
+        self.state().finish_at_end(Instant::now());
+    }
+
+    /// Animates the progress bar to completion over `duration`, then finishes it using the
+    /// behavior stored in the [`ProgressStyle`]
+    ///
+    /// The position advances to the length in small steps, each of them drawn regardless of the
+    /// refresh rate, which looks smoother than jumping to completion when the last part of the
+    /// work finished quicker than expected. This blocks the calling thread for `duration`, hidden
+    /// progress bars are finished right away. On `wasm32` targets, which can't block the thread,
+    /// the progress bar is finished right away as well.
+    ///
+    /// ```rust,no_run
+    /// # use std::time::Duration;
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(100);
+    /// pb.set_position(80);
+    /// pb.finish_animated(Duration::from_millis(300));
+    /// ```
+    pub fn finish_animated(&self, duration: Duration) {
+        if self.is_finished() {
+            return;
+        }
+
+        if !self.is_hidden() && !cfg!(target_arch = "wasm32") {
+            let (start, len) = self.with_state(|state| (state.pos, state.len));
+            let frames = Ord::max((duration.as_secs_f64() * ANIMATION_FPS) as u64, 1);
+            let started = Instant::now();
+            for frame in 1..=frames {
+                // Measured from the start, so that rounding doesn't add up over the frames
+                let progress = frame as f64 / frames as f64;
+                thread::sleep(
+                    (started + duration.mul_f64(progress))
+                        .saturating_duration_since(Instant::now()),
+                );
+                let pos = start + (len.saturating_sub(start) as f64 * progress) as u64;
+                self.state().update_and_force_draw(Instant::now(), |state| {
+                    state.pos = pos;
+                });
+            }
+        }
+
+        self.finish_using_style();
     }
 
     /// Sets a different draw target for the progress bar
@@ -433,15 +1073,59 @@ impl ProgressBar {
     /// [`MultiProgress::add`]: crate::MultiProgress::add
     /// [`MultiProgress::set_draw_target`]: crate::MultiProgress::set_draw_target
     pub fn set_draw_target(&self, target: ProgressDrawTarget) {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state();
         state.draw_target.disconnect(Instant::now());
         state.draw_target = target;
     }
 
+    /// Returns the error of the last attempt to draw the progress bar, if it failed
+    ///
+    /// Drawing fails e.g. when the terminal is gone or the pipe the progress bar is written to
+    /// was closed. The error is cleared once the progress bar is drawn successfully.
+    pub fn last_draw_error(&self) -> Option<io::Error> {
+        self.state().draw_errors.last.as_ref().map(copy_error)
+    }
+
+    /// Calls `f` with each error drawing the progress bar
+    ///
+    /// **Note:** The internal lock is held while `f` is called, so it must not call back into
+    /// the progress bar.
+    ///
+    /// ```rust,no_run
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(100);
+    /// pb.on_draw_error(|err| eprintln!("failed to draw progress: {}", err));
+    /// ```
+    pub fn on_draw_error(&self, f: impl Fn(&io::Error) + Send + 'static) {
+        self.state().draw_errors.handler = Some(Box::new(f));
+    }
+
+    /// Hides the progress bar once drawing it failed `limit` times in a row
+    ///
+    /// This stops a progress bar writing to a closed pipe from failing over and over. Its state
+    /// is still updated, and the last error is kept, see [`ProgressBar::last_draw_error()`].
+    /// `None` removes the limit, which is the default.
+    ///
+    /// If the progress bar belongs to a [`MultiProgress`], the draw target of the
+    /// `MultiProgress` is hidden instead, as all its progress bars draw to it.
+    ///
+    /// [`MultiProgress`]: crate::MultiProgress
+    pub fn set_draw_failure_limit(&self, limit: impl Into<Option<u32>>) {
+        self.state().draw_errors.limit = limit.into();
+    }
+
+    /// Draws the progress bar again, e.g. after its draw target changed
+    pub(crate) fn redraw(&self, now: Instant) {
+        let _ = self.state().draw(true, now);
+    }
+
     /// Hide the progress bar temporarily, execute `f`, then redraw the progress bar
     ///
     /// Useful for external code that writes to the standard output.
     ///
+    /// If the progress bar was added to a [`MultiProgress`], all progress bars of the
+    /// `MultiProgress` are hidden, see [`MultiProgress::suspend()`].
+    ///
     /// **Note:** The internal lock is held while `f` is executed. Other threads trying to print
     /// anything on the progress bar will be blocked until `f` finishes.
     /// Therefore, it is recommended to avoid long-running operations in `f`.
@@ -453,9 +1137,21 @@ impl ProgressBar {
     ///     println!("Log message");
     /// })
     /// ```
+    ///
+    /// [`MultiProgress`]: crate::MultiProgress
+    /// [`MultiProgress::suspend()`]: crate::MultiProgress::suspend
     pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state();
         let now = Instant::now();
+        if let Some((multi, _)) = state.draw_target.remote() {
+            let multi = multi.clone();
+            let suspended = multi.write().unwrap().suspend(now);
+            let ret = f();
+            let _ = state.draw(true, now);
+            multi.write().unwrap().resume(suspended, Instant::now());
+            return ret;
+        }
+
         if let Some(drawable) = state.draw_target.drawable(true, now) {
             let _ = drawable.clear();
         }
@@ -465,6 +1161,60 @@ impl ProgressBar {
         ret
     }
 
+    /// Runs `command`, showing its output above the progress bar
+    ///
+    /// The standard output and error of the command are captured and printed line by line with
+    /// [`ProgressBar::println()`], so they don't garble the progress bar. The progress bar is
+    /// ticked while the command runs. If the progress bar is hidden, the lines are written to
+    /// the standard output and error of this process instead. Returns the exit status of the
+    /// command.
+    ///
+    /// ```rust,no_run
+    /// # use std::process::Command;
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new_spinner().with_message("building");
+    /// let status = pb.run_command(Command::new("cargo").arg("build")).unwrap();
+    /// pb.finish_with_message(format!("build finished: {}", status));
+    /// ```
+    pub fn run_command(&self, command: &mut Command) -> io::Result<ExitStatus> {
+        let child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        self.wait_child(child)
+    }
+
+    /// Waits for `child` to exit, showing its output above the progress bar
+    ///
+    /// This works like [`ProgressBar::run_command()`] for an already spawned child process.
+    /// Only the output streams that were set up as [`Stdio::piped()`] are captured.
+    pub fn wait_child(&self, mut child: Child) -> io::Result<ExitStatus> {
+        let (tx, rx) = mpsc::channel();
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(forward_lines(stdout, false, tx.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(forward_lines(stderr, true, tx.clone()));
+        }
+        drop(tx);
+
+        loop {
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok((line, _)) if !self.is_hidden() => self.println(line),
+                Ok((line, false)) => println!("{}", line),
+                Ok((line, true)) => eprintln!("{}", line),
+                Err(RecvTimeoutError::Timeout) => self.tick(),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        for reader in readers {
+            let _ = reader.join();
+        }
+        child.wait()
+    }
+
     /// Wraps an [`Iterator`] with the progress bar
     ///
     /// ```rust,no_run
@@ -479,6 +1229,28 @@ impl ProgressBar {
         it.progress_with(self.clone())
     }
 
+    /// Wraps a channel receiver with the progress bar
+    ///
+    /// The progress bar advances by 1 per received item, and finishes once the channel is closed
+    /// and empty. Besides [`mpsc::Receiver`], this accepts any receiver that can be turned into a
+    /// blocking iterator, such as the receivers of `crossbeam-channel`.
+    ///
+    /// ```rust
+    /// # use std::sync::mpsc;
+    /// # use std::thread;
+    /// # use indicatif::ProgressBar;
+    /// let (tx, rx) = mpsc::channel();
+    /// thread::spawn(move || (0..10).for_each(|i| tx.send(i).unwrap()));
+    /// let pb = ProgressBar::new(10);
+    /// for item in pb.wrap_recv(rx) {
+    ///     // ...
+    /// }
+    /// assert!(pb.is_finished());
+    /// ```
+    pub fn wrap_recv<R: IntoIterator>(&self, rx: R) -> ProgressBarIter<R::IntoIter> {
+        self.wrap_iter(rx.into_iter())
+    }
+
     /// Wraps an [`io::Read`] with the progress bar
     ///
     /// ```rust,no_run
@@ -497,6 +1269,36 @@ impl ProgressBar {
         ProgressBarIter {
             progress: self.clone(),
             it: read,
+            seek_progress: true,
+            finish_at_end: false,
+        }
+    }
+
+    /// Wraps an [`io::BufRead`] and iterates over its lines
+    ///
+    /// The progress bar advances by the number of bytes consumed for each line, including the
+    /// line ending, so it stays accurate for line-based processing. Like [`io::BufRead::lines()`],
+    /// the yielded lines don't include the line ending.
+    ///
+    /// ```rust,no_run
+    /// # use std::fs::File;
+    /// # use std::io::{self, BufReader};
+    /// # use indicatif::ProgressBar;
+    /// # fn test() -> io::Result<()> {
+    /// let file = File::open("log.txt")?;
+    /// let pb = ProgressBar::new(file.metadata()?.len());
+    /// for line in pb.wrap_lines(BufReader::new(file)) {
+    ///     let _line = line?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wrap_lines<R: io::BufRead>(&self, read: R) -> ProgressBarIter<Lines<R>> {
+        ProgressBarIter {
+            progress: self.clone(),
+            it: Lines(read),
+            seek_progress: true,
+            finish_at_end: false,
         }
     }
 
@@ -518,6 +1320,8 @@ impl ProgressBar {
         ProgressBarIter {
             progress: self.clone(),
             it: write,
+            seek_progress: true,
+            finish_at_end: false,
         }
     }
 
@@ -543,6 +1347,8 @@ impl ProgressBar {
         ProgressBarIter {
             progress: self.clone(),
             it: write,
+            seek_progress: true,
+            finish_at_end: false,
         }
     }
     #[cfg(feature = "tokio")]
@@ -564,43 +1370,244 @@ impl ProgressBar {
         ProgressBarIter {
             progress: self.clone(),
             it: write,
+            seek_progress: true,
+            finish_at_end: false,
+        }
+    }
+
+    #[cfg(feature = "futures-io")]
+    /// Wraps a [`futures_io::AsyncRead`] with the progress bar, for use with `async-std` or
+    /// `futures`
+    ///
+    /// ```rust
+    /// # use futures::executor::block_on;
+    /// # use futures::io::{self, AsyncReadExt};
+    /// # use indicatif::ProgressBar;
+    /// let source = io::Cursor::new(vec![0; 1024]);
+    /// let pb = ProgressBar::new(1024);
+    /// let mut buf = Vec::new();
+    /// block_on(pb.wrap_futures_read(source).read_to_end(&mut buf)).unwrap();
+    /// assert_eq!(pb.position(), 1024);
+    /// ```
+    pub fn wrap_futures_read<R: futures_io::AsyncRead + Unpin>(
+        &self,
+        read: R,
+    ) -> ProgressBarIter<R> {
+        ProgressBarIter {
+            progress: self.clone(),
+            it: read,
+            seek_progress: true,
+            finish_at_end: false,
+        }
+    }
+
+    #[cfg(feature = "futures-io")]
+    /// Wraps a [`futures_io::AsyncWrite`] with the progress bar, for use with `async-std` or
+    /// `futures`
+    ///
+    /// ```rust
+    /// # use futures::executor::block_on;
+    /// # use futures::io::{self, AsyncWriteExt};
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(1024);
+    /// let mut target = pb.wrap_futures_write(io::Cursor::new(Vec::new()));
+    /// block_on(target.write_all(&[0; 1024])).unwrap();
+    /// assert_eq!(pb.position(), 1024);
+    /// ```
+    pub fn wrap_futures_write<W: futures_io::AsyncWrite + Unpin>(
+        &self,
+        write: W,
+    ) -> ProgressBarIter<W> {
+        ProgressBarIter {
+            progress: self.clone(),
+            it: write,
+            seek_progress: true,
+            finish_at_end: false,
+        }
+    }
+
+    #[cfg(feature = "futures")]
+    /// Wraps a [`futures_core::Stream`] with the progress bar
+    ///
+    /// The progress bar advances by 1 per item. Use [`ProgressBarIter::bytes_mode()`] to advance
+    /// it by the length of each chunk in a stream of bytes instead.
+    ///
+    /// ```rust,no_run
+    /// # use futures::stream::{self, StreamExt};
+    /// # use indicatif::ProgressBar;
+    /// # async fn test() {
+    /// let pb = ProgressBar::new(3);
+    /// let items: Vec<_> = pb.wrap_stream(stream::iter(vec![1, 2, 3])).collect().await;
+    /// # }
+    /// ```
+    pub fn wrap_stream<S: futures_core::Stream + Unpin>(&self, stream: S) -> ProgressBarIter<S> {
+        ProgressBarIter {
+            progress: self.clone(),
+            it: stream,
+            seek_progress: true,
+            finish_at_end: false,
+        }
+    }
+
+    #[cfg(feature = "futures")]
+    /// Wraps a [`futures_sink::Sink`] with the progress bar
+    ///
+    /// The progress bar advances by 1 per item sent. To finish it when the sink is closed, see
+    /// [`ProgressBarIter::with_finish_at_end()`].
+    ///
+    /// ```rust
+    /// # use futures::executor::block_on;
+    /// # use futures::sink::SinkExt;
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(3);
+    /// let mut sink = pb.wrap_sink(Vec::new());
+    /// block_on(sink.send("message")).unwrap();
+    /// assert_eq!(pb.position(), 1);
+    /// ```
+    pub fn wrap_sink<S: Unpin>(&self, sink: S) -> ProgressBarIter<S> {
+        ProgressBarIter {
+            progress: self.clone(),
+            it: sink,
+            seek_progress: true,
+            finish_at_end: false,
         }
     }
 
     fn update_and_draw<F: FnOnce(&mut ProgressState)>(&self, now: Instant, f: F) {
         // Delegate to the wrapped state.
-        let mut state = self.state.lock().unwrap();
+        let mut state = self.state();
         state.update_and_draw(now, f);
     }
 
+    /// Applies the ticks, and the position with the `sharded-state` feature, updated without
+    /// locking, and draws if needed
+    ///
+    /// Nothing is done if another thread holds the state, it applies the updates once it
+    /// releases the state instead.
+    fn try_sync(&self) {
+        while self.pending_ticks.load(Ordering::Relaxed) != 0 {
+            let mut state = match self.try_state() {
+                Some(state) => state,
+                None => return,
+            };
+            // Updates made meanwhile are picked up by the next iteration, not by the guard
+            state.bar = None;
+            let ticks = self.pending_ticks.swap(0, Ordering::Relaxed);
+            state.update_and_draw(Instant::now(), |state| {
+                if state.steady_tick == 0 || state.tick == 0 {
+                    state.tick = state.tick.saturating_add(ticks);
+                }
+            });
+        }
+    }
+
+    /// Moves the position to `pos` without counting it as progress, see
+    /// [`ProgressBarIter::with_seek_progress()`]
+    pub(crate) fn skip_to(&self, pos: u64) {
+        let now = Instant::now();
+        let mut state = self.state();
+        state.state.skip_to(pos, now);
+        let _ = state.draw(false, now);
+    }
+
+    /// Returns the current position
+    #[cfg(feature = "sharded-state")]
+    pub fn position(&self) -> u64 {
+        self.pos.load(Ordering::Relaxed)
+    }
+
     /// Returns the current position
+    #[cfg(not(feature = "sharded-state"))]
     pub fn position(&self) -> u64 {
-        self.state.lock().unwrap().state.pos
+        let state = &mut self.state().state;
+        state.sync_shared_pos(Instant::now());
+        state.pos
     }
 
     /// Returns the current length
     pub fn length(&self) -> u64 {
-        self.state.lock().unwrap().state.len
+        self.state().state.len
+    }
+
+    /// Returns the current message
+    pub fn message(&self) -> String {
+        self.state().state.message().to_string()
+    }
+
+    /// Returns the current prefix
+    pub fn prefix(&self) -> String {
+        self.state().state.prefix().to_string()
+    }
+
+    /// Calls `f` with a read-only view of the current state of the progress bar
+    ///
+    /// The progress bar is locked while `f` runs, so `f` must not call back into this progress
+    /// bar.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(10).with_message("downloading");
+    /// let fraction = pb.with_state(|state| state.fraction());
+    /// assert_eq!(fraction, 0.0);
+    /// pb.set_message(format!("{} (retrying)", pb.message()));
+    /// ```
+    pub fn with_state<R>(&self, f: impl FnOnce(&ProgressState) -> R) -> R {
+        f(&self.state().state)
+    }
+
+    /// Returns a serializable snapshot of the state of the progress bar
+    ///
+    /// This helps debugging progress bars, e.g. by logging the snapshot as JSON, or comparing
+    /// progress flows against golden files in tests.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressBar;
+    /// let pb = ProgressBar::new(10);
+    /// pb.inc(3);
+    /// let json = serde_json::to_string(&pb.as_serde()).unwrap();
+    /// assert!(json.contains(r#""pos":3"#));
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn as_serde(&self) -> crate::ProgressSnapshot {
+        crate::ProgressSnapshot::new(&self.state().state)
     }
 
     /// Returns the current ETA
     pub fn eta(&self) -> Duration {
-        self.state.lock().unwrap().state.eta()
+        self.state().state.eta()
+    }
+
+    /// Returns the optimistic and pessimistic ETA, see [`ProgressState::eta_range()`]
+    pub fn eta_range(&self) -> (Duration, Duration) {
+        self.state().state.eta_range()
     }
 
     /// Returns the current rate of progress
     pub fn per_sec(&self) -> f64 {
-        self.state.lock().unwrap().state.per_sec()
+        self.state().state.per_sec()
     }
 
     /// Returns the current expected duration
     pub fn duration(&self) -> Duration {
-        self.state.lock().unwrap().state.duration()
+        self.state().state.duration()
     }
 
     /// Returns the current elapsed time
+    ///
+    /// Once the progress bar is finished or abandoned, the elapsed time no longer advances.
     pub fn elapsed(&self) -> Duration {
-        self.state.lock().unwrap().state.started.elapsed()
+        self.state().state.elapsed()
+    }
+
+    /// Returns the time the progress bar took until it was finished or abandoned
+    ///
+    /// Returns `None` while the progress bar is in progress.
+    pub fn final_elapsed(&self) -> Option<Duration> {
+        let state = &self.state().state;
+        state.finished_at.map(|finished_at| {
+            let elapsed = finished_at.saturating_duration_since(state.started);
+            saturating_add(state.resumed_elapsed, elapsed)
+        })
     }
 
     /// Index in the `MultiState`
@@ -608,17 +1615,136 @@ impl ProgressBar {
         self.state().draw_target.remote().map(|(_, idx)| idx)
     }
 
-    pub(crate) fn state(&self) -> MutexGuard<'_, BarState> {
-        self.state.lock().unwrap()
+    pub(crate) fn state(&self) -> BarGuard<'_> {
+        BarGuard::new(self.state.lock().unwrap(), self)
+    }
+
+    /// Locks the state, unless another thread holds it
+    fn try_state(&self) -> Option<BarGuard<'_>> {
+        let state = match self.state.try_lock() {
+            Ok(state) => state,
+            Err(TryLockError::WouldBlock) => return None,
+            // A panic while drawing shouldn't take the updates down with it
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+        };
+        Some(BarGuard::new(state, self))
+    }
+}
+
+/// The locked state of a progress bar
+///
+/// The work left over for the [`MultiProgress`](crate::MultiProgress) the progress bar belongs to is done once it's
+/// unlocked, see [`ProgressDrawTarget::after_unlock()`]. The updates made without locking
+/// while it was locked are applied then too.
+pub(crate) struct BarGuard<'a> {
+    guard: Option<MutexGuard<'a, BarState>>,
+    bar: Option<&'a ProgressBar>,
+}
+
+impl<'a> BarGuard<'a> {
+    fn new(state: MutexGuard<'a, BarState>, bar: &'a ProgressBar) -> BarGuard<'a> {
+        BarGuard {
+            guard: Some(state),
+            bar: Some(bar),
+        }
+    }
+}
+
+impl Deref for BarGuard<'_> {
+    type Target = BarState;
+
+    fn deref(&self) -> &BarState {
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for BarGuard<'_> {
+    fn deref_mut(&mut self) -> &mut BarState {
+        self.guard.as_mut().unwrap()
+    }
+}
+
+impl Drop for BarGuard<'_> {
+    fn drop(&mut self) {
+        let after_unlock = self
+            .guard
+            .as_ref()
+            .and_then(|bar| bar.draw_target.after_unlock());
+        let pending_frame = self
+            .guard
+            .as_mut()
+            .and_then(|bar| bar.draw_target.take_pending_frame());
+        drop(self.guard.take());
+        if let Some(after_unlock) = after_unlock {
+            after_unlock.run();
+        }
+        if let (Some(at), Some(bar)) = (pending_frame, self.bar) {
+            ticker().schedule_draw(Scheduled::Bar(bar.downgrade()), at);
+        }
+        if let Some(bar) = self.bar {
+            bar.try_sync();
+        }
+    }
+}
+
+/// Sends the lines read from `read` to `tx` from a new thread, along with `is_stderr`.
+fn forward_lines(
+    read: impl io::Read + Send + 'static,
+    is_stderr: bool,
+    tx: mpsc::Sender<(String, bool)>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut read = io::BufReader::new(read);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match io::BufRead::read_until(&mut read, b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&buf);
+                    let line = line.trim_end_matches(&['\r', '\n'][..]).to_owned();
+                    if tx.send((line, is_stderr)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Lock-free access to the position of a [`ProgressBar`]
+///
+/// Created with [`ProgressBar::position_handle()`]. This is cheap to clone, clones update the same
+/// progress bar.
+#[derive(Clone, Debug)]
+pub struct PositionHandle(Arc<AtomicU64>);
+
+impl PositionHandle {
+    /// Sets the position of the progress bar
+    pub fn store(&self, pos: u64) {
+        self.0.store(pos, Ordering::Relaxed);
+    }
+
+    /// Advances the position of the progress bar by `delta`, returning the previous position
+    pub fn fetch_add(&self, delta: u64) -> u64 {
+        self.0.fetch_add(delta, Ordering::Relaxed)
+    }
+
+    /// Returns the position of the progress bar
+    pub fn load(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
     }
 }
 
 /// A weak reference to a `ProgressBar`.
 ///
 /// Useful for creating custom steady tick implementations
-#[derive(Clone, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct WeakProgressBar {
     state: Weak<Mutex<BarState>>,
+    pending_ticks: Weak<AtomicU64>,
+    #[cfg(feature = "sharded-state")]
+    pos: Weak<AtomicU64>,
 }
 
 impl WeakProgressBar {
@@ -634,26 +1760,79 @@ impl WeakProgressBar {
     ///
     /// [`ProgressBar`]: struct.ProgressBar.html
     pub fn upgrade(&self) -> Option<ProgressBar> {
-        self.state.upgrade().map(|state| ProgressBar { state })
+        let state = self.state.upgrade()?;
+        let pending_ticks = self.pending_ticks.upgrade()?;
+        Some(ProgressBar {
+            state,
+            pending_ticks,
+            #[cfg(feature = "sharded-state")]
+            pos: self.pos.upgrade()?,
+        })
+    }
+
+    /// Returns true if this is a reference to `pb`
+    pub(crate) fn is(&self, pb: &ProgressBar) -> bool {
+        Weak::as_ptr(&self.state) == Arc::as_ptr(&pb.state)
+    }
+
+    /// Returns true if both refer to the same progress bar
+    pub(crate) fn ptr_eq(&self, other: &WeakProgressBar) -> bool {
+        self.state.ptr_eq(&other.state)
+    }
+
+    /// Returns true if the progress bar has been dropped
+    pub(crate) fn is_dropped(&self) -> bool {
+        self.state.strong_count() == 0
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::term_like::tests::Recorder;
+
+    #[test]
+    fn draw_errors() {
+        use std::sync::atomic::AtomicUsize;
+
+        use crate::term_like::tests::BrokenPipe;
+
+        let pb =
+            ProgressBar::with_draw_target(10, ProgressDrawTarget::term_like(Box::new(BrokenPipe)));
+        let errors = Arc::new(AtomicUsize::new(0));
+        pb.on_draw_error({
+            let errors = errors.clone();
+            move |err| {
+                assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+                errors.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        pb.set_draw_failure_limit(2);
+        assert!(pb.last_draw_error().is_none());
+
+        pb.println("first");
+        pb.println("second");
+        pb.println("third");
+        assert_eq!(errors.load(Ordering::SeqCst), 2);
+        assert!(pb.is_hidden());
+        assert_eq!(
+            pb.last_draw_error().map(|err| err.kind()),
+            Some(io::ErrorKind::BrokenPipe)
+        );
+    }
 
     #[allow(clippy::float_cmp)]
     #[test]
     fn test_pbar_zero() {
         let pb = ProgressBar::new(0);
-        assert_eq!(pb.state.lock().unwrap().state.fraction(), 1.0);
+        assert_eq!(pb.state().state.fraction(), 1.0);
     }
 
     #[allow(clippy::float_cmp)]
     #[test]
     fn test_pbar_maxu64() {
         let pb = ProgressBar::new(!0);
-        assert_eq!(pb.state.lock().unwrap().state.fraction(), 0.0);
+        assert_eq!(pb.state().state.fraction(), 0.0);
     }
 
     #[test]
@@ -673,6 +1852,335 @@ mod tests {
         assert_eq!(pos, 2);
     }
 
+    #[test]
+    fn test_resume() {
+        let pb = ProgressBar::resume(100, 50, Duration::from_secs(10));
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+        assert_eq!(pb.position(), 50);
+        assert!(pb.elapsed() >= Duration::from_secs(10));
+        assert!((pb.per_sec() - 5.0).abs() < 1e-9);
+        assert_eq!(pb.eta(), Duration::from_secs(10));
+
+        // Longer than the monotonic clock can go back
+        let elapsed = Duration::from_secs(u64::MAX / 4);
+        let pb = ProgressBar::resume(100, 50, elapsed);
+        assert_eq!(pb.position(), 50);
+        assert!(pb.per_sec() > 0.0);
+        assert!(pb.elapsed() >= elapsed);
+        pb.finish();
+        assert!(pb.final_elapsed().unwrap() >= elapsed);
+    }
+
+    #[test]
+    fn test_notify_on_finish() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let pb = ProgressBar::hidden();
+        let count_clone = count.clone();
+        pb.notify_on_finish(Notify::Callback(Box::new(move || {
+            count_clone.fetch_add(1, Ordering::SeqCst);
+        })));
+
+        pb.inc(1);
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        pb.finish();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        pb.finish_and_clear();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_on_finish() {
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let pb = ProgressBar::hidden().with_style(
+            ProgressStyle::default_bar().on_finish(crate::ProgressFinish::AtCurrentPos),
+        );
+        pb.set_length(10);
+        let clone = finished.clone();
+        pb.on_finish(move |state| clone.lock().unwrap().push(state.pos));
+
+        pb.inc(4);
+        assert!(finished.lock().unwrap().is_empty());
+        drop(pb);
+        assert_eq!(*finished.lock().unwrap(), vec![4]);
+    }
+
+    #[test]
+    fn test_laps() {
+        let pb = ProgressBar::hidden();
+        assert!(pb.laps().is_empty());
+        let first = pb.lap("first");
+        let second = pb.lap("second");
+        let laps = pb.laps();
+        assert_eq!(laps.len(), 2);
+        assert_eq!(laps[0], ("first".into(), first));
+        assert_eq!(laps[1], ("second".into(), second));
+        assert!(first + second <= pb.elapsed());
+
+        pb.reset();
+        assert!(pb.laps().is_empty());
+    }
+
+    #[test]
+    fn test_draw_final_frame() {
+        let recorder = Arc::new(Recorder::default());
+        let target = ProgressDrawTarget::term_like(Box::new(recorder.clone()));
+        let pb = ProgressBar::with_draw_target(10, target)
+            .with_style(ProgressStyle::default_bar().template("{pos}/{len}"));
+        pb.set_draw_final_frame(true);
+        pb.inc(3);
+        pb.finish_and_clear();
+        assert!(recorder.0.lock().unwrap().iter().any(|s| s == "10/10"));
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let recorder = Arc::new(Recorder::default());
+        let target =
+            ProgressDrawTarget::term_like(Box::new(recorder.clone())).with_crlf_line_endings();
+        let pb = ProgressBar::with_draw_target(10, target)
+            .with_style(ProgressStyle::default_bar().template("{pos}\n{len}"));
+        pb.tick();
+        let written = recorder.0.lock().unwrap();
+        assert_eq!(&written[..3], ["0", "\r\n", "10"]);
+        assert!(!written.iter().any(|s| s.ends_with('\n') && s != "\r\n"));
+    }
+
+    #[test]
+    fn test_message_and_prefix() {
+        let pb = ProgressBar::hidden()
+            .with_message("downloading")
+            .with_prefix("[1/2]");
+        assert_eq!(pb.message(), "downloading");
+        assert_eq!(pb.prefix(), "[1/2]");
+
+        pb.set_message(format!("{} (retrying)", pb.message()));
+        assert_eq!(pb.message(), "downloading (retrying)");
+        assert_eq!(pb.with_state(|state| state.message().len()), 22);
+    }
+
+    #[test]
+    fn test_decreasing_direction() {
+        let recorder = Arc::new(Recorder::default());
+        let target = ProgressDrawTarget::term_like(Box::new(recorder.clone()));
+        let pb = ProgressBar::with_draw_target(10, target)
+            .with_style(ProgressStyle::default_bar().template("{bar:10} {pos}/{len} {percent}%"));
+        pb.set_direction(Direction::Decreasing);
+        pb.inc(3);
+        assert_eq!(pb.position(), 3);
+        let written = recorder.0.lock().unwrap();
+        assert!(written.iter().any(|s| s == "███████▏░░ 7/10 70%"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command() {
+        let recorder = Arc::new(Recorder::default());
+        let target = ProgressDrawTarget::term_like(Box::new(recorder.clone()));
+        let pb = ProgressBar::with_draw_target(10, target);
+        let status = pb
+            .run_command(Command::new("sh").args(&["-c", "echo out; echo err >&2; exit 3"]))
+            .unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        let written = recorder.0.lock().unwrap();
+        assert!(written.iter().any(|s| s == "out\n"));
+        assert!(written.iter().any(|s| s == "err\n"));
+    }
+
+    #[test]
+    fn test_message_fn() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let recorder = Arc::new(Recorder::default());
+        let target = ProgressDrawTarget::term_like(Box::new(recorder.clone()));
+        let pb = ProgressBar::with_draw_target(100, target)
+            .with_style(ProgressStyle::default_bar().template("{msg}"));
+        pb.set_draw_delta(10);
+        let counter = calls.clone();
+        pb.set_message_fn(move || format!("call {}", counter.fetch_add(1, Ordering::SeqCst)));
+        for _ in 0..25 {
+            pb.inc(1);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(pb.message(), "call 2");
+
+        pb.set_message("fixed");
+        pb.finish();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(recorder.0.lock().unwrap().iter().any(|s| s == "fixed"));
+    }
+
+    #[test]
+    fn test_tick_and_yield() {
+        let pb = ProgressBar::hidden();
+        let guard = pb.state();
+        pb.tick_and_yield();
+        pb.tick_and_yield();
+        assert_eq!(guard.state.tick, 0);
+        drop(guard);
+        assert_eq!(pb.state().state.tick, 2);
+
+        pb.tick_and_yield();
+        assert_eq!(pb.state().state.tick, 3);
+    }
+
+    #[test]
+    fn test_tick_finished() {
+        let pb = ProgressBar::hidden();
+        pb.tick();
+        pb.finish();
+        let tick = pb.state().state.tick;
+        pb.tick();
+        assert_eq!(pb.state().state.tick, tick);
+    }
+
+    #[test]
+    fn test_elapsed_frozen_on_finish() {
+        let pb = ProgressBar::hidden();
+        assert_eq!(pb.final_elapsed(), None);
+        pb.abandon();
+        let elapsed = pb.final_elapsed().unwrap();
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(pb.elapsed(), elapsed);
+        pb.reset();
+        assert_eq!(pb.final_elapsed(), None);
+    }
+
+    #[test]
+    fn it_can_wrap_a_receiver() {
+        let (tx, rx) = mpsc::channel();
+        let sender = thread::spawn(move || (0..3).for_each(|i| tx.send(i).unwrap()));
+        let pb = ProgressBar::with_draw_target(3, ProgressDrawTarget::hidden());
+        let items = pb.wrap_recv(rx).collect::<Vec<_>>();
+        sender.join().unwrap();
+        assert_eq!(items, [0, 1, 2]);
+        assert_eq!(pb.position(), 3);
+        assert!(pb.is_finished());
+    }
+
+    #[cfg(feature = "sharded-state")]
+    #[test]
+    fn sharded_updates_dont_wait() {
+        let pb = ProgressBar::with_draw_target(100, ProgressDrawTarget::hidden()).with_position(5);
+        let guard = pb.state();
+        // Would deadlock if the update waited for the lock
+        pb.inc(3);
+        pb.set_position(20);
+        pb.inc(2);
+        assert_eq!(pb.position(), 22);
+        assert_eq!(guard.state.pos, 5);
+        // The holder of the lock applies the updates once it's released
+        drop(guard);
+        assert_eq!(pb.with_state(|state| state.pos), 22);
+
+        pb.inc(1);
+        assert_eq!(pb.with_state(|state| state.pos), 23);
+        pb.finish();
+        assert_eq!(pb.position(), 100);
+
+        pb.set_position(!0);
+        pb.inc(1);
+        assert_eq!(pb.position(), !0);
+
+        // A poisoned state doesn't make the updates panic
+        let poisoner = pb.clone();
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.state.lock().unwrap();
+            panic!("poisoning the state");
+        })
+        .join();
+        pb.set_position(7);
+        pb.tick_and_yield();
+        assert_eq!(pb.position(), 7);
+    }
+
+    #[test]
+    fn position_handle() {
+        let pb = ProgressBar::with_draw_target(100, ProgressDrawTarget::hidden()).with_position(5);
+        let handle = pb.position_handle();
+        assert_eq!(handle.load(), 5);
+
+        let threads = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        handle.fetch_add(1);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        pb.inc(10);
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(pb.position(), 55);
+
+        handle.store(80);
+        pb.tick();
+        assert_eq!(pb.with_state(|state| state.pos), 80);
+        pb.finish();
+        assert_eq!(handle.load(), 100);
+    }
+
+    #[test]
+    fn thread_and_task_id() {
+        let pb = ProgressBar::with_draw_target(10, ProgressDrawTarget::hidden())
+            .with_style(ProgressStyle::default_bar().template("{thread}|{task_id}"));
+        let render = |pb: &ProgressBar| {
+            pb.with_state(|state| {
+                let mut lines = Vec::new();
+                state.style.format_state(state, &mut lines, 80, Some(false));
+                lines.concat()
+            })
+        };
+
+        let worker = pb.clone();
+        thread::Builder::new()
+            .name("worker-1".into())
+            .spawn(move || worker.inc(1))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(render(&pb), "worker-1|worker-1");
+
+        pb.set_task_id("download");
+        assert_eq!(
+            render(&pb),
+            format!("{0}|download", thread::current().name().unwrap())
+        );
+    }
+
+    #[test]
+    fn finish_animated() {
+        let recorder = Arc::new(Recorder::default());
+        let pb = ProgressBar::with_draw_target(
+            100,
+            ProgressDrawTarget::term_like(Box::new(recorder.clone())),
+        )
+        .with_style(
+            ProgressStyle::default_bar()
+                .template("{pos}")
+                .on_finish(ProgressFinish::AndLeave),
+        )
+        .with_position(40);
+        let started = Instant::now();
+        pb.finish_animated(Duration::from_millis(100));
+        assert!(pb.is_finished());
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        let written = recorder.0.lock().unwrap();
+        let positions = written
+            .iter()
+            .filter_map(|s| s.trim().parse::<u64>().ok())
+            .collect::<Vec<_>>();
+        // 3 frames at 30 fps, then the final frame
+        assert_eq!(positions, [60, 80, 100, 100]);
+    }
+
     #[test]
     fn test_weak_pb() {
         let pb = ProgressBar::new(0);
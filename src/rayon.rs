@@ -3,6 +3,7 @@ use rayon::iter::{
     plumbing::{Consumer, Folder, Producer, ProducerCallback, UnindexedConsumer},
     IndexedParallelIterator, ParallelIterator,
 };
+use std::borrow::Cow;
 use std::convert::TryFrom;
 
 /// Wraps a Rayon parallel iterator.
@@ -38,11 +39,28 @@ where
         let bar = ProgressBar::new(len).with_style(style);
         self.progress_with(bar)
     }
+
+    /// Wrap an iterator with a progress bar, and set its style and message.
+    fn progress_styled(
+        self,
+        style: crate::ProgressStyle,
+        message: impl Into<Cow<'static, str>>,
+    ) -> ProgressBarIter<Self>
+    where
+        Self: IndexedParallelIterator,
+    {
+        self.progress_with_style(style).with_message(message)
+    }
 }
 
 impl<S: Send, T: ParallelIterator<Item = S>> ParallelProgressIterator for T {
     fn progress_with(self, progress: ProgressBar) -> ProgressBarIter<Self> {
-        ProgressBarIter { it: self, progress }
+        ProgressBarIter {
+            it: self,
+            progress,
+            seek_progress: true,
+            finish_at_end: false,
+        }
     }
 }
 
@@ -100,6 +118,8 @@ impl<T, P: Producer<Item = T>> Producer for ProgressProducer<P> {
         ProgressBarIter {
             it: self.base.into_iter(),
             progress: self.progress,
+            seek_progress: true,
+            finish_at_end: false,
         }
     }
 
@@ -230,5 +250,9 @@ mod test {
             let style = ProgressStyle::default_bar().template("{wide_bar:.red} {percent}/100%");
             v.par_iter().progress_with_style(style)
         });
+        wrap({
+            let style = ProgressStyle::default_bar().template("{msg} {wide_bar}");
+            v.par_iter().progress_styled(style, "doubling")
+        });
     }
 }
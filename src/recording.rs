@@ -0,0 +1,172 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::state::{saturating_add, ProgressState};
+use crate::time::Instant;
+
+/// Minimum time between two samples of a recording
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A recording of the progress of a progress bar over time.
+///
+/// Created with [`ProgressBar::record()`]. The position and rate of the progress bar are sampled
+/// whenever it is updated, at most every 100 ms, and once more when it finishes. This is cheap to
+/// clone, clones refer to the same recording.
+///
+/// [`ProgressBar::record()`]: crate::ProgressBar::record
+#[derive(Clone, Debug, Default)]
+pub struct ProgressRecording {
+    inner: Arc<Mutex<RecordingState>>,
+}
+
+#[derive(Debug, Default)]
+struct RecordingState {
+    samples: Vec<Sample>,
+    last: Option<Instant>,
+    finished: bool,
+}
+
+/// The progress of a progress bar at a moment in time, see [`ProgressRecording`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// Time since the progress bar was started
+    pub elapsed: Duration,
+    /// Position of the progress bar
+    pub pos: u64,
+    /// Length of the progress bar
+    pub len: u64,
+    /// Steps per second
+    pub per_sec: f64,
+}
+
+impl ProgressRecording {
+    /// Records a sample of `state`, unless the last sample was taken too recently.
+    pub(crate) fn sample(&self, state: &ProgressState, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.finished {
+            return;
+        }
+
+        let finished = state.is_finished();
+        let due = match inner.last {
+            Some(last) => now.saturating_duration_since(last) >= SAMPLE_INTERVAL,
+            None => true,
+        };
+        if !due && !finished {
+            return;
+        }
+
+        inner.last = Some(now);
+        inner.finished = finished;
+        inner.samples.push(Sample {
+            elapsed: saturating_add(
+                state.resumed_elapsed,
+                now.saturating_duration_since(state.started),
+            ),
+            pos: state.pos,
+            len: state.len,
+            per_sec: state.per_sec(),
+        });
+    }
+
+    /// Returns the samples recorded so far.
+    pub fn samples(&self) -> Vec<Sample> {
+        self.inner.lock().unwrap().samples.clone()
+    }
+
+    /// Writes the samples as CSV, with a header row.
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(w, "elapsed_secs,pos,len,per_sec")?;
+        for sample in self.samples() {
+            writeln!(
+                w,
+                "{:.3},{},{},{:.3}",
+                sample.elapsed.as_secs_f64(),
+                sample.pos,
+                sample.len,
+                finite(sample.per_sec)
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the samples as a JSON array of objects.
+    pub fn write_json(&self, mut w: impl Write) -> io::Result<()> {
+        write!(w, "[")?;
+        for (i, sample) in self.samples().iter().enumerate() {
+            if i > 0 {
+                write!(w, ",")?;
+            }
+            write!(
+                w,
+                r#"{{"elapsed_secs":{:.3},"pos":{},"len":{},"per_sec":{:.3}}}"#,
+                sample.elapsed.as_secs_f64(),
+                sample.pos,
+                sample.len,
+                finite(sample.per_sec)
+            )?;
+        }
+        writeln!(w, "]")
+    }
+
+    /// Saves the samples to `path`, as JSON if its extension is `json` and as CSV otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut w = BufWriter::new(File::create(path)?);
+        match path.extension().map_or(false, |ext| ext == "json") {
+            true => self.write_json(&mut w)?,
+            false => self.write_csv(&mut w)?,
+        }
+        w.flush()
+    }
+}
+
+/// Replaces infinite and NaN rates, which can't be represented in JSON, by 0.
+fn finite(v: f64) -> f64 {
+    match v.is_finite() {
+        true => v,
+        false => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording_export() {
+        let recording = ProgressRecording::default();
+        let mut state = ProgressState::new(10);
+        let start = state.started;
+        state.pos = 3;
+        recording.sample(&state, start + Duration::from_millis(10));
+        state.pos = 4;
+        recording.sample(&state, start + Duration::from_millis(50));
+        state.pos = 10;
+        recording.sample(&state, start + Duration::from_millis(200));
+        assert_eq!(
+            recording
+                .samples()
+                .iter()
+                .map(|s| s.pos)
+                .collect::<Vec<_>>(),
+            [3, 10]
+        );
+
+        let mut csv = Vec::new();
+        recording.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("elapsed_secs,pos,len,per_sec"));
+        assert!(lines.next().unwrap().starts_with("0.010,3,10,"));
+
+        let mut json = Vec::new();
+        recording.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with(r#"[{"elapsed_secs":0.010,"pos":3,"len":10,"per_sec":"#));
+        assert!(json.ends_with("}]\n"));
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::progress_bar::{ProgressBar, WeakProgressBar};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::default);
+
+/// Weak references to the progress bars created while the registry is enabled
+#[derive(Default)]
+struct Registry {
+    bars: Mutex<Vec<WeakProgressBar>>,
+}
+
+impl Registry {
+    fn register(&self, pb: &ProgressBar) {
+        let mut bars = self.bars.lock().unwrap();
+        // Dropped progress bars are pruned here too, so the registry only grows with the number
+        // of progress bars alive at the same time
+        bars.retain(|bar| !bar.is_dropped());
+        bars.push(pb.downgrade());
+    }
+
+    fn active_bars(&self) -> Vec<WeakProgressBar> {
+        let bars = {
+            let mut bars = self.bars.lock().unwrap();
+            bars.retain(|bar| !bar.is_dropped());
+            bars.clone()
+        };
+
+        // Upgrading may drop the last reference to a progress bar, which must not happen while
+        // the registry is locked
+        bars.into_iter()
+            .filter(|bar| bar.upgrade().map_or(false, |pb| !pb.is_finished()))
+            .collect()
+    }
+}
+
+/// Enables the global registry of progress bars.
+///
+/// Once enabled, every progress bar created afterwards is tracked, so that it can be found with
+/// [`active_bars()`]. The registry is disabled by default, as it adds a little overhead to the
+/// creation of every progress bar.
+pub fn enable_registry() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Returns the progress bars that are alive and not finished yet.
+///
+/// This allows e.g. signal handlers or logging frameworks to suspend or clear all progress bars
+/// without access to the progress bars themselves. Only progress bars created after
+/// [`enable_registry()`] was called are returned.
+///
+/// ```rust
+/// # use indicatif::{active_bars, enable_registry, ProgressBar};
+/// enable_registry();
+/// let pb = ProgressBar::hidden();
+/// for bar in active_bars().iter().filter_map(|bar| bar.upgrade()) {
+///     bar.abandon_with_message("interrupted");
+/// }
+/// assert!(pb.is_finished());
+/// ```
+pub fn active_bars() -> Vec<WeakProgressBar> {
+    REGISTRY.active_bars()
+}
+
+/// Adds `pb` to the registry, if it's enabled.
+pub(crate) fn register(pb: &ProgressBar) {
+    if ENABLED.load(Ordering::SeqCst) {
+        REGISTRY.register(pb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry() {
+        // A local registry, enabling the global one would track the progress bars of all tests
+        let registry = Registry::default();
+        let pb = ProgressBar::hidden().with_message("registry test");
+        registry.register(&pb);
+        let is_registered = || {
+            registry
+                .active_bars()
+                .iter()
+                .filter_map(WeakProgressBar::upgrade)
+                .any(|bar| bar.message() == "registry test")
+        };
+
+        assert!(is_registered());
+        pb.finish();
+        assert!(!is_registered());
+    }
+
+    #[test]
+    fn registry_prunes_dropped_bars() {
+        let registry = Registry::default();
+        for _ in 0..10 {
+            registry.register(&ProgressBar::hidden());
+        }
+        assert_eq!(registry.bars.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,110 @@
+//! Building blocks for alternative renderers
+//!
+//! **This module is unstable**: it is only available with the `unstable-renderer` feature, and
+//! may change in any release.
+//!
+//! A [`Renderer`] set with [`ProgressBar::set_renderer()`] is handed the [`ProgressState`] of a
+//! progress bar whenever it would be drawn. Combined with a hidden draw target, this allows
+//! rendering progress bars to a GUI, a web page or anything else, while still relying on the
+//! estimation and state handling of `indicatif`. The functions in this module expose the values
+//! and layout used by the terminal renderer.
+//!
+//! ```rust
+//! # use indicatif::renderer;
+//! # use indicatif::{ProgressBar, ProgressDrawTarget};
+//! let pb = ProgressBar::with_draw_target(100, ProgressDrawTarget::hidden());
+//! pb.set_renderer(|state: &indicatif::ProgressState| {
+//!     let bar = renderer::format_bar(state, 30);
+//!     println!("{} {:.1}/s", bar, renderer::per_sec(state));
+//! });
+//! pb.inc(1);
+//! ```
+//!
+//! [`ProgressBar::set_renderer()`]: crate::ProgressBar::set_renderer
+
+use std::time::Duration;
+
+use crate::state::ProgressState;
+use crate::style::ProgressStyle;
+
+/// Renders the state of a progress bar
+pub trait Renderer: Send {
+    /// Called with the current state whenever the progress bar would be drawn
+    fn render(&mut self, state: &ProgressState);
+}
+
+impl<F: FnMut(&ProgressState) + Send> Renderer for F {
+    fn render(&mut self, state: &ProgressState) {
+        self(state)
+    }
+}
+
+/// Returns the style of the progress bar.
+pub fn style(state: &ProgressState) -> &ProgressStyle {
+    &state.style
+}
+
+/// Returns the number of steps per second.
+pub fn per_sec(state: &ProgressState) -> f64 {
+    state.per_sec()
+}
+
+/// Returns the expected total duration, i.e. the elapsed time plus the ETA.
+pub fn duration(state: &ProgressState) -> Duration {
+    state.duration()
+}
+
+/// Returns the current spinner frame.
+pub fn tick_str(state: &ProgressState) -> &str {
+    state.current_tick_str()
+}
+
+/// Returns the position as shown by the template keys, taking the direction into account.
+pub fn displayed_pos(state: &ProgressState) -> u64 {
+    state.displayed_pos()
+}
+
+/// Returns the completion as shown by the bar, taking the direction into account.
+pub fn displayed_fraction(state: &ProgressState) -> f32 {
+    state.displayed_fraction()
+}
+
+/// Formats the lines of the progress bar's template, laid out for `width` columns.
+///
+/// The lines contain ANSI color codes if the style uses colors and `console` detects support
+/// for them.
+pub fn format_lines(state: &ProgressState, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    state.style.format_state(state, &mut lines, width, None);
+    lines
+}
+
+/// Formats the `{bar}` of the progress bar, `width` columns wide.
+pub fn format_bar(state: &ProgressState, width: usize) -> String {
+    let mut buf = String::new();
+    state.style.write_bar(&mut buf, state, width, None, None);
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{ProgressBar, ProgressDrawTarget};
+
+    #[test]
+    fn custom_renderer() {
+        let rendered = Arc::new(Mutex::new(Vec::new()));
+        let pb = ProgressBar::with_draw_target(4, ProgressDrawTarget::hidden())
+            .with_style(ProgressStyle::default_bar().progress_chars("#>-"));
+        pb.set_draw_delta(1);
+        let sink = rendered.clone();
+        pb.set_renderer(move |state: &ProgressState| {
+            sink.lock().unwrap().push(format_bar(state, 4))
+        });
+        pb.inc(1);
+        pb.finish();
+        assert_eq!(*rendered.lock().unwrap(), ["#>--", "####"]);
+    }
+}
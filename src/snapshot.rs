@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use crate::state::ProgressState;
+
+/// The state of a progress bar at a moment in time, see [`ProgressBar::as_serde()`]
+///
+/// Durations are serialized by `serde` as seconds and nanoseconds.
+///
+/// [`ProgressBar::as_serde()`]: crate::ProgressBar::as_serde
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct ProgressSnapshot {
+    /// Position of the progress bar
+    pub pos: u64,
+    /// Length of the progress bar, `None` for spinners
+    pub len: Option<u64>,
+    /// Completion of the progress bar, between 0 and 1
+    pub fraction: f32,
+    /// Message of the progress bar
+    pub message: String,
+    /// Prefix of the progress bar
+    pub prefix: String,
+    /// Number of ticks of the spinner
+    pub tick: u64,
+    /// Time since the progress bar was started
+    pub elapsed: Duration,
+    /// Expected remaining time
+    pub eta: Duration,
+    /// Steps per second
+    pub per_sec: f64,
+    /// Whether the progress bar is finished
+    pub finished: bool,
+    /// Whether the progress bar is paused, see [`ProgressBar::set_paused()`]
+    ///
+    /// [`ProgressBar::set_paused()`]: crate::ProgressBar::set_paused
+    pub paused: bool,
+    /// Task id of the progress bar, if set
+    pub task_id: Option<String>,
+    /// Position and length of the secondary quantity, see [`ProgressBar::set_aux_position()`]
+    ///
+    /// [`ProgressBar::set_aux_position()`]: crate::ProgressBar::set_aux_position
+    pub aux: (u64, u64),
+    /// Labels and durations of the laps, see [`ProgressBar::lap()`]
+    ///
+    /// [`ProgressBar::lap()`]: crate::ProgressBar::lap
+    pub laps: Vec<(String, Duration)>,
+}
+
+impl ProgressSnapshot {
+    pub(crate) fn new(state: &ProgressState) -> ProgressSnapshot {
+        ProgressSnapshot {
+            pos: state.pos,
+            len: Some(state.len).filter(|&len| len != !0),
+            fraction: state.fraction(),
+            message: state.message().to_owned(),
+            prefix: state.prefix().to_owned(),
+            tick: state.tick,
+            elapsed: state.elapsed(),
+            eta: state.eta(),
+            per_sec: state.per_sec(),
+            finished: state.is_finished(),
+            paused: state.paused,
+            task_id: state.task_id.as_ref().map(|id| id.to_string()),
+            aux: (state.aux_pos, state.aux_len),
+            laps: state
+                .laps
+                .iter()
+                .map(|(label, duration)| (label.to_string(), *duration))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ProgressBar;
+
+    #[test]
+    fn snapshot() {
+        let pb = ProgressBar::hidden();
+        pb.set_length(10);
+        pb.set_position(4);
+        pb.set_message("working");
+
+        let snapshot = pb.as_serde();
+        assert_eq!(snapshot.len, Some(10));
+        assert_eq!(snapshot.message, "working");
+        assert!(!snapshot.finished);
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["pos"], 4);
+        assert_eq!(json["fraction"], 0.4f32 as f64);
+        assert_eq!(json["task_id"], serde_json::Value::Null);
+
+        assert_eq!(ProgressBar::new_spinner().as_serde().len, None);
+    }
+}
@@ -1,15 +1,45 @@
 use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::fmt;
 use std::io;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use crate::draw_target::ProgressDrawTarget;
+use crate::draw_target::{ProgressDrawTarget, SystemProgress};
+use crate::recording::ProgressRecording;
 use crate::style::{ProgressFinish, ProgressStyle};
+use crate::time::Instant;
 
 pub(crate) struct BarState {
     pub(crate) draw_target: ProgressDrawTarget,
     pub(crate) state: ProgressState,
+    pub(crate) draw_errors: DrawErrors,
+}
+
+/// Errors writing the progress bar to its draw target, and how they are handled
+#[derive(Default)]
+pub(crate) struct DrawErrors {
+    /// Error of the last draw, if it failed
+    pub(crate) last: Option<io::Error>,
+    /// Number of draws that failed in a row
+    consecutive: u32,
+    /// Called with each error
+    pub(crate) handler: Option<DrawErrorHandler>,
+    /// Number of draws failing in a row after which the progress bar is hidden
+    pub(crate) limit: Option<u32>,
+}
+
+pub(crate) type DrawErrorHandler = Box<dyn Fn(&io::Error) + Send>;
+
+/// Copies an I/O error, which doesn't implement `Clone`
+pub(crate) fn copy_error(err: &io::Error) -> io::Error {
+    match err.raw_os_error() {
+        Some(code) => io::Error::from_raw_os_error(code),
+        None => io::Error::new(err.kind(), err.to_string()),
+    }
 }
 
 impl BarState {
@@ -21,23 +51,41 @@ impl BarState {
             ProgressFinish::AtCurrentPos => self.finish_at_current_pos(now),
             ProgressFinish::WithMessage(msg) => {
                 // Equivalent to `self.finish_with_message` but avoids borrow checker error
-                self.state.message.clone_from(msg);
+                self.state.set_message(msg.clone());
                 self.finish(now);
             }
             ProgressFinish::AndClear => self.finish_and_clear(now),
             ProgressFinish::Abandon => self.abandon(now),
             ProgressFinish::AbandonWithMessage(msg) => {
                 // Equivalent to `self.abandon_with_message` but avoids borrow checker error
-                self.state.message.clone_from(msg);
+                self.state.set_message(msg.clone());
                 self.abandon(now);
             }
         }
     }
 
+    /// Finishes the progress bar like [`BarState::finish_using_style()`], but leaves the
+    /// position as is if the length is unknown instead of moving it to the end.
+    pub(crate) fn finish_at_end(&mut self, now: Instant) {
+        if self.state.len != !0 {
+            return self.finish_using_style(now);
+        }
+
+        let status = match self.state.style.get_on_finish() {
+            ProgressFinish::AndClear => Status::DoneHidden,
+            ProgressFinish::WithMessage(msg) | ProgressFinish::AbandonWithMessage(msg) => {
+                self.state.set_message(msg.clone());
+                Status::DoneVisible
+            }
+            _ => Status::DoneVisible,
+        };
+        self.update_and_force_draw(now, |state| state.status = status);
+    }
+
     /// Finishes the progress bar and leaves the current message.
     pub(crate) fn finish(&mut self, now: Instant) {
         self.update_and_force_draw(now, |state| {
-            state.pos = state.len;
+            state.complete();
             state.status = Status::DoneVisible;
         });
     }
@@ -53,14 +101,19 @@ impl BarState {
     pub(crate) fn finish_with_message(&mut self, msg: impl Into<Cow<'static, str>>, now: Instant) {
         let msg = msg.into();
         self.update_and_force_draw(now, |state| {
-            state.message = msg;
-            state.pos = state.len;
+            state.set_message(msg);
+            state.complete();
             state.status = Status::DoneVisible;
         });
     }
 
     /// Finishes the progress bar and completely clears it.
     pub(crate) fn finish_and_clear(&mut self, now: Instant) {
+        if self.state.draw_final_frame && !self.state.is_finished() {
+            self.update_and_force_draw(now, |state| {
+                state.pos = state.len;
+            });
+        }
         self.update_and_force_draw(now, |state| {
             state.pos = state.len;
             state.status = Status::DoneHidden;
@@ -78,7 +131,7 @@ impl BarState {
     pub(crate) fn abandon_with_message(&mut self, msg: impl Into<Cow<'static, str>>, now: Instant) {
         let msg = msg.into();
         self.update_and_force_draw(now, |state| {
-            state.message = msg;
+            state.set_message(msg);
             state.status = Status::DoneVisible;
         });
     }
@@ -98,38 +151,124 @@ impl BarState {
         now: Instant,
         f: F,
     ) {
+        let was_finished = self.state.is_finished();
         self.state.update(now, |state| {
             f(state);
         });
+        if !was_finished && self.state.is_finished() {
+            self.state.finished_at = Some(now);
+        }
         self.draw(true, now).ok();
+        if !was_finished && self.state.is_finished() {
+            self.notify_finished();
+        }
+    }
+
+    /// Emits the [`Notify`] set on the progress bar, if any, and runs the finish hooks.
+    fn notify_finished(&mut self) {
+        match &self.state.notify {
+            Some(Notify::Bell) => self.draw_target.ring_bell(),
+            Some(Notify::Callback(f)) => f(),
+            None => {}
+        }
+        // The `MultiProgress` may not have seen the progress bar finish if it's hidden
+        if let Some((multi, idx)) = self.draw_target.remote() {
+            multi.write().unwrap().set_finished(idx, true);
+        }
+        #[cfg(feature = "metrics")]
+        self.state.clear_metrics();
+
+        for hook in mem::take(&mut self.state.finish_hooks) {
+            hook(&self.state);
+        }
     }
 
     pub(crate) fn draw(&mut self, mut force_draw: bool, now: Instant) -> io::Result<()> {
+        self.state.sync_shared_pos(now);
+        self.state.style.reload_template(now);
+        if let Some(recording) = &self.state.recording {
+            recording.sample(&self.state, now);
+        }
+        #[cfg(feature = "unstable-renderer")]
+        if let Some(mut renderer) = self.state.renderer.take() {
+            renderer.render(&self.state);
+            self.state.renderer = Some(renderer);
+        }
+        #[cfg(feature = "metrics")]
+        self.state.export_metrics();
+
         // we can bail early if the draw target is hidden.
         if self.draw_target.is_hidden() {
+            self.draw_target.count_draw();
+            self.state.last_draw = Some((self.state.pos, now));
             return Ok(());
         }
 
-        let width = self.draw_target.width();
         force_draw |= self.state.is_finished();
         let mut drawable = match self.draw_target.drawable(force_draw, now) {
             Some(drawable) => drawable,
             None => return Ok(()),
         };
+        let (width, colors) = (drawable.width(), drawable.colors());
+
+        drawable.sync_progress(&mut self.state);
+        if let Some(f) = &self.state.message_fn {
+            self.state.message = f().into();
+        }
 
         // `|| self.is_finished()` should not be needed here, but we used to always for draw for
         // finished progress bar, so it's kept as to not cause compatibility issues in weird cases.
+        let graphics = drawable.graphics();
         let mut draw_state = drawable.state();
 
         if self.state.should_render() {
-            self.state
-                .style
-                .format_state(&self.state, &mut draw_state.lines, width);
+            let draw_state = &mut *draw_state;
+            self.state.style.format_state_with(
+                &self.state,
+                &mut draw_state.lines,
+                &mut draw_state.spare_lines,
+                width,
+                colors,
+                graphics.as_deref(),
+            );
         }
+        draw_state.finished_at = self.state.finished_at;
+        draw_state.progress = self.state.system_progress();
 
         drop(draw_state);
         self.state.last_draw = Some((self.state.pos, now));
-        drawable.draw()
+        let result = drawable.draw();
+        self.draw_target.flush_queued(now);
+        self.record_draw(result)
+    }
+
+    /// Records the result of a draw, hiding the progress bar, or its `MultiProgress`, once the
+    /// limit of failed draws in a row is reached
+    pub(crate) fn record_draw(&mut self, result: io::Result<()>) -> io::Result<()> {
+        let errors = &mut self.draw_errors;
+        let err = match result {
+            Ok(()) => {
+                errors.last = None;
+                errors.consecutive = 0;
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+
+        if let Some(handler) = &errors.handler {
+            handler(&err);
+        }
+        errors.last = Some(copy_error(&err));
+        errors.consecutive = errors.consecutive.saturating_add(1);
+        if matches!(errors.limit, Some(limit) if errors.consecutive >= limit) {
+            match self.draw_target.remote() {
+                // The progress bars of a `MultiProgress` all draw to its target, which is hidden
+                // instead, so they all stop failing and keep their slots
+                Some((multi, _)) => multi.write().unwrap().hide(),
+                None => self.draw_target = ProgressDrawTarget::hidden(),
+            }
+        }
+        Err(err)
     }
 }
 
@@ -141,7 +280,102 @@ impl Drop for BarState {
         }
 
         self.finish_using_style(Instant::now());
+        // The progress bar can't be locked anymore, so the left over work can be done right away
+        if let Some(after_unlock) = self.draw_target.after_unlock() {
+            after_unlock.run();
+        }
+    }
+}
+
+/// A span of wall-clock time the progress of a deadline-based progress bar tracks.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Deadline {
+    pub(crate) start: Instant,
+    pub(crate) end: Instant,
+}
+
+impl Deadline {
+    /// Returns the fraction of the span that has passed at `now`.
+    pub(crate) fn fraction(&self, now: Instant) -> f32 {
+        let total = self.end.saturating_duration_since(self.start);
+        if total == Duration::new(0, 0) {
+            return 1.0;
+        }
+        let passed = now.saturating_duration_since(self.start);
+        (passed.as_secs_f64() / total.as_secs_f64()).min(1.0) as f32
+    }
+}
+
+/// Completion reported directly by the user, see
+/// [`ProgressBar::set_fraction()`](crate::ProgressBar::set_fraction).
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct UserFraction {
+    pub(crate) value: f64,
+    /// The first reported fraction and when it was reported, the ETA is based on the average rate
+    /// since then
+    pub(crate) first: (f64, Instant),
+}
+
+impl UserFraction {
+    pub(crate) fn new(value: f64, now: Instant) -> Self {
+        Self {
+            value,
+            first: (value, now),
+        }
     }
+
+    /// Returns the time until the fraction reaches 1 at the average rate since the first report.
+    pub(crate) fn eta(&self, now: Instant) -> Duration {
+        let (start, at) = self.first;
+        let done = self.value - start;
+        if done <= 0.0 {
+            return Duration::new(0, 0);
+        }
+        let secs = now.saturating_duration_since(at).as_secs_f64();
+        secs_to_duration(secs * (1.0 - self.value) / done)
+    }
+}
+
+/// The direction in which a progress bar is displayed to move
+///
+/// See [`ProgressBar::set_direction()`](crate::ProgressBar::set_direction).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// The position counts up from 0 to the length and the bar fills up
+    Increasing,
+    /// The position counts down from the length to 0 and the bar drains
+    Decreasing,
+}
+
+/// How the position of a progress bar maps to the fill of its bar
+///
+/// See [`ProgressBar::set_scale()`](crate::ProgressBar::set_scale).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scale {
+    /// The bar fills proportionally to the position
+    Linear,
+    /// The bar fills proportionally to the logarithm of the position, so early progress shows
+    /// more and the bar slows down towards the end
+    Logarithmic,
+}
+
+/// Number of progress bars in a `MultiProgress`, by status.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct BarCounts {
+    pub(crate) active: usize,
+    pub(crate) finished: usize,
+    pub(crate) total: usize,
+}
+
+/// Progress of the `MultiProgress` a progress bar is part of.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct GroupProgress {
+    /// Completion of the parent bar, if the progress bar was added with a level
+    pub(crate) parent_fraction: Option<f32>,
+    /// Completion of all progress bars, not counting the total bar
+    pub(crate) fraction: f32,
+    /// Time until the last unfinished progress bar is expected to finish
+    pub(crate) eta: Duration,
 }
 
 /// The state of a progress bar at a moment in time.
@@ -151,18 +385,83 @@ pub struct ProgressState {
     pub len: u64,
     pub(crate) tick: u64,
     pub(crate) started: Instant,
+    /// Time elapsed before the progress bar was started, see [`ProgressBar::resume()`]
+    ///
+    /// [`ProgressBar::resume()`]: crate::ProgressBar::resume
+    pub(crate) resumed_elapsed: Duration,
     pub(crate) message: Cow<'static, str>,
     pub(crate) prefix: Cow<'static, str>,
     pub(crate) draw_limit: Limit,
     pub(crate) last_draw: Option<(u64, Instant)>,
     pub(crate) status: Status,
     pub(crate) est: Estimate,
-    pub(crate) tick_thread: Option<thread::JoinHandle<()>>,
     pub(crate) steady_tick: u64,
+    pub(crate) notify: Option<Notify>,
+    /// Labels and durations of the recorded laps
+    pub(crate) laps: Vec<(Cow<'static, str>, Duration)>,
+    /// Time at which the current lap started
+    pub(crate) lap_started: Instant,
+    /// Whether to draw the bar at 100% before clearing it
+    pub(crate) draw_final_frame: bool,
+    /// Weighted work units completed so far
+    pub(crate) work_done: u64,
+    /// Weighted work units left, if the ETA is based on weighted work
+    pub(crate) remaining_work: Option<u64>,
+    pub(crate) work_est: Estimate,
+    /// Time at which the progress bar finished
+    pub(crate) finished_at: Option<Instant>,
+    /// Progress bar counts of the `MultiProgress` the progress bar is part of, if any
+    pub(crate) bar_counts: Option<BarCounts>,
+    /// Progress of the `MultiProgress` the progress bar is part of, if any
+    pub(crate) group: Option<GroupProgress>,
+    pub(crate) direction: Direction,
+    /// Functions to call once when the progress bar finishes
+    pub(crate) finish_hooks: Vec<FinishHook>,
+    /// Provides the message when the progress bar is drawn, if set
+    pub(crate) message_fn: Option<Box<dyn Fn() -> String + Send>>,
+    /// Recording the progress is sampled into, if any
+    pub(crate) recording: Option<ProgressRecording>,
+    /// Deadline the progress tracks instead of the position, if any
+    pub(crate) deadline: Option<Deadline>,
+    /// Completion reported by the user, which takes the place of the position if set
+    pub(crate) user_fraction: Option<UserFraction>,
+    /// Time the progress bar is expected to take, if it has a time budget
+    pub(crate) expected_duration: Option<Duration>,
+    /// Completion of each chunk, if the progress is tracked in chunks
+    pub(crate) chunks: Option<Vec<bool>>,
+    /// Position shared with the [`PositionHandle`]s of the progress bar, if any
+    ///
+    /// [`PositionHandle`]: crate::PositionHandle
+    pub(crate) shared_pos: Option<Arc<AtomicU64>>,
+    /// Name of the thread that last updated the progress bar, if it has one
+    pub(crate) thread_name: Option<String>,
+    /// Identifier of the task tracked by the progress bar, if set
+    pub(crate) task_id: Option<Cow<'static, str>>,
+    /// Steps of the most recent seconds, for the `{sparkline}` key
+    pub(crate) throughput: Throughput,
+    /// Position at which the bar is empty, see [`ProgressBar::set_range()`]
+    ///
+    /// [`ProgressBar::set_range()`]: crate::ProgressBar::set_range
+    pub(crate) min: u64,
+    pub(crate) scale: Scale,
+    /// Standard deviations of the rate covered by [`ProgressState::eta_range()`]
+    pub(crate) eta_confidence: f64,
+    /// See [`ProgressBar::set_paused()`](crate::ProgressBar::set_paused)
+    pub(crate) paused: bool,
+    /// Position and length of the secondary quantity, see
+    /// [`ProgressBar::set_aux_position()`](crate::ProgressBar::set_aux_position)
+    pub(crate) aux_pos: u64,
+    pub(crate) aux_len: u64,
+    /// Renderer the state is handed to when drawing, if any
+    #[cfg(feature = "unstable-renderer")]
+    pub(crate) renderer: Option<Box<dyn crate::renderer::Renderer>>,
 }
 
+pub(crate) type FinishHook = Box<dyn FnOnce(&ProgressState) + Send>;
+
 impl ProgressState {
     pub(crate) fn new(len: u64) -> Self {
+        let now = Instant::now();
         Self {
             style: ProgressStyle::default_bar(),
             message: "".into(),
@@ -173,10 +472,40 @@ impl ProgressState {
             draw_limit: Limit::Rate(Duration::from_millis(10)),
             last_draw: None,
             status: Status::InProgress,
-            started: Instant::now(),
+            started: now,
+            resumed_elapsed: Duration::new(0, 0),
             est: Estimate::new(),
-            tick_thread: None,
             steady_tick: 0,
+            notify: None,
+            laps: Vec::new(),
+            lap_started: now,
+            draw_final_frame: false,
+            work_done: 0,
+            remaining_work: None,
+            work_est: Estimate::new(),
+            finished_at: None,
+            bar_counts: None,
+            group: None,
+            direction: Direction::Increasing,
+            finish_hooks: Vec::new(),
+            message_fn: None,
+            recording: None,
+            deadline: None,
+            user_fraction: None,
+            expected_duration: None,
+            chunks: None,
+            shared_pos: None,
+            thread_name: None,
+            task_id: None,
+            throughput: Throughput::new(now),
+            min: 0,
+            scale: Scale::Linear,
+            eta_confidence: 1.0,
+            paused: false,
+            aux_pos: 0,
+            aux_len: 0,
+            #[cfg(feature = "unstable-renderer")]
+            renderer: None,
         }
     }
 
@@ -203,39 +532,211 @@ impl ProgressState {
 
     /// Returns the completion as a floating-point number between 0 and 1
     pub fn fraction(&self) -> f32 {
-        let pct = match (self.pos, self.len) {
+        if let Some(deadline) = &self.deadline {
+            return deadline.fraction(Instant::now());
+        }
+        if let Some(fraction) = &self.user_fraction {
+            return fraction.value as f32;
+        }
+        let min = self.min.min(self.len);
+        let pct = match (self.pos.saturating_sub(min), self.len - min) {
             (_, 0) => 1.0,
             (0, _) => 0.0,
-            (pos, len) => pos as f32 / len as f32,
+            (pos, len) => match self.scale {
+                Scale::Linear => pos as f32 / len as f32,
+                Scale::Logarithmic => ((pos as f64).ln_1p() / (len as f64).ln_1p()) as f32,
+            },
         };
-        pct.max(0.0).min(1.0)
+        pct.clamp(0.0, 1.0)
+    }
+
+    /// Moves the position, or the user-reported fraction, to the end.
+    fn complete(&mut self) {
+        self.pos = self.len;
+        if let Some(fraction) = &mut self.user_fraction {
+            fraction.value = 1.0;
+        }
+    }
+
+    /// Returns the position as shown by the template keys, taking the direction into account.
+    pub(crate) fn displayed_pos(&self) -> u64 {
+        match self.direction {
+            Direction::Increasing => self.pos,
+            Direction::Decreasing => self.len.saturating_sub(self.pos),
+        }
+    }
+
+    /// Returns the fraction as shown by the bar, taking the direction into account.
+    pub(crate) fn displayed_fraction(&self) -> f32 {
+        match self.direction {
+            Direction::Increasing => self.fraction(),
+            Direction::Decreasing => 1.0 - self.fraction(),
+        }
+    }
+
+    /// Returns the progress to report to the terminal.
+    pub(crate) fn system_progress(&self) -> SystemProgress {
+        if self.is_finished() {
+            SystemProgress::None
+        } else if self.len == !0 && self.user_fraction.is_none() {
+            SystemProgress::Indeterminate
+        } else {
+            SystemProgress::Percent((self.fraction() * 100.0) as u8)
+        }
     }
 
     /// Returns the current message of the progress bar.
-    pub(crate) fn message(&self) -> &str {
+    pub fn message(&self) -> &str {
         &self.message
     }
 
+    /// Sets the message, replacing the message function if any.
+    pub(crate) fn set_message(&mut self, msg: Cow<'static, str>) {
+        self.message = msg;
+        self.message_fn = None;
+    }
+
     /// Returns the current prefix of the progress bar.
-    pub(crate) fn prefix(&self) -> &str {
+    pub fn prefix(&self) -> &str {
         &self.prefix
     }
 
+    /// Returns the elapsed time since the progress bar was started.
+    ///
+    /// Once the progress bar is finished, this is the time it took until it finished.
+    pub fn elapsed(&self) -> Duration {
+        let elapsed = match self.finished_at {
+            Some(finished_at) => finished_at.saturating_duration_since(self.started),
+            None => self.started.elapsed(),
+        };
+        saturating_add(self.resumed_elapsed, elapsed)
+    }
+
+    /// Returns how long the progress bar has been running past its expected duration, if it has.
+    pub(crate) fn overshoot(&self) -> Option<Duration> {
+        let overshoot = self.elapsed().checked_sub(self.expected_duration?)?;
+        match overshoot > Duration::from_secs(0) {
+            true => Some(overshoot),
+            false => None,
+        }
+    }
+
+    /// Returns the label and duration of the most recent lap, if any.
+    pub(crate) fn last_lap(&self) -> Option<&(Cow<'static, str>, Duration)> {
+        self.laps.last()
+    }
+
+    /// Records `weight` units of weighted work as done.
+    pub(crate) fn record_work(&mut self, weight: u64, now: Instant) {
+        self.work_done = self.work_done.saturating_add(weight);
+        self.remaining_work = self.remaining_work.map(|r| r.saturating_sub(weight));
+        self.work_est.record_step(self.work_done, now);
+    }
+
     /// The expected ETA
     pub fn eta(&self) -> Duration {
-        if self.len == !0 || self.is_finished() {
+        if self.is_finished() {
+            return Duration::new(0, 0);
+        }
+        if let Some(deadline) = &self.deadline {
+            return deadline.end.saturating_duration_since(Instant::now());
+        }
+        if let Some(fraction) = &self.user_fraction {
+            return fraction.eta(Instant::now());
+        }
+        if let Some(remaining) = self.remaining_work {
+            let t = self.work_est.seconds_per_step();
+            if t.is_finite() {
+                return secs_to_duration(t * remaining as f64);
+            }
+        }
+        if self.len == !0 {
             return Duration::new(0, 0);
         }
         let t = self.est.seconds_per_step();
         secs_to_duration(t * self.len.saturating_sub(self.pos) as f64)
     }
 
+    /// Sets the gauges of progress bars with a task id, see the `metrics` feature
+    #[cfg(feature = "metrics")]
+    fn export_metrics(&self) {
+        let id = match &self.task_id {
+            Some(id) if !self.is_finished() => id.clone(),
+            _ => return,
+        };
+
+        ::metrics::gauge!("indicatif_position", "bar" => id.clone()).set(self.pos as f64);
+        if self.len != !0 {
+            ::metrics::gauge!("indicatif_length", "bar" => id.clone()).set(self.len as f64);
+        }
+        ::metrics::gauge!("indicatif_rate", "bar" => id).set(self.per_sec());
+    }
+
+    /// Resets the gauges of a finished or removed progress bar, so that it doesn't linger
+    #[cfg(feature = "metrics")]
+    pub(crate) fn clear_metrics(&self) {
+        let id = match &self.task_id {
+            Some(id) => id,
+            None => return,
+        };
+
+        for &name in &["indicatif_position", "indicatif_length", "indicatif_rate"] {
+            ::metrics::gauge!(name, "bar" => id.clone()).set(0.0);
+        }
+    }
+
+    /// The completion of the secondary quantity, rendered by the `{aux_bar}` key
+    pub(crate) fn aux_fraction(&self) -> f32 {
+        match self.aux_len {
+            0 => 0.0,
+            len => (self.aux_pos as f64 / len as f64).min(1.0) as f32,
+        }
+    }
+
+    /// The optimistic and pessimistic ETA
+    ///
+    /// The range spans the configured number of standard deviations of the recent time per step
+    /// (see [`ProgressBar::set_eta_confidence()`]) on both sides of the average used by
+    /// [`ProgressState::eta()`].
+    ///
+    /// [`ProgressBar::set_eta_confidence()`]: crate::ProgressBar::set_eta_confidence
+    pub fn eta_range(&self) -> (Duration, Duration) {
+        if self.is_finished() || self.deadline.is_some() || self.user_fraction.is_some() {
+            let eta = self.eta();
+            return (eta, eta);
+        }
+        if let Some(remaining) = self.remaining_work {
+            let (low, high) = self.work_est.seconds_per_step_range(self.eta_confidence);
+            if high.is_finite() {
+                return (
+                    secs_to_duration(low * remaining as f64),
+                    secs_to_duration(high * remaining as f64),
+                );
+            }
+        }
+        if self.len == !0 {
+            return (Duration::new(0, 0), Duration::new(0, 0));
+        }
+        let (low, high) = self.est.seconds_per_step_range(self.eta_confidence);
+        let remaining = self.len.saturating_sub(self.pos) as f64;
+        (
+            secs_to_duration(low * remaining),
+            secs_to_duration(high * remaining),
+        )
+    }
+
     /// The expected total duration (that is, elapsed time + expected ETA)
     pub(crate) fn duration(&self) -> Duration {
+        if let Some(deadline) = &self.deadline {
+            return deadline.end.saturating_duration_since(self.started);
+        }
+        if self.user_fraction.is_some() && !self.is_finished() {
+            return saturating_add(self.elapsed(), self.eta());
+        }
         if self.len == !0 || self.is_finished() {
             return Duration::new(0, 0);
         }
-        self.started.elapsed() + self.eta()
+        saturating_add(self.elapsed(), self.eta())
     }
 
     /// The number of steps per second
@@ -248,17 +749,96 @@ impl ProgressState {
                 per_sec
             }
         } else {
-            self.len as f64 / self.started.elapsed().as_secs_f64()
+            self.len as f64 / self.elapsed().as_secs_f64()
+        }
+    }
+
+    /// The number of steps per second, as estimated by `mode`
+    pub(crate) fn rate(&self, mode: RateMode) -> f64 {
+        self.rate_at(mode, Instant::now())
+    }
+
+    /// The number of steps per second, as estimated by `mode` at `now`
+    fn rate_at(&self, mode: RateMode, now: Instant) -> f64 {
+        if !matches!(&self.status, Status::InProgress) {
+            return self.per_sec();
+        }
+
+        let secs_per_step = match mode {
+            RateMode::Smoothed => return self.per_sec(),
+            RateMode::Instant => self.est.last_seconds_per_step,
+            RateMode::Average => {
+                let steps = self.pos.saturating_sub(self.est.start_value);
+                duration_to_secs(now.saturating_duration_since(self.est.start_time)) / steps as f64
+            }
+        };
+        match secs_per_step > 0.0 && secs_per_step.is_finite() {
+            true => 1.0 / secs_per_step,
+            false => 0.0,
+        }
+    }
+
+    /// Picks up the position stored through the [`PositionHandle`]s of the progress bar
+    ///
+    /// [`PositionHandle`]: crate::PositionHandle
+    pub(crate) fn sync_shared_pos(&mut self, now: Instant) {
+        let pos = match &self.shared_pos {
+            Some(shared) => shared.load(Ordering::Relaxed),
+            None => return,
+        };
+        if pos != self.pos {
+            self.throughput.record(pos.saturating_sub(self.pos), now);
+            self.pos = pos;
+            self.est.record_step(pos, now);
+        }
+    }
+
+    /// Sets the position without recording a step, e.g. when building a progress bar
+    pub(crate) fn set_pos_unsynced(&mut self, pos: u64) {
+        self.pos = pos;
+        if let Some(shared) = &self.shared_pos {
+            shared.store(pos, Ordering::Relaxed);
+        }
+    }
+
+    /// Moves the position to `pos` without counting the difference as progress, so that the rate
+    /// and ETA only reflect the steps actually made
+    pub(crate) fn skip_to(&mut self, pos: u64, now: Instant) {
+        self.sync_shared_pos(now);
+        self.est.skip(self.pos, pos);
+        self.set_pos_unsynced(pos);
+    }
+
+    /// Remembers the name of the current thread, for the `{thread}` key
+    fn record_thread(&mut self) {
+        let thread = thread::current();
+        if thread.name() != self.thread_name.as_deref() {
+            self.thread_name = thread.name().map(String::from);
+        }
+    }
+
+    /// Returns the task id, or the name of the thread that last updated the progress bar
+    pub(crate) fn task_id(&self) -> &str {
+        match &self.task_id {
+            Some(id) => id,
+            None => self.thread_name.as_deref().unwrap_or(""),
         }
     }
 
     /// Call the provided `FnOnce` to update the state. If a draw should be run, returns `true`.
     pub(crate) fn update<F: FnOnce(&mut ProgressState)>(&mut self, now: Instant, f: F) -> bool {
+        self.sync_shared_pos(now);
+        self.record_thread();
         let old_pos = self.pos;
         f(self);
         let new_pos = self.pos;
         if new_pos != old_pos {
             self.est.record_step(new_pos, now);
+            self.throughput.record(new_pos.saturating_sub(old_pos), now);
+            if let Some(shared) = &self.shared_pos {
+                // Applied as a delta so that concurrent updates through the handles aren't lost
+                shared.fetch_add(new_pos.wrapping_sub(old_pos), Ordering::Relaxed);
+            }
         }
 
         let (last_pos, last_time) = match self.last_draw {
@@ -273,6 +853,82 @@ impl ProgressState {
     }
 }
 
+/// How the `{*_per_sec}` template keys estimate the rate, selected with `{per_sec:instant}` and
+/// `{per_sec:avg}`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum RateMode {
+    /// Averaged over the most recent steps (the default)
+    Smoothed,
+    /// Rate of the most recent step only
+    Instant,
+    /// Average rate since the progress bar was started or reset
+    Average,
+}
+
+/// Steps made in each of the most recent seconds
+#[derive(Debug)]
+pub(crate) struct Throughput {
+    start: Instant,
+    /// Steps of each second, the last one being the second `last` since `start`
+    buckets: VecDeque<u64>,
+    last: u64,
+}
+
+impl Throughput {
+    /// Number of seconds kept, the longest window of the `{sparkline}` key
+    const MAX_SECONDS: usize = 120;
+
+    pub(crate) fn new(start: Instant) -> Self {
+        Self {
+            start,
+            buckets: VecDeque::from(vec![0]),
+            last: 0,
+        }
+    }
+
+    fn second(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.start).as_secs()
+    }
+
+    pub(crate) fn record(&mut self, steps: u64, now: Instant) {
+        let second = self.second(now);
+        if second.saturating_sub(self.last) > Self::MAX_SECONDS as u64 {
+            // Every kept second is idle after a long gap, so skip over it at once
+            self.buckets.clear();
+            self.buckets.resize(Self::MAX_SECONDS + 1, 0);
+            self.last = second;
+        }
+        while self.last < second {
+            self.buckets.push_back(0);
+            self.last += 1;
+            if self.buckets.len() > Self::MAX_SECONDS + 1 {
+                self.buckets.pop_front();
+            }
+        }
+        if let Some(bucket) = self.buckets.back_mut() {
+            *bucket = bucket.saturating_add(steps);
+        }
+    }
+
+    /// Returns the steps of the `n` seconds completed before `now`, oldest first
+    ///
+    /// Seconds before the start are `None`.
+    pub(crate) fn recent(&self, n: usize, now: Instant) -> impl Iterator<Item = Option<u64>> + '_ {
+        let current = self.second(now);
+        (0..n as u64).rev().map(move |ago| {
+            let second = current.checked_sub(ago + 1)?;
+            match second > self.last {
+                true => Some(0),
+                false => {
+                    let back = (self.last - second) as usize;
+                    let idx = self.buckets.len().checked_sub(back + 1)?;
+                    self.buckets.get(idx).copied()
+                }
+            }
+        })
+    }
+}
+
 /// Ring buffer with constant capacity. Used by `ProgressBar`s to display `{eta}`, `{eta_precise}`,
 /// and `{*_per_sec}`.
 pub(crate) struct Estimate {
@@ -288,6 +944,10 @@ pub(crate) struct Estimate {
     data: u8,
     start_time: Instant,
     start_value: u64,
+    /// Value and time of the most recent step
+    last_step: Option<(u64, Instant)>,
+    /// Time per step in seconds of the most recent step alone
+    last_seconds_per_step: f64,
 }
 
 impl Estimate {
@@ -318,6 +978,8 @@ impl Estimate {
             data: 0,
             start_time: Instant::now(),
             start_value: 0,
+            last_step: None,
+            last_seconds_per_step: 0.0,
         };
         // Make sure not to break anything accidentally as self.data can't handle bufs longer than
         // 15 elements (not enough space in a u8)
@@ -329,6 +991,34 @@ impl Estimate {
         self.start_time = Instant::now();
         self.start_value = start_value;
         self.data = 0;
+        self.last_step = None;
+        self.last_seconds_per_step = 0.0;
+    }
+
+    /// Shifts the recorded values by the distance from `from` to `to`, so that the jump isn't
+    /// counted as steps
+    fn skip(&mut self, from: u64, to: u64) {
+        let shift = |value: u64| match to >= from {
+            true => value.saturating_add(to - from),
+            false => value.saturating_sub(from - to),
+        };
+        self.start_value = shift(self.start_value);
+        if let Some((value, time)) = self.last_step {
+            self.last_step = Some((shift(value), time));
+        }
+    }
+
+    /// Seeds the estimate with the average rate of a previous session that reached `pos` after
+    /// `elapsed`, so that the estimate is meaningful before any new step is recorded.
+    pub(crate) fn resume(&mut self, pos: u64, elapsed: Duration, now: Instant) {
+        self.start_time = now;
+        self.start_value = pos;
+        self.data = 0;
+        self.last_step = None;
+        self.last_seconds_per_step = 0.0;
+        if pos > 0 {
+            self.push(duration_to_secs(elapsed) / pos as f64);
+        }
     }
 
     fn record_step(&mut self, value: u64, current_time: Instant) {
@@ -343,6 +1033,15 @@ impl Estimate {
         };
 
         self.push(item);
+
+        if let Some((last_value, last_time)) = self.last_step {
+            let steps = value.saturating_sub(last_value);
+            if steps > 0 {
+                let elapsed = current_time.saturating_duration_since(last_time);
+                self.last_seconds_per_step = duration_to_secs(elapsed) / steps as f64;
+            }
+        }
+        self.last_step = Some((value, current_time));
     }
 
     /// Adds the `value` into the buffer, overwriting the oldest one if full, or increasing length
@@ -369,6 +1068,19 @@ impl Estimate {
         let len = self.len();
         self.buf[0..usize::from(len)].iter().sum::<f64>() / f64::from(len)
     }
+
+    /// Time per step in seconds, `deviations` standard deviations below and above the average
+    fn seconds_per_step_range(&self, deviations: f64) -> (f64, f64) {
+        let len = self.len();
+        let mean = self.seconds_per_step();
+        let variance = self.buf[0..usize::from(len)]
+            .iter()
+            .map(|t| (t - mean) * (t - mean))
+            .sum::<f64>()
+            / f64::from(len);
+        let spread = variance.sqrt() * deviations;
+        ((mean - spread).max(0.0), mean + spread)
+    }
 }
 
 impl fmt::Debug for Estimate {
@@ -379,6 +1091,8 @@ impl fmt::Debug for Estimate {
             .field("last_idx", &self.last_idx())
             .field("start_time", &self.start_time)
             .field("start_value", &self.start_value)
+            .field("last_step", &self.last_step)
+            .field("last_seconds_per_step", &self.last_seconds_per_step)
             .finish()
     }
 }
@@ -387,12 +1101,40 @@ fn duration_to_secs(d: Duration) -> f64 {
     d.as_secs() as f64 + f64::from(d.subsec_nanos()) / 1_000_000_000f64
 }
 
+/// Adds two durations, saturating at the largest duration instead of overflowing
+pub(crate) fn saturating_add(a: Duration, b: Duration) -> Duration {
+    a.checked_add(b)
+        .unwrap_or_else(|| Duration::new(u64::MAX, 999_999_999))
+}
+
 fn secs_to_duration(s: f64) -> Duration {
     let secs = s.trunc() as u64;
     let nanos = (s.fract() * 1_000_000_000f64) as u32;
     Duration::new(secs, nanos)
 }
 
+/// Notification emitted when a progress bar finishes
+///
+/// See [`ProgressBar::notify_on_finish()`](crate::ProgressBar::notify_on_finish).
+pub enum Notify {
+    /// Rings the terminal bell of the draw target
+    Bell,
+    /// Calls the given function
+    ///
+    /// **Note:** The progress bar's internal lock is held while the function is called, so it
+    /// must not call back into the progress bar.
+    Callback(Box<dyn Fn() + Send>),
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Notify::Bell => f.write_str("Bell"),
+            Notify::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum Status {
     InProgress,
@@ -409,6 +1151,142 @@ pub(crate) enum Limit {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn export_metrics() {
+        use std::sync::{Arc, Mutex};
+
+        use metrics::{Counter, Gauge, GaugeFn, Histogram, Key, KeyName, Metadata};
+        use metrics::{Recorder, SharedString, Unit};
+
+        #[derive(Default)]
+        struct Gauges(Arc<Mutex<Vec<String>>>);
+
+        struct Set(Key, Arc<Mutex<Vec<String>>>);
+
+        impl GaugeFn for Set {
+            fn increment(&self, _: f64) {}
+            fn decrement(&self, _: f64) {}
+            fn set(&self, value: f64) {
+                let label = self.0.labels().next().unwrap();
+                let entry = format!(
+                    "{}{{{}={}}} {}",
+                    self.0.name(),
+                    label.key(),
+                    label.value(),
+                    value
+                );
+                self.1.lock().unwrap().push(entry);
+            }
+        }
+
+        impl Recorder for Gauges {
+            fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn register_counter(&self, _: &Key, _: &Metadata<'_>) -> Counter {
+                Counter::noop()
+            }
+            fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+                Gauge::from_arc(Arc::new(Set(key.clone(), self.0.clone())))
+            }
+            fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+                Histogram::noop()
+            }
+        }
+
+        let recorder = Gauges::default();
+        let mut state = ProgressState::new(10);
+        state.pos = 3;
+        metrics::with_local_recorder(&recorder, || {
+            state.export_metrics();
+            state.task_id = Some("download".into());
+            state.export_metrics();
+            state.status = Status::DoneVisible;
+            state.export_metrics();
+            state.clear_metrics();
+        });
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "indicatif_position{bar=download} 3",
+                "indicatif_length{bar=download} 10",
+                "indicatif_rate{bar=download} 0",
+                "indicatif_position{bar=download} 0",
+                "indicatif_length{bar=download} 0",
+                "indicatif_rate{bar=download} 0"
+            ]
+        );
+    }
+
+    #[test]
+    fn eta_range() {
+        let mut state = ProgressState::new(10);
+        let start = state.est.start_time;
+        state.est.record_step(1, start + Duration::from_secs(1));
+        state.est.record_step(2, start + Duration::from_secs(3));
+        state.pos = 2;
+        // Seconds per step of 1 and 1.5
+        assert_eq!(state.eta(), Duration::from_secs(10));
+        assert_eq!(
+            state.eta_range(),
+            (Duration::from_secs(8), Duration::from_secs(12))
+        );
+
+        state.eta_confidence = 0.0;
+        assert_eq!(
+            state.eta_range(),
+            (Duration::from_secs(10), Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn range_and_scale() {
+        let mut state = ProgressState::new(200);
+        state.min = 100;
+        state.pos = 50;
+        assert_eq!(state.fraction(), 0.0);
+        state.pos = 150;
+        assert_eq!(state.fraction(), 0.5);
+
+        state.min = 0;
+        state.len = 999;
+        state.pos = 9;
+        state.scale = Scale::Logarithmic;
+        assert!((state.fraction() - 1.0 / 3.0).abs() < 1e-6);
+        state.pos = 999;
+        assert_eq!(state.fraction(), 1.0);
+    }
+
+    #[test]
+    fn deadline_fraction() {
+        let start = Instant::now();
+        let deadline = Deadline {
+            start,
+            end: start + Duration::from_secs(10),
+        };
+        assert_eq!(deadline.fraction(start), 0.0);
+        assert_eq!(deadline.fraction(start + Duration::from_secs(4)), 0.4);
+        assert_eq!(deadline.fraction(start + Duration::from_secs(20)), 1.0);
+        let passed = Deadline { start, end: start };
+        assert_eq!(passed.fraction(start), 1.0);
+    }
+
+    #[test]
+    fn user_fraction_eta() {
+        let start = Instant::now();
+        let mut fraction = UserFraction::new(0.2, start);
+        assert_eq!(
+            fraction.eta(start + Duration::from_secs(5)),
+            Duration::new(0, 0)
+        );
+        fraction.value = 0.4;
+        assert_eq!(
+            fraction.eta(start + Duration::from_secs(10)),
+            Duration::from_secs(30)
+        );
+    }
+
     #[test]
     fn test_time_per_step() {
         let test_rate = |items_per_second| {
@@ -447,6 +1325,72 @@ mod tests {
         test_rate(1_000_000_000_000_000);
     }
 
+    #[test]
+    fn test_weighted_eta() {
+        let mut state = ProgressState::new(10);
+        state.remaining_work = Some(90);
+        let start = state.work_est.start_time;
+        state.record_work(10, start + Duration::from_secs(5));
+        assert_eq!(state.remaining_work, Some(80));
+        assert_eq!(state.eta(), Duration::from_secs(40));
+    }
+
+    #[test]
+    fn test_rate_modes() {
+        let mut state = ProgressState::new(100);
+        let start = state.est.start_time;
+        state.update(start + Duration::from_secs(1), |s| s.pos = 10);
+        state.update(start + Duration::from_secs(2), |s| s.pos = 30);
+        assert_eq!(state.rate(RateMode::Instant), 20.0);
+        let average = state.rate_at(RateMode::Average, start + Duration::from_secs(10));
+        assert_eq!(average, 3.0);
+
+        // The average is measured against the wall clock while formatting, so it can only be
+        // bounded by the rates before and after
+        state.style = ProgressStyle::default_bar().template("{per_sec:instant} {per_sec:avg}");
+        let mut lines = Vec::new();
+        let before = state.rate(RateMode::Average);
+        state.style.format_state(&state, &mut lines, 80, None);
+        let after = state.rate(RateMode::Average);
+        let mut rates = lines[0].split(' ');
+        assert_eq!(rates.next(), Some("20.0000/s"));
+        let rendered = rates.next().unwrap().trim_end_matches("/s");
+        let rendered = rendered.parse::<f64>().unwrap();
+        assert!(
+            after - 1e-4 <= rendered && rendered <= before + 1e-4,
+            "{}",
+            lines[0]
+        );
+    }
+
+    #[test]
+    fn throughput_after_long_gap() {
+        let start = Instant::now();
+        let mut throughput = Throughput::new(start);
+        throughput.record(5, start);
+        let later = start + Duration::from_secs(u64::from(u32::MAX));
+        throughput.record(3, later);
+        assert_eq!(throughput.buckets.len(), Throughput::MAX_SECONDS + 1);
+
+        let recent = throughput
+            .recent(3, later + Duration::from_secs(1))
+            .collect::<Vec<_>>();
+        assert_eq!(recent, vec![Some(0), Some(0), Some(3)]);
+    }
+
+    #[test]
+    fn skip_to() {
+        let mut state = ProgressState::new(2000);
+        let start = state.est.start_time;
+        state.update(start + Duration::from_secs(1), |s| s.pos = 10);
+        state.skip_to(1000, start + Duration::from_secs(1));
+        state.update(start + Duration::from_secs(2), |s| s.pos = 1010);
+        assert_eq!(state.pos, 1010);
+        assert_eq!(state.rate(RateMode::Instant), 10.0);
+        // The average rate only counts the 20 steps that were not skipped
+        assert_eq!(state.pos - state.est.start_value, 20);
+    }
+
     #[test]
     fn test_duration_stuff() {
         let duration = Duration::new(42, 100_000_000);
@@ -1,27 +1,122 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::{self, Write};
-use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fs, io, mem};
 
-use console::{measure_text_width, Style};
+use crate::compat::{self, measure_text_width, AnsiCodeIterator, Color, Style};
 #[cfg(feature = "unicode-segmentation")]
 use unicode_segmentation::UnicodeSegmentation;
 
 use crate::format::{
-    BinaryBytes, DecimalBytes, FormattedDuration, HumanBytes, HumanCount, HumanDuration,
+    AdaptiveDuration, BinaryBytes, Clock, ClockTime, DecimalBytes, FormattedDuration, HumanBytes,
+    HumanCount, Locale, LocalizedDuration, SiNumber,
 };
-use crate::state::ProgressState;
+use crate::graphics::{self, BarGraphics};
+use crate::state::{saturating_add, Direction, ProgressState, RateMode};
+use crate::theme::Theme;
+use crate::time::{Instant, SystemTime};
 
 /// Controls the rendering style of progress bars
+///
+/// With the `serde` feature, styles can be serialized and deserialized, e.g. to load them from
+/// a configuration file. Only the template, tick strings, progress characters and finish
+/// behavior are serialized, the other settings are left at their defaults when deserializing.
+///
+/// ```rust
+/// # #[cfg(feature = "serde")] {
+/// # use indicatif::ProgressStyle;
+/// let style: ProgressStyle = serde_json::from_str(
+///     r##"{"template": "{spinner} {wide_bar} {pos}/{len}", "progress_chars": "#>-"}"##,
+/// )
+/// .unwrap();
+/// # }
+/// ```
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "StyleConfig", try_from = "StyleConfig")
+)]
 pub struct ProgressStyle {
     tick_strings: Vec<Box<str>>,
+    /// How long each tick string is shown, if spinner frames are selected by time
+    tick_durations: Option<Vec<Duration>>,
+    /// Number of ticks each spinner frame is shown for
+    tick_divisor: u64,
+    /// How long each spinner frame is shown, if frames advance with time instead of ticks
+    tick_interval: Option<Duration>,
     progress_chars: Vec<Box<str>>,
     template: Template,
     on_finish: ProgressFinish,
     // how unicode-big each char in progress_chars is
     char_width: usize,
     format_map: HashMap<&'static str, fn(&ProgressState) -> String>,
+    /// Styles of keys that have no style in the template
+    key_styles: HashMap<String, Style>,
+    /// Theme applied to the styles in the template
+    theme: Option<Theme>,
+    /// Marks the expected progress in the bar, see [`ProgressBar::set_expected_duration()`]
+    ///
+    /// [`ProgressBar::set_expected_duration()`]: crate::ProgressBar::set_expected_duration
+    budget_marker: Box<str>,
+    /// String and style of the part of the bar beyond the length, if overflow is shown
+    overflow: Option<(Box<str>, Style)>,
+    /// Colors of the bar when drawn with [`BarGraphics`]
+    graphics_colors: Option<(Color, Color)>,
+    /// File the template was loaded from, if any
+    template_file: Option<TemplateFile>,
+    /// What happens to lines wider than the terminal
+    line_overflow: LineOverflow,
+    /// Conventions for numbers and durations, if not English
+    locale: Option<Locale>,
+    /// Time zone of the clock time keys
+    clock: Clock,
+    /// Whether messages too long for their column scroll instead of being truncated
+    marquee: bool,
+}
+
+/// Keys whose numbers are formatted according to the [`Locale`]
+const LOCALIZED_NUMBERS: &[&str] = &[
+    "human_pos",
+    "human_len",
+    "si_pos",
+    "si_len",
+    "bytes",
+    "total_bytes",
+    "decimal_bytes",
+    "decimal_total_bytes",
+    "binary_bytes",
+    "binary_total_bytes",
+    "per_sec",
+    "si_per_sec",
+    "bytes_per_sec",
+    "binary_bytes_per_sec",
+    "aux_bytes",
+    "aux_total_bytes",
+];
+
+#[derive(Clone, Debug)]
+struct TemplateFile {
+    path: PathBuf,
+    /// Modification time and size of the file when it was read
+    version: Option<(std::time::SystemTime, u64)>,
+    /// Interval between checks for changes, and time of the last check, if hot reloading
+    reload: Option<(Duration, Instant)>,
+}
+
+impl TemplateFile {
+    fn version(path: &Path) -> Option<(std::time::SystemTime, u64)> {
+        let metadata = fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    fn read(path: &Path) -> io::Result<String> {
+        let template = fs::read_to_string(path)?;
+        Ok(template.trim_end_matches(&['\r', '\n'][..]).to_owned())
+    }
 }
 
 #[cfg(feature = "unicode-segmentation")]
@@ -36,29 +131,186 @@ fn segment(s: &str) -> Vec<Box<str>> {
     s.chars().map(|x| x.to_string().into()).collect()
 }
 
-#[cfg(feature = "unicode_width")]
-fn measure(s: &str) -> usize {
+#[cfg(feature = "unicode-width")]
+pub(crate) fn measure(s: &str) -> usize {
     unicode_width::UnicodeWidthStr::width(s)
 }
 
-#[cfg(not(feature = "unicode_width"))]
-fn measure(s: &str) -> usize {
+#[cfg(not(feature = "unicode-width"))]
+pub(crate) fn measure(s: &str) -> usize {
     s.chars().count()
 }
 
+#[cfg(feature = "unicode-width")]
+fn char_width(c: char) -> usize {
+    unicode_width::UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+#[cfg(not(feature = "unicode-width"))]
+fn char_width(_: char) -> usize {
+    1
+}
+
+/// Truncates `s` to fit into `width` columns, appending `tail` if it had to be cut.
+///
+/// The width is measured in display columns, so wide characters (e.g. CJK) count double and ANSI
+/// escape codes don't count at all. Escape codes are kept, so styles are still reset after the
+/// cut. If `tail` doesn't fit into `width` it is left out.
+///
+/// ```rust
+/// # use indicatif::truncate_str;
+/// assert_eq!(truncate_str("Größenänderung", 8, "…"), "Größenä…");
+/// assert_eq!(truncate_str("日本語のテキスト", 7, "…"), "日本語…");
+/// assert_eq!(truncate_str("short", 8, "…"), "short");
+/// ```
+pub fn truncate_str<'a>(s: &'a str, width: usize, tail: &str) -> Cow<'a, str> {
+    if measure_text_width(s) <= width {
+        return Cow::Borrowed(s);
+    }
+
+    let tail = match measure_text_width(tail) {
+        w if w <= width => tail,
+        _ => "",
+    };
+    let budget = width - measure_text_width(tail);
+    let mut truncated = String::with_capacity(s.len());
+    let mut used = 0;
+    let mut cut = None;
+    for (part, is_ansi) in AnsiCodeIterator::new(s) {
+        if is_ansi {
+            truncated.push_str(part);
+            continue;
+        } else if cut.is_some() {
+            continue;
+        }
+
+        for c in part.chars() {
+            used += char_width(c);
+            if used > budget {
+                cut = Some(truncated.len());
+                break;
+            }
+            truncated.push(c);
+        }
+    }
+
+    truncated.insert_str(cut.unwrap_or(truncated.len()), tail);
+    Cow::Owned(truncated)
+}
+
+/// Returns the `width` columns of `s` shown at `step` when `s` scrolls through them
+///
+/// Text that fits is returned as is. Longer text scrolls by one character per step and wraps
+/// around after a gap, its ANSI escape codes are removed.
+fn marquee_window(s: &str, width: usize, step: u64) -> Cow<'_, str> {
+    const GAP: &str = "   ";
+    if measure_text_width(s) <= width {
+        return Cow::Borrowed(s);
+    }
+
+    let text = compat::strip_ansi_codes(s);
+    let chars = text.chars().chain(GAP.chars()).collect::<Vec<_>>();
+    let start = (step % chars.len() as u64) as usize;
+    let window = chars[start..].iter().chain(&chars).collect::<String>();
+    Cow::Owned(truncate_str(&window, width, "").into_owned())
+}
+
+/// Splits `s` into lines of at most `width` columns, keeping ANSI escape codes in place
+///
+/// Styles still active at the end of a line are reset there and applied again on the next line,
+/// so that every line can be drawn on its own.
+fn wrap_str(s: &str, width: usize) -> Vec<String> {
+    if measure_text_width(s) <= width {
+        return vec![s.to_owned()];
+    }
+
+    let mut lines = vec![String::new()];
+    let mut used = 0;
+    // SGR sequences applied since the last reset
+    let mut active = String::new();
+    for (part, is_ansi) in AnsiCodeIterator::new(s) {
+        if is_ansi {
+            match part {
+                "\x1b[0m" | "\x1b[m" => active.clear(),
+                _ if part.ends_with('m') => active.push_str(part),
+                _ => {}
+            }
+            lines.last_mut().unwrap().push_str(part);
+            continue;
+        }
+
+        for c in part.chars() {
+            let w = char_width(c);
+            if used + w > width && used > 0 {
+                if !active.is_empty() {
+                    lines.last_mut().unwrap().push_str("\x1b[0m");
+                }
+                lines.push(active.clone());
+                used = 0;
+            }
+            used += w;
+            lines.last_mut().unwrap().push(c);
+        }
+    }
+    lines
+}
+
+/// Pads `s` with spaces to `width` columns, optionally truncating it if it's wider.
+///
+/// Like [`truncate_str()`], this measures display columns and ignores ANSI escape codes.
+///
+/// ```rust
+/// # use indicatif::{pad_str, Alignment};
+/// assert_eq!(pad_str("日本", 6, Alignment::Center, false), " 日本 ");
+/// assert_eq!(pad_str("\x1b[31mred\x1b[0m", 5, Alignment::Right, false), "  \x1b[31mred\x1b[0m");
+/// assert_eq!(pad_str("truncated", 5, Alignment::Left, true), "trunc");
+/// ```
+pub fn pad_str(s: &str, width: usize, align: Alignment, truncate: bool) -> Cow<'_, str> {
+    let cols = measure_text_width(s);
+    if cols == width || (cols > width && !truncate) {
+        return Cow::Borrowed(s);
+    }
+
+    let padded = PaddedStringDisplay {
+        str: s,
+        width,
+        align,
+        truncate,
+    };
+    Cow::Owned(padded.to_string())
+}
+
+/// Returns the index of the frame shown after `elapsed`, cycling through the frame durations.
+fn frame_at(durations: &[Duration], elapsed: Duration) -> usize {
+    let cycle = durations.iter().map(Duration::as_nanos).sum::<u128>();
+    if cycle == 0 {
+        return 0;
+    }
+
+    let mut t = elapsed.as_nanos() % cycle;
+    for (i, d) in durations.iter().enumerate() {
+        match t.checked_sub(d.as_nanos()) {
+            Some(rest) => t = rest,
+            None => return i,
+        }
+    }
+    0
+}
+
 /// finds the unicode-aware width of the passed grapheme cluters
 /// panics on an empty parameter, or if the characters are not equal-width
 fn width(c: &[Box<str>]) -> usize {
-    c.iter()
-        .map(|s| measure(s.as_ref()))
-        .fold(None, |acc, new| {
-            match acc {
-                None => return Some(new),
-                Some(old) => assert_eq!(old, new, "got passed un-equal width progress characters"),
-            }
-            acc
-        })
-        .unwrap()
+    try_width(c).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Returns the width of the progress characters, which must all be equally wide
+fn try_width(c: &[Box<str>]) -> Result<usize, StyleError> {
+    let mut widths = c.iter().map(|s| measure(s.as_ref()));
+    let first = widths.next().ok_or(StyleError::TooFewProgressChars)?;
+    match widths.all(|width| width == first) {
+        true => Ok(first),
+        false => Err(StyleError::UnequalProgressChars),
+    }
 }
 
 impl ProgressStyle {
@@ -72,43 +324,141 @@ impl ProgressStyle {
         Self::new("{spinner} {msg}")
     }
 
+    /// Returns the default progress bar style for bars, using only ASCII characters
+    ///
+    /// This is useful for terminals that can't render Unicode block characters.
+    pub fn default_bar_ascii() -> ProgressStyle {
+        Self::default_bar()
+            .progress_chars("#>-")
+            .tick_chars(r"-\|/ ")
+    }
+
     fn new(template: &str) -> Self {
-        let progress_chars = segment("█░");
+        // Eighth blocks between the filled and the "to do" character give sub-character
+        // resolution.
+        let progress_chars = segment("█▉▊▋▌▍▎▏░");
         let char_width = width(&progress_chars);
         ProgressStyle {
             tick_strings: "⠁⠁⠉⠙⠚⠒⠂⠂⠒⠲⠴⠤⠄⠄⠤⠠⠠⠤⠦⠖⠒⠐⠐⠒⠓⠋⠉⠈⠈ "
                 .chars()
                 .map(|c| c.to_string().into())
                 .collect(),
+            tick_durations: None,
+            tick_divisor: 1,
+            tick_interval: None,
             progress_chars,
             char_width,
             template: Template::from_str(template),
             on_finish: ProgressFinish::default(),
             format_map: HashMap::default(),
+            key_styles: HashMap::default(),
+            theme: None,
+            budget_marker: "|".into(),
+            overflow: None,
+            graphics_colors: None,
+            line_overflow: LineOverflow::Truncate,
+            locale: None,
+            clock: Clock::Local,
+            marquee: false,
+            template_file: None,
         }
     }
 
     /// Sets the tick character sequence for spinners
-    pub fn tick_chars(mut self, s: &str) -> ProgressStyle {
-        self.tick_strings = s.chars().map(|c| c.to_string().into()).collect();
+    ///
+    /// Panics if fewer than 2 characters are given, see [`ProgressStyle::try_tick_chars()`].
+    pub fn tick_chars(self, s: &str) -> ProgressStyle {
         // Format bar will panic with some potentially confusing message, better to panic here
         // with a message explicitly informing of the problem
-        assert!(
-            self.tick_strings.len() >= 2,
-            "at least 2 tick chars required"
-        );
-        self
+        self.try_tick_chars(s)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sets the tick character sequence for spinners, failing if fewer than 2 characters are
+    /// given
+    pub fn try_tick_chars(self, s: &str) -> Result<ProgressStyle, StyleError> {
+        let strings = s.chars().map(String::from).collect::<Vec<_>>();
+        self.try_tick_strings(&strings.iter().map(|s| s.as_str()).collect::<Vec<_>>())
     }
 
     /// Sets the tick string sequence for spinners
-    pub fn tick_strings(mut self, s: &[&str]) -> ProgressStyle {
+    ///
+    /// Panics if fewer than 2 strings are given, see [`ProgressStyle::try_tick_strings()`].
+    pub fn tick_strings(self, s: &[&str]) -> ProgressStyle {
+        self.try_tick_strings(s)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sets the tick string sequence for spinners, failing if fewer than 2 strings are given or
+    /// one of them is empty
+    ///
+    /// The last string is shown when the progress bar is finished, so at least one more is
+    /// needed for the spinner itself.
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressStyle, StyleError};
+    /// let style = ProgressStyle::default_spinner().try_tick_strings(&["✔"]);
+    /// assert_eq!(style.err(), Some(StyleError::TooFewTickStrings));
+    /// ```
+    pub fn try_tick_strings(mut self, s: &[&str]) -> Result<ProgressStyle, StyleError> {
+        if s.len() < 2 {
+            return Err(StyleError::TooFewTickStrings);
+        } else if s.iter().any(|s| s.is_empty()) {
+            return Err(StyleError::EmptyTickString);
+        }
         self.tick_strings = s.iter().map(|s| s.to_string().into()).collect();
-        // Format bar will panic with some potentially confusing message, better to panic here
-        // with a message explicitly informing of the problem
+        self.tick_durations = None;
+        Ok(self)
+    }
+
+    /// Sets the tick string sequence for spinners, with the duration each string is shown
+    ///
+    /// Unlike with [`ProgressStyle::tick_strings()`], the spinner frame is selected by the time
+    /// elapsed since the progress bar started, which allows for animations with frames of
+    /// different lengths. Enable a steady tick with an interval no longer than the shortest
+    /// frame to render every frame. As with `tick_strings()`, the last string is shown when the
+    /// progress bar is finished, its duration is ignored.
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use indicatif::ProgressStyle;
+    /// let ms = Duration::from_millis;
+    /// let style = ProgressStyle::default_spinner().tick_frames(&[
+    ///     ("⠋", ms(80)),
+    ///     ("⠙", ms(80)),
+    ///     ("✨", ms(300)),
+    ///     ("✔", ms(0)),
+    /// ]);
+    /// ```
+    pub fn tick_frames(mut self, frames: &[(&str, Duration)]) -> ProgressStyle {
+        assert!(frames.len() >= 2, "{}", StyleError::TooFewTickStrings);
         assert!(
-            self.progress_chars.len() >= 2,
-            "at least 2 tick strings required"
+            frames.iter().all(|(s, _)| !s.is_empty()),
+            "{}",
+            StyleError::EmptyTickString
         );
+        self.tick_strings = frames.iter().map(|(s, _)| s.to_string().into()).collect();
+        let durations = &frames[..frames.len() - 1];
+        self.tick_durations = Some(durations.iter().map(|(_, d)| *d).collect());
+        self
+    }
+
+    /// Shows each spinner frame for `n` ticks instead of advancing the spinner on every tick
+    ///
+    /// This keeps spinners readable when a progress bar is ticked often, e.g. by a fast steady
+    /// tick. See also [`ProgressStyle::tick_interval()`].
+    pub fn tick_every(mut self, n: u64) -> ProgressStyle {
+        self.tick_divisor = n.max(1);
+        self
+    }
+
+    /// Advances the spinner every `interval` instead of on every tick
+    ///
+    /// The spinner frame is selected by the time elapsed since the progress bar started, so the
+    /// spinner turns at the same speed however often the progress bar is drawn. This is ignored
+    /// for frames set with [`ProgressStyle::tick_frames()`], which have their own durations.
+    pub fn tick_interval(mut self, interval: Duration) -> ProgressStyle {
+        self.tick_interval = Some(interval).filter(|interval| interval.as_nanos() > 0);
         self
     }
 
@@ -116,16 +466,32 @@ impl ProgressStyle {
     ///
     /// You can pass more than three for a more detailed display.
     /// All passed grapheme clusters need to be of equal width.
-    pub fn progress_chars(mut self, s: &str) -> ProgressStyle {
-        self.progress_chars = segment(s);
+    ///
+    /// Panics if the characters are invalid, see [`ProgressStyle::try_progress_chars()`].
+    pub fn progress_chars(self, s: &str) -> ProgressStyle {
         // Format bar will panic with some potentially confusing message, better to panic here
         // with a message explicitly informing of the problem
-        assert!(
-            self.progress_chars.len() >= 2,
-            "at least 2 progress chars required"
-        );
-        self.char_width = width(&self.progress_chars);
-        self
+        self.try_progress_chars(s)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sets the progress characters `(filled, current, to do)`, failing if fewer than 2 are given
+    /// or they aren't all of the same width
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressStyle, StyleError};
+    /// assert!(ProgressStyle::default_bar().try_progress_chars("=>-").is_ok());
+    /// let style = ProgressStyle::default_bar().try_progress_chars("#");
+    /// assert_eq!(style.err(), Some(StyleError::TooFewProgressChars));
+    /// ```
+    pub fn try_progress_chars(mut self, s: &str) -> Result<ProgressStyle, StyleError> {
+        let chars = segment(s);
+        if chars.len() < 2 {
+            return Err(StyleError::TooFewProgressChars);
+        }
+        self.char_width = try_width(&chars)?;
+        self.progress_chars = chars;
+        Ok(self)
     }
 
     /// Adds a custom key that references a `&ProgressState` to the template
@@ -134,11 +500,260 @@ impl ProgressStyle {
         self
     }
 
+    /// Sets the style of a template key
+    ///
+    /// The style is applied wherever `key` appears in the template without a style of its own,
+    /// so templates can stay free of markup and styles can be changed at runtime. For `bar` and
+    /// `wide_bar` the style applies to the filled part of the bar.
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressStyle, Style};
+    /// let style = ProgressStyle::default_bar()
+    ///     .template("{bar:40} {pos}/{len} {eta}")
+    ///     .key_style("bar", Style::new().cyan())
+    ///     .key_style("eta", Style::new().dim());
+    /// ```
+    pub fn key_style(mut self, key: &str, style: Style) -> ProgressStyle {
+        self.key_styles.insert(key.to_owned(), style);
+        self
+    }
+
+    /// Sets the string marking where the progress is expected to be in the bar
+    ///
+    /// The marker is only shown if an expected duration is set (see
+    /// [`ProgressBar::set_expected_duration()`]) and the progress is behind the expectation. It
+    /// defaults to `|`, and should be as wide as the progress characters.
+    ///
+    /// [`ProgressBar::set_expected_duration()`]: crate::ProgressBar::set_expected_duration
+    pub fn budget_marker(mut self, marker: &str) -> ProgressStyle {
+        self.budget_marker = marker.into();
+        self
+    }
+
+    /// Shows by how much the position exceeds the length
+    ///
+    /// By default, the bar is full and `{percent}` shows 100 once the position reaches the
+    /// length. With overflow shown, a position beyond the length shrinks the regular part of the
+    /// bar to the length and fills the rest with `overflow` in `style`, and `{percent}` goes past
+    /// 100. This is useful when the length is only an estimate.
+    ///
+    /// ```rust
+    /// # use indicatif::{ProgressStyle, Style};
+    /// let style = ProgressStyle::default_bar()
+    ///     .template("{bar:40} {percent}%")
+    ///     .overflow("█", Style::new().red());
+    /// ```
+    pub fn overflow(mut self, overflow: &str, style: Style) -> ProgressStyle {
+        self.overflow = Some((overflow.into(), style));
+        self
+    }
+
+    /// Sets the colors of the filled and the empty part of the bar when it is drawn with
+    /// [`BarGraphics`], see [`ProgressDrawTarget::with_bar_graphics()`]
+    ///
+    /// [`ProgressDrawTarget::with_bar_graphics()`]: crate::ProgressDrawTarget::with_bar_graphics
+    pub fn graphics_colors(mut self, filled: Color, empty: Color) -> ProgressStyle {
+        self.graphics_colors = Some((filled, empty));
+        self
+    }
+
+    /// Sets what happens to lines that are wider than the terminal
+    ///
+    /// By default they are truncated. With [`LineOverflow::Wrap`] they continue on the next lines
+    /// instead, so long messages can be read in full on narrow terminals.
+    ///
+    /// ```rust
+    /// # use indicatif::{LineOverflow, ProgressStyle};
+    /// let style = ProgressStyle::default_bar()
+    ///     .template("{bar:20} {msg}")
+    ///     .line_overflow(LineOverflow::Wrap);
+    /// ```
+    pub fn line_overflow(mut self, overflow: LineOverflow) -> ProgressStyle {
+        self.line_overflow = overflow;
+        self
+    }
+
+    /// Sets the conventions for numbers and durations, like decimal separators and unit names
+    ///
+    /// This affects the keys formatting numbers for humans, like `{human_pos}`, `{bytes}` and
+    /// `{per_sec}`, and the keys formatting durations for humans, like `{eta}` and `{elapsed}`.
+    /// The `*_precise` keys are not affected.
+    pub fn locale(mut self, locale: Locale) -> ProgressStyle {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// Sets the time zone of the clock time keys `{start_time}` and `{end_time_estimate}`
+    ///
+    /// Clock times are shown in the local time zone by default, which requires the `local-time`
+    /// feature on Unix. Otherwise they are shown in UTC.
+    ///
+    /// ```rust
+    /// # use indicatif::{Clock, ProgressStyle};
+    /// let style = ProgressStyle::default_bar()
+    ///     .template("{wide_bar} finishes ~{end_time_estimate}")
+    ///     .clock(Clock::Utc);
+    /// ```
+    pub fn clock(mut self, clock: Clock) -> ProgressStyle {
+        self.clock = clock;
+        self
+    }
+
+    /// Scrolls messages that don't fit their column instead of truncating them
+    ///
+    /// This applies to `{wide_msg}` and to `{msg}` with a width and the truncation flag, as in
+    /// `{msg:30!}`. The message moves by one column on every tick, so long file names can be
+    /// read in full. Styles within scrolling messages are removed.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressStyle;
+    /// let style = ProgressStyle::default_spinner()
+    ///     .template("{spinner} {wide_msg}")
+    ///     .marquee(true);
+    /// ```
+    pub fn marquee(mut self, enabled: bool) -> ProgressStyle {
+        self.marquee = enabled;
+        self
+    }
+
     /// Sets the template string for the progress bar
     ///
-    /// Review the [list of template keys](./index.html#templates) for more information.
-    pub fn template(mut self, s: &str) -> ProgressStyle {
-        self.template = Template::from_str(s);
+    /// Review the [list of template keys](./index.html#templates) for more information. Panics
+    /// if the template is invalid, see [`ProgressStyle::try_template()`].
+    pub fn template(self, s: &str) -> ProgressStyle {
+        self.try_template(s).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sets the template string for the progress bar, failing if it is invalid
+    ///
+    /// A template is invalid if it has a misplaced character, like a `{` within a key, or if the
+    /// width of a key isn't a number between 1 and 65535.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressStyle;
+    /// assert!(ProgressStyle::default_bar().try_template("{bar:40} {pos}").is_ok());
+    /// assert!(ProgressStyle::default_bar().try_template("{bar:100000}").is_err());
+    /// ```
+    pub fn try_template(mut self, s: &str) -> Result<ProgressStyle, StyleError> {
+        self.template = Template::try_parse(s, self.theme.as_ref())?;
+        self.template_file = None;
+        Ok(self)
+    }
+
+    /// Sets the template from the contents of the file at `path`
+    ///
+    /// Trailing newlines are ignored. See [`ProgressStyle::hot_reload()`] to pick up changes to
+    /// the file while the progress bar is running. An invalid template is reported as an error
+    /// of kind [`io::ErrorKind::InvalidData`] wrapping the [`StyleError`].
+    pub fn with_template_file(mut self, path: impl AsRef<Path>) -> io::Result<ProgressStyle> {
+        let path = path.as_ref();
+        let version = TemplateFile::version(path);
+        self.template = Template::try_parse(&TemplateFile::read(path)?, self.theme.as_ref())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.template_file = Some(TemplateFile {
+            path: path.to_owned(),
+            version,
+            reload: None,
+        });
+        Ok(self)
+    }
+
+    /// Reloads the template file when it changes, checking at most once per `interval`
+    ///
+    /// The file is checked before the progress bar is drawn, so that the appearance of a long
+    /// running progress bar can be adjusted without restarting it. If the file can't be read or
+    /// holds an invalid template, the previous template is kept. This has no effect unless the template was loaded with
+    /// [`ProgressStyle::with_template_file()`].
+    ///
+    /// ```rust,no_run
+    /// # use std::time::Duration;
+    /// # use indicatif::ProgressStyle;
+    /// let style = ProgressStyle::default_bar()
+    ///     .with_template_file("/etc/migrate/progress.tmpl")?
+    ///     .hot_reload(Duration::from_secs(5));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn hot_reload(mut self, interval: Duration) -> ProgressStyle {
+        if let Some(file) = &mut self.template_file {
+            file.reload = Some((interval, Instant::now()));
+        }
+        self
+    }
+
+    /// Reloads the template file if hot reloading is enabled and the file changed
+    pub(crate) fn reload_template(&mut self, now: Instant) {
+        let file = match &mut self.template_file {
+            Some(file) => file,
+            None => return,
+        };
+        match &mut file.reload {
+            Some((interval, last)) if now.saturating_duration_since(*last) >= *interval => {
+                *last = now
+            }
+            _ => return,
+        }
+
+        let version = TemplateFile::version(&file.path);
+        if version.is_none() || version == file.version {
+            return;
+        }
+        if let Ok(template) = TemplateFile::read(&file.path) {
+            file.version = version;
+            if let Ok(template) = Template::try_parse(&template, self.theme.as_ref()) {
+                self.template = template;
+            }
+        }
+    }
+
+    /// Sets the theme adapting the colors of the template to the terminal background
+    ///
+    /// See [`Theme`] for details.
+    pub fn theme(mut self, theme: Theme) -> ProgressStyle {
+        if let Some(source) = self.template.source.take() {
+            self.template = Template::parse(&source, Some(&theme));
+        }
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Sets the template from a list of typed columns, separated by a single space
+    ///
+    /// This is an alternative to [`ProgressStyle::template()`] that doesn't require building a
+    /// template string:
+    ///
+    /// ```rust
+    /// # use indicatif::{Column, ProgressStyle};
+    /// let style = ProgressStyle::default_bar().columns(vec![
+    ///     Column::Spinner,
+    ///     Column::Bar(40),
+    ///     Column::Percent,
+    ///     Column::Eta,
+    /// ]);
+    /// ```
+    pub fn columns(mut self, columns: impl IntoIterator<Item = Column>) -> ProgressStyle {
+        self.template = Template::from_columns(columns);
+        self.template_file = None;
+        self
+    }
+
+    /// Prepares the template for being drawn repeatedly
+    ///
+    /// Adjacent literal parts are merged and the styles set with [`ProgressStyle::key_style()`]
+    /// are resolved into the template, so that they aren't looked up on every draw. Together
+    /// with the reuse of the line buffers between draws, templates without wide elements then
+    /// draw without allocating, unless they contain custom keys or need to be truncated.
+    ///
+    /// Call this last when building the style, as setting the template or theme afterwards
+    /// replaces the precompiled template.
+    ///
+    /// ```rust
+    /// # use indicatif::ProgressStyle;
+    /// let style = ProgressStyle::default_bar()
+    ///     .template("{spinner} {bar:40.cyan/blue} {pos:>7}/{len:7}")
+    ///     .precompile();
+    /// ```
+    pub fn precompile(mut self) -> ProgressStyle {
+        self.template.precompile(&self.key_styles);
         self
     }
 
@@ -158,10 +773,23 @@ impl ProgressStyle {
         self
     }
 
+    pub(crate) fn set_on_finish(&mut self, finish: ProgressFinish) {
+        self.on_finish = finish;
+    }
+
     pub(crate) fn current_tick_str(&self, state: &ProgressState) -> &str {
         match state.is_finished() {
             true => self.get_final_tick_str(),
-            false => self.get_tick_str(state.tick),
+            false => match &self.tick_durations {
+                Some(durations) => &self.tick_strings[frame_at(durations, state.started.elapsed())],
+                None => match self.tick_interval {
+                    Some(interval) => {
+                        let elapsed = state.started.elapsed().as_nanos();
+                        self.get_tick_str((elapsed / interval.as_nanos()) as u64)
+                    }
+                    None => self.get_tick_str(state.tick / self.tick_divisor),
+                },
+            },
         }
     }
 
@@ -180,7 +808,13 @@ impl ProgressStyle {
         &self.on_finish
     }
 
-    fn format_bar(&self, fract: f32, width: usize, alt_style: Option<&Style>) -> BarDisplay<'_> {
+    fn format_bar<'a>(
+        &'a self,
+        fract: f32,
+        width: usize,
+        alt_style: Option<&'a Style>,
+        colors: Option<bool>,
+    ) -> BarDisplay<'a> {
         // The number of clusters from progress_chars to write (rounding down).
         let width = width / self.char_width;
         // The number of full clusters (including a fractional component for a partially-full one).
@@ -214,17 +848,135 @@ impl ProgressStyle {
 
         // Number of entirely empty clusters needed to fill the bar up to `width`.
         let bg = width.saturating_sub(entirely_filled).saturating_sub(head);
-        let rest = RepeatedStringDisplay {
-            str: &self.progress_chars[self.progress_chars.len() - 1],
-            num: bg,
-        };
 
         BarDisplay {
             chars: &self.progress_chars,
             filled: entirely_filled,
             cur,
-            rest: alt_style.unwrap_or(&Style::new()).apply_to(rest),
+            rest: bg,
+            rest_style: force_colors(
+                alt_style.map_or_else(|| Cow::Owned(Style::new()), Cow::Borrowed),
+                colors,
+            ),
+            marker: None,
+        }
+    }
+
+    /// Writes the bar of `state`, which is a completion bitmap if the progress is tracked in
+    /// chunks.
+    pub(crate) fn write_bar(
+        &self,
+        buf: &mut String,
+        state: &ProgressState,
+        width: usize,
+        alt_style: Option<&Style>,
+        colors: Option<bool>,
+    ) {
+        match &state.chunks {
+            Some(chunks) => self.format_chunks(buf, chunks, width, alt_style, colors),
+            None if self.overflow_ratio(state).is_some() => {
+                let (overflow, style) = self.overflow.as_ref().unwrap();
+                let ratio = self.overflow_ratio(state).unwrap();
+                let cells = width / self.char_width;
+                let filled = (cells as f64 / ratio).round() as usize;
+                let over = RepeatedStringDisplay {
+                    str: overflow,
+                    num: cells - filled,
+                };
+                buf.write_fmt(format_args!(
+                    "{}{}",
+                    RepeatedStringDisplay {
+                        str: &self.progress_chars[0],
+                        num: filled,
+                    },
+                    force_colors(Cow::Borrowed(style), colors).apply_to(over)
+                ))
+                .unwrap();
+            }
+            None => {
+                let mut bar = self.format_bar(state.displayed_fraction(), width, alt_style, colors);
+                if let Some(expected) = state.expected_duration {
+                    // The cell progress is expected to have reached by now
+                    let cells = width / self.char_width;
+                    let fraction = state.elapsed().as_secs_f64() / expected.as_secs_f64();
+                    let cell =
+                        Ord::min((fraction * cells as f64) as usize, cells.saturating_sub(1));
+                    let ahead = cells - bar.rest;
+                    if cell >= ahead && bar.rest > 0 {
+                        bar.marker = Some((cell - ahead, &self.budget_marker));
+                    }
+                }
+                buf.write_fmt(format_args!("{}", bar)).unwrap();
+            }
+        }
+    }
+
+    /// Returns the ratio of the position to the length, if overflow is shown and the position
+    /// exceeds the length.
+    fn overflow_ratio(&self, state: &ProgressState) -> Option<f64> {
+        match (&self.overflow, &state.deadline, state.direction) {
+            (Some(_), None, Direction::Increasing) if state.len > 0 && state.pos > state.len => {
+                Some(state.pos as f64 / state.len as f64)
+            }
+            _ => None,
+        }
+    }
+
+    /// Writes a bar in which each cell shows the completion of the chunks it covers.
+    fn format_chunks(
+        &self,
+        buf: &mut String,
+        chunks: &[bool],
+        width: usize,
+        alt_style: Option<&Style>,
+        colors: Option<bool>,
+    ) {
+        let width = width / self.char_width;
+        let rest_style = force_colors(
+            alt_style.map_or_else(|| Cow::Owned(Style::new()), Cow::Borrowed),
+            colors,
+        );
+        let last = self.progress_chars.len() - 1;
+        // Number of fine-grained progress entries in progress_chars.
+        let n = self.progress_chars.len().saturating_sub(2);
+        let mut pending = 0;
+        for cell in 0..width {
+            let start = cell * chunks.len() / width;
+            let end = Ord::max((cell + 1) * chunks.len() / width, start + 1);
+            let covered = &chunks[start..Ord::min(end, chunks.len())];
+            let done = covered.iter().filter(|&&done| done).count();
+            let idx = match done {
+                0 => last,
+                done if done == covered.len() => 0,
+                _ if n <= 1 => 1,
+                done => n.saturating_sub(done * n / covered.len()).max(1),
+            };
+
+            if idx == last {
+                pending += 1;
+                continue;
+            }
+            if pending > 0 {
+                let rest = RepeatedStringDisplay {
+                    str: &self.progress_chars[last],
+                    num: mem::take(&mut pending),
+                };
+                buf.write_fmt(format_args!("{}", rest_style.apply_to(rest)))
+                    .unwrap();
+            }
+            buf.push_str(&self.progress_chars[idx]);
         }
+
+        let rest = RepeatedStringDisplay {
+            str: &self.progress_chars[last],
+            num: pending,
+        };
+        buf.write_fmt(format_args!("{}", rest_style.apply_to(rest)))
+            .unwrap();
+    }
+
+    fn human_duration(&self, d: Duration) -> LocalizedDuration<'_> {
+        LocalizedDuration(d, self.locale.as_ref())
     }
 
     pub(crate) fn format_state(
@@ -232,10 +984,36 @@ impl ProgressStyle {
         state: &ProgressState,
         lines: &mut Vec<String>,
         target_width: usize,
+        colors: Option<bool>,
     ) {
-        let mut cur = String::new();
-        let mut buf = String::new();
-        let mut wide = None;
+        self.format_state_with(state, lines, &mut Vec::new(), target_width, colors, None)
+    }
+
+    /// Formats the lines of `state`, drawing its bars with `graphics` if supported
+    ///
+    /// The lines are built in the cleared strings of `spare` if there are any, so that drawing
+    /// doesn't allocate once the strings are large enough. The strings are popped in order, the
+    /// first one is used as scratch buffer.
+    pub(crate) fn format_state_with(
+        &self,
+        state: &ProgressState,
+        lines: &mut Vec<String>,
+        spare: &mut Vec<String>,
+        target_width: usize,
+        colors: Option<bool>,
+        graphics: Option<&dyn BarGraphics>,
+    ) {
+        let first_line = lines.len();
+        let mut buf = match spare.is_empty() {
+            true => String::new(),
+            false => spare.remove(0),
+        };
+        let mut cur = spare.pop().unwrap_or_default();
+        let mut wide = Vec::new();
+        let mut color = None;
+        // Byte ranges of the bars in `cur` and in `lines`, to draw them with `graphics`
+        let mut line_bars = Vec::new();
+        let mut bars = Vec::<(usize, Range<usize>)>::new();
         for part in &self.template.parts {
             match part {
                 TemplatePart::Placeholder {
@@ -245,102 +1023,278 @@ impl ProgressStyle {
                     truncate,
                     style,
                     alt_style,
+                    rate,
                 } => {
                     buf.clear();
+                    let mut is_bar = false;
                     if let Some(formatter) = self.format_map.get(key.as_str()) {
                         buf.push_str(&formatter(state));
                     } else {
                         match key.as_str() {
                             "wide_bar" => {
-                                wide = Some(WideElement::Bar { alt_style });
+                                wide.push((WideElement::Bar { alt_style }, width.unwrap_or(1)));
                                 buf.push('\x00');
                             }
-                            "bar" => buf
+                            "bar" => {
+                                is_bar = true;
+                                self.write_bar(
+                                    &mut buf,
+                                    state,
+                                    width.unwrap_or(20) as usize,
+                                    alt_style.as_ref(),
+                                    colors,
+                                );
+                            }
+                            "aux_bar" => buf
                                 .write_fmt(format_args!(
                                     "{}",
                                     self.format_bar(
-                                        state.fraction(),
+                                        state.aux_fraction(),
                                         width.unwrap_or(20) as usize,
                                         alt_style.as_ref(),
+                                        colors,
                                     )
                                 ))
                                 .unwrap(),
+                            "aux_pos" => buf.write_fmt(format_args!("{}", state.aux_pos)).unwrap(),
+                            "aux_len" => buf.write_fmt(format_args!("{}", state.aux_len)).unwrap(),
+                            "aux_bytes" => buf
+                                .write_fmt(format_args!("{}", HumanBytes(state.aux_pos)))
+                                .unwrap(),
+                            "aux_total_bytes" => buf
+                                .write_fmt(format_args!("{}", HumanBytes(state.aux_len)))
+                                .unwrap(),
                             "spinner" => buf.push_str(state.current_tick_str()),
                             "wide_msg" => {
-                                wide = Some(WideElement::Message { align });
+                                wide.push((WideElement::Message { align }, width.unwrap_or(1)));
                                 buf.push('\x00');
                             }
-                            "msg" => buf.push_str(state.message()),
-                            "prefix" => buf.push_str(state.prefix()),
-                            "pos" => buf.write_fmt(format_args!("{}", state.pos)).unwrap(),
+                            "msg" => {
+                                let msg = styled_text(state.message(), colors);
+                                match width {
+                                    Some(width) if self.marquee && *truncate => buf.push_str(
+                                        &marquee_window(&msg, *width as usize, state.tick),
+                                    ),
+                                    _ => buf.push_str(&msg),
+                                }
+                            }
+                            "prefix" => buf.push_str(&styled_text(state.prefix(), colors)),
+                            "thread" => buf.push_str(&styled_text(
+                                state.thread_name.as_deref().unwrap_or(""),
+                                colors,
+                            )),
+                            "task_id" => buf.push_str(&styled_text(state.task_id(), colors)),
+                            "pos" => buf
+                                .write_fmt(format_args!("{}", state.displayed_pos()))
+                                .unwrap(),
                             "human_pos" => buf
-                                .write_fmt(format_args!("{}", HumanCount(state.pos)))
+                                .write_fmt(format_args!("{}", HumanCount(state.displayed_pos())))
+                                .unwrap(),
+                            "si_pos" => buf
+                                .write_fmt(format_args!(
+                                    "{}",
+                                    SiNumber(state.displayed_pos() as f64)
+                                ))
                                 .unwrap(),
                             "len" => buf.write_fmt(format_args!("{}", state.len)).unwrap(),
+                            "si_len" => buf
+                                .write_fmt(format_args!("{}", SiNumber(state.len as f64)))
+                                .unwrap(),
                             "human_len" => buf
                                 .write_fmt(format_args!("{}", HumanCount(state.len)))
                                 .unwrap(),
-                            "percent" => buf
-                                .write_fmt(format_args!("{:.*}", 0, state.fraction() * 100f32))
-                                .unwrap(),
+                            "percent" => match self.overflow_ratio(state) {
+                                Some(ratio) => {
+                                    buf.write_fmt(format_args!("{:.*}", 0, ratio * 100.0))
+                                }
+                                None => buf.write_fmt(format_args!(
+                                    "{:.*}",
+                                    0,
+                                    state.displayed_fraction() * 100f32
+                                )),
+                            }
+                            .unwrap(),
                             "bytes" => buf
-                                .write_fmt(format_args!("{}", HumanBytes(state.pos)))
+                                .write_fmt(format_args!("{}", HumanBytes(state.displayed_pos())))
                                 .unwrap(),
                             "total_bytes" => buf
                                 .write_fmt(format_args!("{}", HumanBytes(state.len)))
                                 .unwrap(),
                             "decimal_bytes" => buf
-                                .write_fmt(format_args!("{}", DecimalBytes(state.pos)))
+                                .write_fmt(format_args!("{}", DecimalBytes(state.displayed_pos())))
                                 .unwrap(),
                             "decimal_total_bytes" => buf
                                 .write_fmt(format_args!("{}", DecimalBytes(state.len)))
                                 .unwrap(),
                             "binary_bytes" => buf
-                                .write_fmt(format_args!("{}", BinaryBytes(state.pos)))
+                                .write_fmt(format_args!("{}", BinaryBytes(state.displayed_pos())))
                                 .unwrap(),
                             "binary_total_bytes" => buf
                                 .write_fmt(format_args!("{}", BinaryBytes(state.len)))
                                 .unwrap(),
                             "elapsed_precise" => buf
-                                .write_fmt(format_args!(
-                                    "{}",
-                                    FormattedDuration(state.started.elapsed())
-                                ))
+                                .write_fmt(format_args!("{}", FormattedDuration(state.elapsed())))
                                 .unwrap(),
                             "elapsed" => buf
-                                .write_fmt(format_args!(
-                                    "{:#}",
-                                    HumanDuration(state.started.elapsed())
-                                ))
+                                .write_fmt(format_args!("{}", self.human_duration(state.elapsed())))
                                 .unwrap(),
                             "per_sec" => buf
-                                .write_fmt(format_args!("{:.4}/s", state.per_sec()))
+                                .write_fmt(format_args!("{:.4}/s", state.rate(*rate)))
+                                .unwrap(),
+                            "si_per_sec" => buf
+                                .write_fmt(format_args!("{}/s", SiNumber(state.rate(*rate))))
                                 .unwrap(),
                             "bytes_per_sec" => buf
-                                .write_fmt(format_args!("{}/s", HumanBytes(state.per_sec() as u64)))
+                                .write_fmt(format_args!(
+                                    "{}/s",
+                                    HumanBytes(state.rate(*rate) as u64)
+                                ))
                                 .unwrap(),
                             "binary_bytes_per_sec" => buf
                                 .write_fmt(format_args!(
                                     "{}/s",
-                                    BinaryBytes(state.per_sec() as u64)
+                                    BinaryBytes(state.rate(*rate) as u64)
                                 ))
                                 .unwrap(),
+                            "overshoot" => {
+                                if let Some(overshoot) = state.overshoot() {
+                                    buf.write_fmt(format_args!(
+                                        "+{}",
+                                        self.human_duration(overshoot)
+                                    ))
+                                    .unwrap();
+                                }
+                            }
                             "eta_precise" => buf
                                 .write_fmt(format_args!("{}", FormattedDuration(state.eta())))
                                 .unwrap(),
                             "eta" => buf
-                                .write_fmt(format_args!("{:#}", HumanDuration(state.eta())))
+                                .write_fmt(format_args!("{}", self.human_duration(state.eta())))
+                                .unwrap(),
+                            "eta_low" => buf
+                                .write_fmt(format_args!(
+                                    "{}",
+                                    self.human_duration(state.eta_range().0)
+                                ))
+                                .unwrap(),
+                            "eta_high" => buf
+                                .write_fmt(format_args!(
+                                    "{}",
+                                    self.human_duration(state.eta_range().1)
+                                ))
+                                .unwrap(),
+                            "sparkline" => write_sparkline(
+                                &mut buf,
+                                state
+                                    .throughput
+                                    .recent(usize::from(width.unwrap_or(10)), Instant::now()),
+                            ),
+                            "eta_adaptive" => buf
+                                .write_fmt(format_args!("{}", AdaptiveDuration(state.eta())))
                                 .unwrap(),
                             "duration_precise" => buf
                                 .write_fmt(format_args!("{}", FormattedDuration(state.duration())))
                                 .unwrap(),
                             "duration" => buf
-                                .write_fmt(format_args!("{:#}", HumanDuration(state.duration())))
+                                .write_fmt(format_args!(
+                                    "{}",
+                                    self.human_duration(state.duration())
+                                ))
                                 .unwrap(),
+                            "total_duration" => buf
+                                .write_fmt(format_args!(
+                                    "{}",
+                                    self.human_duration(saturating_add(
+                                        state.elapsed(),
+                                        state.eta()
+                                    ))
+                                ))
+                                .unwrap(),
+                            "start_time" => {
+                                let start = SystemTime::now() - state.elapsed();
+                                buf.write_fmt(format_args!("{}", ClockTime(start, self.clock)))
+                                    .unwrap()
+                            }
+                            "end_time_estimate" => {
+                                // Renders nothing if the end is too far out to be represented
+                                if let Some(end) = SystemTime::now().checked_add(state.eta()) {
+                                    buf.write_fmt(format_args!("{}", ClockTime(end, self.clock)))
+                                        .unwrap()
+                                }
+                            }
+                            "active" | "finished" | "total_bars" => {
+                                if let Some(counts) = state.bar_counts {
+                                    let count = match key.as_str() {
+                                        "active" => counts.active,
+                                        "finished" => counts.finished,
+                                        _ => counts.total,
+                                    };
+                                    buf.write_fmt(format_args!("{}", count)).unwrap();
+                                }
+                            }
+                            "parent_percent" => {
+                                if let Some(fraction) =
+                                    state.group.and_then(|group| group.parent_fraction)
+                                {
+                                    buf.write_fmt(format_args!("{:.*}", 0, fraction * 100f32))
+                                        .unwrap();
+                                }
+                            }
+                            "group_percent" => {
+                                if let Some(group) = state.group {
+                                    buf.write_fmt(format_args!(
+                                        "{:.*}",
+                                        0,
+                                        group.fraction * 100f32
+                                    ))
+                                    .unwrap();
+                                }
+                            }
+                            "group_eta" => {
+                                if let Some(group) = state.group {
+                                    buf.write_fmt(format_args!(
+                                        "{}",
+                                        self.human_duration(group.eta)
+                                    ))
+                                    .unwrap();
+                                }
+                            }
+                            "last_lap" => {
+                                if let Some((label, duration)) = state.last_lap() {
+                                    buf.write_fmt(format_args!(
+                                        "{}: {}",
+                                        label,
+                                        self.human_duration(*duration)
+                                    ))
+                                    .unwrap()
+                                }
+                            }
                             _ => (),
                         }
+
+                        if let Some(locale) = &self.locale {
+                            if LOCALIZED_NUMBERS.contains(&key.as_str()) {
+                                locale.localize_number(&mut buf);
+                            }
+                        }
+                    };
+
+                    // The width of wide elements is their weight when sharing the remaining space
+                    let width = match buf.as_str() {
+                        "\x00" => &None,
+                        _ => width,
                     };
+                    let style = match buf.as_str() {
+                        // Wide elements are styled when they are expanded
+                        "\x00" => None,
+                        _ => style
+                            .as_ref()
+                            .or_else(|| self.key_styles.get(key.as_str()))
+                            .or(color),
+                    }
+                    .map(|s| force_colors(Cow::Borrowed(s), colors));
 
+                    let start = cur.len();
                     match width {
                         Some(width) => {
                             let padded = PaddedStringDisplay {
@@ -361,26 +1315,217 @@ impl ProgressStyle {
                             None => cur.push_str(&buf),
                         },
                     }
-                }
-                TemplatePart::Literal(s) => cur.push_str(s),
-                TemplatePart::NewLine => lines.push(match wide {
-                    Some(inner) => {
-                        inner.expand(mem::take(&mut cur), self, state, &mut buf, target_width)
+                    if is_bar {
+                        line_bars.push(start..cur.len());
                     }
-                    None => mem::take(&mut cur),
-                }),
+                }
+                TemplatePart::Literal(s) => match color {
+                    Some(style) => cur
+                        .write_fmt(format_args!(
+                            "{}",
+                            force_colors(Cow::Borrowed(style), colors).apply_to(s)
+                        ))
+                        .unwrap(),
+                    None => cur.push_str(s),
+                },
+                TemplatePart::Color(style) => color = style.as_ref(),
+                TemplatePart::NewLine => {
+                    let next = spare.pop().unwrap_or_default();
+                    lines.push(self.expand_wide(
+                        mem::replace(&mut cur, next),
+                        &mem::take(&mut wide),
+                        state,
+                        &mut buf,
+                        target_width,
+                        colors,
+                        &mut line_bars,
+                    ));
+                    let line = lines.len() - 1;
+                    bars.extend(line_bars.drain(..).map(|range| (line, range)));
+                }
             }
         }
 
-        if !cur.is_empty() {
-            lines.push(match wide {
-                Some(inner) => {
-                    inner.expand(mem::take(&mut cur), self, state, &mut buf, target_width)
+        match cur.is_empty() {
+            true => spare.push(cur),
+            false => {
+                lines.push(self.expand_wide(
+                    cur,
+                    &wide,
+                    state,
+                    &mut buf,
+                    target_width,
+                    colors,
+                    &mut line_bars,
+                ));
+                let line = lines.len() - 1;
+                bars.extend(line_bars.drain(..).map(|range| (line, range)));
+            }
+        }
+        buf.clear();
+        spare.insert(0, buf);
+
+        // Lines wider than the terminal would wrap and break the redrawing of the progress bar,
+        // so they are either truncated or wrapped into lines we can keep track of
+        if target_width > 0 {
+            match self.line_overflow {
+                LineOverflow::Truncate => {
+                    for (i, line) in lines.iter_mut().enumerate().skip(first_line) {
+                        if let Cow::Owned(truncated) = truncate_str(line, target_width, "") {
+                            *line = truncated;
+                            // The bars may have been cut, so they are kept as text
+                            bars.retain(|(bar_line, _)| *bar_line != i);
+                        }
+                    }
                 }
-                None => mem::take(&mut cur),
-            })
+                LineOverflow::Wrap => {
+                    let mut i = first_line;
+                    while i < lines.len() {
+                        let wrapped = wrap_str(&lines[i], target_width);
+                        let n = wrapped.len();
+                        if n > 1 {
+                            lines.splice(i..=i, wrapped);
+                            bars.retain(|(bar_line, _)| *bar_line != i);
+                            for (bar_line, _) in &mut bars {
+                                if *bar_line > i {
+                                    *bar_line += n - 1;
+                                }
+                            }
+                        }
+                        i += n;
+                    }
+                }
+            }
+        }
+
+        // Bars are only replaced once laid out, as `console` can't measure graphics
+        let graphics = graphics.filter(|graphics| {
+            state.chunks.is_none()
+                && self.overflow_ratio(state).is_none()
+                && graphics.is_supported()
+        });
+        if let Some(graphics) = graphics {
+            graphics::apply(
+                lines,
+                &bars,
+                graphics,
+                state.displayed_fraction(),
+                self.graphics_colors,
+            );
+        }
+    }
+
+    /// Replaces the placeholders of the wide elements in `cur`, sharing the space left on the
+    /// line between them proportionally to their weights.
+    #[allow(clippy::too_many_arguments)]
+    fn expand_wide(
+        &self,
+        cur: String,
+        wide: &[(WideElement<'_>, u16)],
+        state: &ProgressState,
+        buf: &mut String,
+        width: usize,
+        colors: Option<bool>,
+        bars: &mut Vec<Range<usize>>,
+    ) -> String {
+        if wide.is_empty() {
+            return cur;
+        }
+
+        let left = width.saturating_sub(measure_text_width(&cur.replace('\x00', "")));
+        let total = wide
+            .iter()
+            .map(|(_, w)| usize::from(*w))
+            .sum::<usize>()
+            .max(1);
+        let mut remaining = left;
+        let mut pieces = cur.split('\x00');
+        let first = pieces.next().unwrap_or_default();
+        let mut expanded = String::with_capacity(cur.len() + left);
+        expanded.push_str(first);
+        // Positions of the placeholders in `cur` and the lengths they were expanded to
+        let mut placeholder = first.len();
+        let mut expansions = Vec::with_capacity(wide.len());
+        let mut wide_bars = Vec::new();
+        for (i, ((element, weight), piece)) in wide.iter().zip(pieces).enumerate() {
+            let last = i + 1 == wide.len();
+            let (share, at_end) = match (width, last) {
+                // Without a known width, wide elements take their natural width
+                (0, _) => (element.natural_width(state, colors), piece.is_empty()),
+                (_, true) => (remaining, piece.is_empty()),
+                (_, false) => (left * usize::from(*weight) / total, false),
+            };
+            remaining = remaining.saturating_sub(share);
+            element.expand(share, at_end, self, state, buf, colors);
+            if let WideElement::Bar { .. } = element {
+                wide_bars.push(expanded.len()..expanded.len() + buf.len());
+            }
+            expanded.push_str(buf);
+            expanded.push_str(piece);
+            expansions.push((placeholder, buf.len()));
+            placeholder += 1 + piece.len();
+        }
+
+        for bar in bars.iter_mut() {
+            let (count, len) = expansions
+                .iter()
+                .filter(|(pos, _)| *pos < bar.start)
+                .fold((0, 0), |(count, len), (_, l)| (count + 1, len + l));
+            *bar = bar.start - count + len..bar.end - count + len;
+        }
+        bars.extend(wide_bars);
+        bars.sort_by_key(|bar| bar.start);
+        expanded
+    }
+}
+
+/// Sanitizes a message or prefix before it is rendered
+///
+/// Control characters would corrupt the layout of the progress bars, so tabs and newlines are
+/// replaced by spaces and other control characters are removed. ANSI color codes of pre-styled
+/// text are kept if colors are enabled, any other escape sequence (such as cursor movement) is
+/// removed.
+fn styled_text(s: &str, colors: Option<bool>) -> Cow<'_, str> {
+    if !s.chars().any(char::is_control) {
+        return Cow::Borrowed(s);
+    }
+
+    let colors = colors.unwrap_or_else(compat::colors_enabled);
+    let mut sanitized = String::with_capacity(s.len());
+    for (part, is_ansi) in AnsiCodeIterator::new(s) {
+        if !is_ansi {
+            sanitized.extend(part.chars().filter_map(|c| match c {
+                '\t' | '\n' => Some(' '),
+                c if c.is_control() => None,
+                c => Some(c),
+            }));
+        } else if colors && part.starts_with("\x1b[") && part.ends_with('m') {
+            sanitized.push_str(part);
         }
     }
+    Cow::Owned(sanitized)
+}
+
+/// Writes the steps per second of `seconds` as a sparkline, scaled to the fastest second
+fn write_sparkline(buf: &mut String, seconds: impl Iterator<Item = Option<u64>>) {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let seconds = seconds.collect::<Vec<_>>();
+    let max = seconds.iter().flatten().copied().max().unwrap_or(0);
+    for steps in seconds {
+        buf.push(match steps {
+            None => ' ',
+            Some(_) if max == 0 => LEVELS[0],
+            Some(steps) => LEVELS[(steps as f64 / max as f64 * 7.0).round() as usize],
+        });
+    }
+}
+
+/// Forces `style` on or off if `colors` is set, instead of relying on `console`'s detection
+fn force_colors(style: Cow<'_, Style>, colors: Option<bool>) -> Cow<'_, Style> {
+    match colors {
+        Some(colors) => Cow::Owned(style.into_owned().force_styling(colors)),
+        None => style,
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -390,42 +1535,49 @@ enum WideElement<'a> {
 }
 
 impl<'a> WideElement<'a> {
+    /// The width of the element if the width of the terminal is unknown
+    fn natural_width(&self, state: &ProgressState, colors: Option<bool>) -> usize {
+        match self {
+            Self::Bar { .. } => 20,
+            Self::Message { .. } => measure_text_width(&styled_text(state.message(), colors)),
+        }
+    }
+
+    /// Renders the element into `buf`, `at_end` indicates that nothing follows it on the line.
     fn expand(
         self,
-        cur: String,
+        width: usize,
+        at_end: bool,
         style: &ProgressStyle,
         state: &ProgressState,
         buf: &mut String,
-        width: usize,
-    ) -> String {
-        let left = width.saturating_sub(measure_text_width(&*cur.replace("\x00", "")));
+        colors: Option<bool>,
+    ) {
+        buf.clear();
         match self {
-            Self::Bar { alt_style } => cur.replace(
-                "\x00",
-                &format!(
-                    "{}",
-                    style.format_bar(state.fraction(), left, alt_style.as_ref())
-                ),
-            ),
+            Self::Bar { alt_style } => {
+                style.write_bar(buf, state, width, alt_style.as_ref(), colors)
+            }
             WideElement::Message { align } => {
-                buf.clear();
+                let msg = styled_text(state.message(), colors);
+                let msg = match style.marquee {
+                    true => marquee_window(&msg, width, state.tick),
+                    false => msg,
+                };
                 buf.write_fmt(format_args!(
                     "{}",
                     PaddedStringDisplay {
-                        str: state.message(),
-                        width: left,
+                        str: &msg,
+                        width,
                         align: *align,
                         truncate: true,
                     }
                 ))
                 .unwrap();
 
-                let trimmed = match cur.as_bytes().last() == Some(&b'\x00') {
-                    true => buf.trim_end(),
-                    false => buf,
-                };
-
-                cur.replace("\x00", trimmed)
+                if at_end {
+                    buf.truncate(buf.trim_end().len());
+                }
             }
         }
     }
@@ -434,26 +1586,129 @@ impl<'a> WideElement<'a> {
 #[derive(Clone, Debug)]
 struct Template {
     parts: Vec<TemplatePart>,
+    /// The template string the template was parsed from, if any
+    source: Option<String>,
 }
 
-impl Template {
+impl Template {
+    /// Merges adjacent literals and resolves `key_styles` into the placeholders without a style
+    fn precompile(&mut self, key_styles: &HashMap<String, Style>) {
+        let mut parts: Vec<TemplatePart> = Vec::with_capacity(self.parts.len());
+        for part in mem::take(&mut self.parts) {
+            match (parts.last_mut(), part) {
+                (Some(TemplatePart::Literal(prev)), TemplatePart::Literal(s)) => prev.push_str(&s),
+                (
+                    _,
+                    TemplatePart::Placeholder {
+                        key,
+                        align,
+                        width,
+                        truncate,
+                        style,
+                        alt_style,
+                        rate,
+                    },
+                ) => {
+                    let style = style.or_else(|| key_styles.get(&key).cloned());
+                    parts.push(TemplatePart::Placeholder {
+                        key,
+                        align,
+                        width,
+                        truncate,
+                        style,
+                        alt_style,
+                        rate,
+                    })
+                }
+                (_, part) => parts.push(part),
+            }
+        }
+        self.parts = parts;
+    }
+
+    fn from_columns(columns: impl IntoIterator<Item = Column>) -> Self {
+        let (mut parts, mut source) = (vec![], String::new());
+        for column in columns {
+            if !parts.is_empty() {
+                parts.push(TemplatePart::Literal(" ".into()));
+                source.push(' ');
+            }
+
+            let (key, width) = match column {
+                Column::Literal(s) => {
+                    match s.contains(&['{', '}'][..]) {
+                        true => source.push_str(&format!("{{raw}}{}{}", s, RAW_END)),
+                        false => source.push_str(&s),
+                    }
+                    parts.push(TemplatePart::Literal(s.into_owned()));
+                    continue;
+                }
+                Column::Bar(width) => ("bar", Some(width)),
+                Column::Key(key) => (key, None),
+                column => (column.key(), None),
+            };
+
+            match width {
+                Some(width) => source.push_str(&format!("{{{}:{}}}", key, width)),
+                None => source.push_str(&format!("{{{}}}", key)),
+            }
+
+            parts.push(TemplatePart::Placeholder {
+                key: key.into(),
+                align: Alignment::Left,
+                width,
+                truncate: false,
+                style: None,
+                alt_style: None,
+                rate: RateMode::Smoothed,
+            });
+        }
+
+        Self {
+            parts,
+            source: Some(source),
+        }
+    }
+
     fn from_str(s: &str) -> Self {
+        Self::parse(s, None)
+    }
+
+    fn parse(s: &str, theme: Option<&Theme>) -> Self {
+        Self::try_parse(s, theme).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_parse(s: &str, theme: Option<&Theme>) -> Result<Self, StyleError> {
         use State::*;
+        let style_of = |buf: &str| match theme {
+            Some(theme) => Style::from_dotted_str(&theme.apply(buf)),
+            None => Style::from_dotted_str(buf),
+        };
         let (mut state, mut parts, mut buf) = (Literal, vec![], String::new());
-        for c in s.chars() {
+        for (offset, c) in s.char_indices() {
+            // A single `}` is a literal brace, carry on with the next character as a literal
+            if state == DoubleClose && c != '}' {
+                state = Literal;
+            }
+
             let new = match (state, c) {
                 (Literal, '{') => (MaybeOpen, None),
-                (Literal, '\n') => {
+                (Literal, '\n') | (Raw, '\n') => {
                     if !buf.is_empty() {
                         parts.push(TemplatePart::Literal(mem::take(&mut buf)));
                     }
                     parts.push(TemplatePart::NewLine);
-                    (Literal, None)
+                    (state, None)
                 }
+                (Raw, c) => (Raw, Some(c)),
                 (Literal, '}') => (DoubleClose, Some('}')),
                 (Literal, c) => (Literal, Some(c)),
                 (DoubleClose, '}') => (Literal, None),
                 (MaybeOpen, '{') => (Literal, Some('{')),
+                (MaybeOpen, c @ '}') | (MaybeOpen, c @ ':') => {
+                    buf.push('{');
+                    (Literal, Some(c))
+                }
                 (MaybeOpen, c) | (Key, c) if c.is_ascii_whitespace() => {
                     // If we find whitespace where the variable key is supposed to go,
                     // backtrack and act as if this was a literal.
@@ -467,6 +1722,10 @@ impl Template {
                 (MaybeOpen, c) if c != '}' && c != ':' => (Key, Some(c)),
                 (Key, c) if c != '}' && c != ':' => (Key, Some(c)),
                 (Key, ':') => (Align, None),
+                (Key, '}') if buf == "raw" => {
+                    buf.clear();
+                    (Raw, None)
+                }
                 (Key, '}') => (Literal, None),
                 (Key, '!') if !buf.is_empty() => {
                     parts.push(TemplatePart::Placeholder {
@@ -476,6 +1735,7 @@ impl Template {
                         truncate: true,
                         style: None,
                         alt_style: None,
+                        rate: RateMode::Smoothed,
                     });
                     (Width, None)
                 }
@@ -492,6 +1752,10 @@ impl Template {
                     (Width, None)
                 }
                 (Align, c @ '0'..='9') => (Width, Some(c)),
+                (Align, c) if c.is_ascii_alphabetic() => (Mode, Some(c)),
+                (Mode, ':') => (Align, None),
+                (Mode, '}') => (Literal, None),
+                (Mode, c) => (Mode, Some(c)),
                 (Align, '!') | (Width, '!') => {
                     if let Some(TemplatePart::Placeholder { truncate, .. }) = parts.last_mut() {
                         *truncate = true;
@@ -508,7 +1772,7 @@ impl Template {
                 (FirstStyle, c) => (FirstStyle, Some(c)),
                 (AltStyle, '}') => (Literal, None),
                 (AltStyle, c) => (AltStyle, Some(c)),
-                (st, c) => panic!("unreachable state: {:?} @ {:?}", c, st),
+                (_, character) => return Err(StyleError::InvalidTemplate { character, offset }),
             };
 
             match (state, new.0) {
@@ -523,23 +1787,47 @@ impl Template {
                         truncate: false,
                         style: None,
                         alt_style: None,
+                        rate: RateMode::Smoothed,
                     })
                 }
                 (Width, FirstStyle) | (Width, Literal) if !buf.is_empty() => {
-                    if let Some(TemplatePart::Placeholder { width, .. }) = parts.last_mut() {
-                        *width = Some(buf.parse().unwrap());
+                    if let Some(TemplatePart::Placeholder { key, width, .. }) = parts.last_mut() {
+                        match buf.parse() {
+                            Ok(parsed) if parsed > 0 => *width = Some(parsed),
+                            _ => {
+                                return Err(StyleError::InvalidWidth {
+                                    key: key.clone(),
+                                    width: buf,
+                                })
+                            }
+                        }
                         buf.clear();
                     }
                 }
                 (FirstStyle, AltStyle) | (FirstStyle, Literal) if !buf.is_empty() => {
                     if let Some(TemplatePart::Placeholder { style, .. }) = parts.last_mut() {
-                        *style = Some(Style::from_dotted_str(&buf));
+                        *style = Some(style_of(&buf));
                         buf.clear();
                     }
                 }
+                (Mode, Align) | (Mode, Literal) => {
+                    if let Some(TemplatePart::Placeholder { key, style, .. }) = parts.last_mut() {
+                        if key == "color" {
+                            *style = Some(style_of(&buf));
+                        }
+                    }
+                    if let Some(TemplatePart::Placeholder { rate, .. }) = parts.last_mut() {
+                        *rate = match buf.as_str() {
+                            "instant" => RateMode::Instant,
+                            "avg" => RateMode::Average,
+                            _ => RateMode::Smoothed,
+                        };
+                    }
+                    buf.clear();
+                }
                 (AltStyle, Literal) if !buf.is_empty() => {
                     if let Some(TemplatePart::Placeholder { alt_style, .. }) = parts.last_mut() {
-                        *alt_style = Some(Style::from_dotted_str(&buf));
+                        *alt_style = Some(style_of(&buf));
                         buf.clear();
                     }
                 }
@@ -550,13 +1838,34 @@ impl Template {
             if let Some(c) = new.1 {
                 buf.push(c);
             }
+
+            if state == Raw && buf.ends_with(RAW_END) {
+                buf.truncate(buf.len() - RAW_END.len());
+                if !buf.is_empty() {
+                    parts.push(TemplatePart::Literal(mem::take(&mut buf)));
+                }
+                state = Literal;
+            }
         }
 
-        if matches!(state, Literal | DoubleClose) && !buf.is_empty() {
+        if matches!(state, Literal | DoubleClose | Raw) && !buf.is_empty() {
             parts.push(TemplatePart::Literal(buf));
         }
 
-        Self { parts }
+        for part in parts.iter_mut() {
+            if let TemplatePart::Placeholder { key, style, .. } = part {
+                match key.as_str() {
+                    "color" => *part = TemplatePart::Color(style.take()),
+                    "/color" => *part = TemplatePart::Color(None),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Self {
+            parts,
+            source: Some(s.to_owned()),
+        })
     }
 }
 
@@ -570,17 +1879,26 @@ enum TemplatePart {
         truncate: bool,
         style: Option<Style>,
         alt_style: Option<Style>,
+        /// Rate used by the `*_per_sec` keys
+        rate: RateMode,
     },
+    /// Starts styling the following parts with `{color:STYLE}`, or stops with `{/color}`
+    Color(Option<Style>),
     NewLine,
 }
 
+/// Ends a raw section of a template, started with `{raw}`
+const RAW_END: &str = "{/raw}";
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum State {
     Literal,
+    Raw,
     MaybeOpen,
     DoubleClose,
     Key,
     Align,
+    Mode,
     Width,
     FirstStyle,
     AltStyle,
@@ -590,7 +1908,11 @@ struct BarDisplay<'a> {
     chars: &'a [Box<str>],
     filled: usize,
     cur: Option<usize>,
-    rest: console::StyledObject<RepeatedStringDisplay<'a>>,
+    /// Number of "to do" clusters
+    rest: usize,
+    rest_style: Cow<'a, Style>,
+    /// Offset into the "to do" clusters and string of the time budget marker, if any
+    marker: Option<(usize, &'a str)>,
 }
 
 impl<'a> fmt::Display for BarDisplay<'a> {
@@ -601,7 +1923,21 @@ impl<'a> fmt::Display for BarDisplay<'a> {
         if let Some(cur) = self.cur {
             f.write_str(&self.chars[cur])?;
         }
-        self.rest.fmt(f)
+
+        let rest = |num| RepeatedStringDisplay {
+            str: &self.chars[self.chars.len() - 1],
+            num,
+        };
+        match self.marker {
+            Some((offset, marker)) => {
+                self.rest_style.apply_to(rest(offset)).fmt(f)?;
+                self.rest_style.apply_to(marker).fmt(f)?;
+                self.rest_style
+                    .apply_to(rest(self.rest - offset - 1))
+                    .fmt(f)
+            }
+            None => self.rest_style.apply_to(rest(self.rest)).fmt(f),
+        }
     }
 }
 
@@ -631,7 +1967,7 @@ impl<'a> fmt::Display for PaddedStringDisplay<'a> {
         let cols = measure_text_width(self.str);
         if cols >= self.width {
             return match self.truncate {
-                true => f.write_str(self.str.get(..self.width).unwrap_or(self.str)),
+                true => f.write_str(&truncate_str(self.str, self.width, "")),
                 false => f.write_str(self.str),
             };
         }
@@ -654,6 +1990,82 @@ impl<'a> fmt::Display for PaddedStringDisplay<'a> {
     }
 }
 
+/// A typed template element for [`ProgressStyle::columns()`]
+///
+/// Each column renders the same as the template key of the same name (see the
+/// [list of template keys](./index.html#templates)).
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Column {
+    /// The current spinner tick string (`{spinner}`)
+    Spinner,
+    /// The prefix (`{prefix}`)
+    Prefix,
+    /// The message (`{msg}`)
+    Message,
+    /// The message, filling the remaining space (`{wide_msg}`)
+    WideMessage,
+    /// A progress bar with the given width (`{bar:WIDTH}`)
+    Bar(u16),
+    /// A progress bar filling the remaining space (`{wide_bar}`)
+    WideBar,
+    /// The current position (`{pos}`)
+    Pos,
+    /// The total length (`{len}`)
+    Len,
+    /// The completion percentage (`{percent}`)
+    Percent,
+    /// The current position as bytes (`{bytes}`)
+    Bytes,
+    /// The total length as bytes (`{total_bytes}`)
+    TotalBytes,
+    /// The speed in steps per second (`{per_sec}`)
+    PerSec,
+    /// The speed in bytes per second (`{bytes_per_sec}`)
+    BytesPerSec,
+    /// The elapsed time (`{elapsed}`)
+    Elapsed,
+    /// The elapsed time as `HH:MM:SS` (`{elapsed_precise}`)
+    ElapsedPrecise,
+    /// The remaining time (`{eta}`)
+    Eta,
+    /// The remaining time as `HH:MM:SS` (`{eta_precise}`)
+    EtaPrecise,
+    /// The remaining time with a precision adapted to it (`{eta_adaptive}`)
+    EtaAdaptive,
+    /// A custom key added with [`ProgressStyle::with_key()`]
+    Key(&'static str),
+    /// Literal text
+    Literal(Cow<'static, str>),
+}
+
+impl Column {
+    fn key(&self) -> &'static str {
+        match self {
+            Column::Spinner => "spinner",
+            Column::Prefix => "prefix",
+            Column::Message => "msg",
+            Column::WideMessage => "wide_msg",
+            Column::Bar(_) => "bar",
+            Column::WideBar => "wide_bar",
+            Column::Pos => "pos",
+            Column::Len => "len",
+            Column::Percent => "percent",
+            Column::Bytes => "bytes",
+            Column::TotalBytes => "total_bytes",
+            Column::PerSec => "per_sec",
+            Column::BytesPerSec => "bytes_per_sec",
+            Column::Elapsed => "elapsed",
+            Column::ElapsedPrecise => "elapsed_precise",
+            Column::Eta => "eta",
+            Column::EtaPrecise => "eta_precise",
+            Column::EtaAdaptive => "eta_adaptive",
+            Column::Key(key) => key,
+            Column::Literal(_) => "",
+        }
+    }
+}
+
 /// Behavior of a progress bar when it is finished
 ///
 /// This is invoked when a [`ProgressBar`] or [`ProgressBarIter`] completes and
@@ -663,6 +2075,7 @@ impl<'a> fmt::Display for PaddedStringDisplay<'a> {
 /// [`ProgressBarIter`]: crate::ProgressBarIter
 /// [`ProgressBar::is_finished`]: crate::ProgressBar::is_finished
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ProgressFinish {
     /// Finishes the progress bar and leaves the current message
     ///
@@ -696,10 +2109,119 @@ impl Default for ProgressFinish {
     }
 }
 
+/// Serialized form of a [`ProgressStyle`]
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct StyleConfig {
+    template: String,
+    tick_strings: Vec<String>,
+    progress_chars: String,
+    on_finish: ProgressFinish,
+}
+
+#[cfg(feature = "serde")]
+impl Default for StyleConfig {
+    fn default() -> Self {
+        ProgressStyle::default_bar().into()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ProgressStyle> for StyleConfig {
+    fn from(style: ProgressStyle) -> Self {
+        Self {
+            template: style.template.source.unwrap_or_default(),
+            tick_strings: style.tick_strings.iter().map(|s| s.to_string()).collect(),
+            progress_chars: style.progress_chars.concat(),
+            on_finish: style.on_finish,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<StyleConfig> for ProgressStyle {
+    type Error = StyleError;
+
+    fn try_from(config: StyleConfig) -> Result<Self, Self::Error> {
+        let tick_strings = config.tick_strings.iter().map(|s| &**s).collect::<Vec<_>>();
+        Ok(ProgressStyle::default_bar()
+            .try_template(&config.template)?
+            .try_tick_strings(&tick_strings)?
+            .try_progress_chars(&config.progress_chars)?
+            .on_finish(config.on_finish))
+    }
+}
+
+/// Error returned by the validating builder methods of [`ProgressStyle`], like
+/// [`ProgressStyle::try_template()`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StyleError {
+    /// Fewer than 2 progress characters were given
+    TooFewProgressChars,
+    /// The progress characters aren't all of the same width
+    UnequalProgressChars,
+    /// Fewer than 2 tick strings were given
+    TooFewTickStrings,
+    /// One of the tick strings is empty
+    EmptyTickString,
+    /// The template has a character that isn't allowed where it appears
+    InvalidTemplate {
+        /// The unexpected character
+        character: char,
+        /// The byte offset of the character in the template
+        offset: usize,
+    },
+    /// The width of a key in the template isn't a number between 1 and 65535
+    InvalidWidth {
+        /// The key with the invalid width
+        key: String,
+        /// The width as written in the template
+        width: String,
+    },
+}
+
+impl fmt::Display for StyleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StyleError::TooFewProgressChars => f.write_str("at least 2 progress chars required"),
+            StyleError::UnequalProgressChars => {
+                f.write_str("got passed un-equal width progress characters")
+            }
+            StyleError::TooFewTickStrings => f.write_str("at least 2 tick strings required"),
+            StyleError::EmptyTickString => f.write_str("tick strings must not be empty"),
+            StyleError::InvalidTemplate { character, offset } => write!(
+                f,
+                "unexpected {:?} at offset {} of the template",
+                character, offset
+            ),
+            StyleError::InvalidWidth { key, width } => {
+                write!(f, "invalid width {:?} of template key {:?}", width, key)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StyleError {}
+
+/// Handling of lines wider than the terminal, see [`ProgressStyle::line_overflow()`]
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum LineOverflow {
+    /// Cuts off the end of the line
+    Truncate,
+    /// Continues the line on the next lines
+    Wrap,
+}
+
+/// Horizontal alignment of padded text, see [`pad_str()`]
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
-enum Alignment {
+pub enum Alignment {
+    /// Pads on the right
     Left,
+    /// Pads on both sides
     Center,
+    /// Pads on the left
     Right,
 }
 
@@ -707,7 +2229,36 @@ enum Alignment {
 mod tests {
     use super::*;
     use crate::draw_target::ProgressDrawTarget;
-    use crate::state::ProgressState;
+    use crate::state::{ProgressState, Throughput};
+    use std::time::Instant;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let style = ProgressStyle::default_bar()
+            .columns(vec![
+                Column::Literal("{".into()),
+                Column::Bar(10),
+                Column::Pos,
+            ])
+            .progress_chars("#>-")
+            .on_finish(ProgressFinish::WithMessage("done".into()));
+        let json = serde_json::to_string(&style).unwrap();
+        assert!(json.contains(r#""template":"{raw}{{/raw} {bar:10} {pos}""#));
+
+        let state = ProgressState::new(10);
+        let render = |style: &ProgressStyle| {
+            let mut lines = Vec::new();
+            style.format_state(&state, &mut lines, 80, Some(false));
+            lines
+        };
+        let parsed: ProgressStyle = serde_json::from_str(&json).unwrap();
+        assert_eq!(render(&parsed), render(&style));
+        assert_eq!(render(&parsed), ["{ ---------- 0"]);
+        assert!(matches!(parsed.on_finish, ProgressFinish::WithMessage(msg) if msg == "done"));
+
+        assert!(serde_json::from_str::<ProgressStyle>(r#"{"tick_strings": ["x"]}"#).is_err());
+    }
 
     #[test]
     fn test_expand_template() {
@@ -721,18 +2272,469 @@ mod tests {
         style.format_map.insert("bar", |_| "BAR".into());
 
         style.template = Template::from_str("{{ {foo} {bar} }}");
-        style.format_state(&state, &mut buf, width);
+        style.format_state(&state, &mut buf, width, None);
         assert_eq!(&buf[0], "{ FOO BAR }");
 
         buf.clear();
         style.template = Template::from_str(r#"{ "foo": "{foo}", "bar": {bar} }"#);
-        style.format_state(&state, &mut buf, width);
+        style.format_state(&state, &mut buf, width, None);
         assert_eq!(&buf[0], r#"{ "foo": "FOO", "bar": BAR }"#);
     }
 
+    #[test]
+    fn test_message_sanitization() {
+        let mut state = ProgressState::new(10);
+        state.message = "a\rb\x1b[2Kc\0d\x1b[31me\x1b[0m\nf".into();
+        let style = ProgressStyle::default_bar().template("{msg}");
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 80, Some(true));
+        assert_eq!(buf[0], "abcd\x1b[31me\x1b[0m f");
+
+        buf.clear();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(buf[0], "abcde f");
+    }
+
+    #[test]
+    fn test_template_escaping() {
+        let state = ProgressState::new(10);
+        let mut buf = Vec::new();
+        let mut style = ProgressStyle::default_bar();
+        style.format_map.insert("foo", |_| "FOO".into());
+
+        style.template = Template::from_str("{{{foo}}} } {} {:x}");
+        style.format_state(&state, &mut buf, 80, None);
+        assert_eq!(&buf[0], "{FOO} } {} {:x}");
+
+        buf.clear();
+        style.template = Template::from_str(r#"{raw}{"foo":{foo}}{/raw} {foo} {raw}{pos}"#);
+        style.format_state(&state, &mut buf, 80, None);
+        assert_eq!(&buf[0], r#"{"foo":{foo}} FOO {pos}"#);
+    }
+
+    #[test]
+    fn test_color_markup_width() {
+        let mut state = ProgressState::new(10);
+        state.message = "\u{1b}[31mred\u{1b}[0m".into();
+        let render = |template: &str, colors| {
+            let mut buf = Vec::new();
+            ProgressStyle::default_bar()
+                .template(template)
+                .progress_chars("#>-")
+                .format_state(&state, &mut buf, 20, Some(colors));
+            buf.pop().unwrap()
+        };
+
+        let plain = render("ab {wide_bar} {msg}", false);
+        assert_eq!(plain, "ab ------------- red");
+        let colored = render("{color:green}ab{/color} {wide_bar} {msg}", true);
+        assert_eq!(
+            colored,
+            "\u{1b}[32mab\u{1b}[0m ------------- \u{1b}[31mred\u{1b}[0m"
+        );
+        assert_eq!(measure_text_width(&colored), 20);
+
+        let colored = render("{color:.green}{pos}{/color}/{len} {wide_bar}", true);
+        assert_eq!(colored, "\u{1b}[32m0\u{1b}[0m/10 ---------------");
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut state = ProgressState::new(10);
+        state.pos = 15;
+        let style = ProgressStyle::default_bar()
+            .template("{bar:9} {percent}%")
+            .progress_chars("#>-");
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(buf, ["######### 100%"]);
+
+        let style = style.overflow("+", Style::new().red());
+        buf.clear();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(buf, ["######+++ 150%"]);
+        buf.clear();
+        style.format_state(&state, &mut buf, 80, Some(true));
+        assert_eq!(buf, ["######\u{1b}[31m+++\u{1b}[0m 150%"]);
+
+        state.pos = 5;
+        buf.clear();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(buf, ["####>---- 50%"]);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        let now = Instant::now();
+        let mut state = ProgressState::new(1000);
+        state.throughput = Throughput::new(now - Duration::from_millis(4500));
+        for (second, steps) in [(0, 40), (1, 80), (3, 20)].iter() {
+            state
+                .throughput
+                .record(*steps, now - Duration::from_millis(4400 - second * 1000));
+        }
+        let style = ProgressStyle::default_bar().template("[{sparkline:6}]");
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        // Two seconds before the start, an idle second, and the current second isn't shown
+        assert_eq!(buf, ["[  ▅█▁▃]"]);
+    }
+
+    #[test]
+    fn test_template_file() {
+        let path = std::env::temp_dir().join(format!("indicatif-template-{}", std::process::id()));
+        fs::write(&path, "{pos}/{len}\n").unwrap();
+        let mut style = ProgressStyle::default_bar()
+            .with_template_file(&path)
+            .unwrap()
+            .hot_reload(Duration::from_secs(0));
+        let mut state = ProgressState::new(10);
+        state.pos = 3;
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(buf, ["3/10"]);
+
+        fs::write(&path, "{percent}% done").unwrap();
+        style.reload_template(Instant::now());
+        buf.clear();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(buf, ["30% done"]);
+
+        // So does an invalid template
+        fs::write(&path, "{pos:{ oops").unwrap();
+        style.reload_template(Instant::now());
+        buf.clear();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(buf, ["30% done"]);
+        let err = ProgressStyle::default_bar()
+            .with_template_file(&path)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A missing file keeps the previous template
+        fs::remove_file(&path).unwrap();
+        style.reload_template(Instant::now());
+        buf.clear();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(buf, ["30% done"]);
+        assert!(ProgressStyle::default_bar()
+            .with_template_file(&path)
+            .is_err());
+    }
+
+    #[test]
+    fn test_precompile_reuses_buffers() {
+        let style = ProgressStyle::default_bar()
+            .template("{pos:.bold}/{len} {bar:8}\n{{{msg}}}")
+            .key_style("len", Style::new().red());
+        let mut state = ProgressState::new(10);
+        state.pos = 5;
+        state.message = "hi".into();
+        let mut expected = Vec::new();
+        style.format_state(&state, &mut expected, 80, Some(true));
+
+        let style = style.precompile();
+        let (mut lines, mut spare) = (Vec::new(), Vec::new());
+        style.format_state_with(&state, &mut lines, &mut spare, 80, Some(true), None);
+        assert_eq!(lines, expected);
+
+        // The second draw builds its lines in the strings of the first one
+        spare.extend(lines.drain(..).rev().map(|mut line| {
+            line.clear();
+            line
+        }));
+        let buffers = spare.iter().map(|s| s.as_ptr()).collect::<Vec<_>>();
+        style.format_state_with(&state, &mut lines, &mut spare, 80, Some(true), None);
+        assert_eq!(lines, expected);
+        assert!(lines.iter().all(|line| buffers.contains(&line.as_ptr())));
+    }
+
+    #[test]
+    fn test_time_keys_with_huge_eta() {
+        let mut state = ProgressState::new(u64::MAX - 1);
+        state.update(Instant::now() + Duration::from_secs(1), |state| {
+            state.pos = 1
+        });
+        let mut buf = Vec::new();
+
+        let mut style = ProgressStyle::default_bar();
+        style.template = Template::from_str("[{end_time_estimate}] {total_duration} {eta}");
+        style.format_state(&state, &mut buf, 80, None);
+        assert!(buf[0].starts_with("[] "), "{}", buf[0]);
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let mut state = ProgressState::new(10);
+        state.pos = 2;
+        state.expected_duration = Some(Duration::from_secs(10));
+        state.started = Instant::now() - Duration::from_secs(6);
+        let style = ProgressStyle::default_bar().progress_chars("#>-");
+        let mut buf = String::new();
+        style.write_bar(&mut buf, &state, 10, None, None);
+        assert_eq!(buf, "##>---|---");
+        assert_eq!(state.overshoot(), None);
+
+        buf.clear();
+        state.started = Instant::now() - Duration::from_secs(12);
+        style.write_bar(&mut buf, &state, 10, None, None);
+        assert_eq!(buf, "##>------|");
+        let mut lines = Vec::new();
+        style
+            .template("{overshoot}")
+            .format_state(&state, &mut lines, 80, None);
+        assert_eq!(lines[0], "+2s");
+    }
+
+    #[test]
+    fn test_chunk_bitmap() {
+        let mut state = ProgressState::new(8);
+        state.chunks = Some(vec![true, false, true, true, false, false, true, false]);
+        let style = ProgressStyle::default_bar().progress_chars("#-.");
+        let mut buf = String::new();
+        style.write_bar(&mut buf, &state, 8, None, None);
+        assert_eq!(buf, "#.##..#.");
+
+        buf.clear();
+        style.write_bar(&mut buf, &state, 4, None, None);
+        assert_eq!(buf, "-#.-");
+
+        buf.clear();
+        state.chunks = Some(vec![true, false]);
+        style.write_bar(&mut buf, &state, 4, None, None);
+        assert_eq!(buf, "##..");
+    }
+
+    #[test]
+    fn test_styled_message_width() {
+        let mut state = ProgressState::new(10);
+        state.message = "\x1b[31mwarning\x1b[0m: disk almost full".into();
+        let style = ProgressStyle::default_bar().template("{bar:5} {msg}");
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 20, Some(true));
+        assert_eq!(&buf[0], "░░░░░ \x1b[31mwarning\x1b[0m: disk ");
+
+        buf.clear();
+        style.format_state(&state, &mut buf, 20, Some(false));
+        assert_eq!(&buf[0], "░░░░░ warning: disk ");
+    }
+
+    #[test]
+    fn test_key_style() {
+        let state = ProgressState::new(10);
+        let style = ProgressStyle::default_bar()
+            .template("{pos:.red}/{len}")
+            .key_style("pos", Style::new().green())
+            .key_style("len", Style::new().bold());
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 80, Some(true));
+        assert_eq!(&buf[0], "\u{1b}[31m0\u{1b}[0m/\u{1b}[1m10\u{1b}[0m");
+    }
+
+    #[test]
+    fn test_default_bar_eighths() {
+        let style = ProgressStyle::default_bar();
+        assert_eq!(
+            style.format_bar(0.55, 10, None, None).to_string(),
+            "█████▌░░░░"
+        );
+        assert_eq!(
+            style.format_bar(0.99, 10, None, None).to_string(),
+            "█████████▉"
+        );
+        assert_eq!(
+            style.format_bar(1.0, 10, None, None).to_string(),
+            "██████████"
+        );
+    }
+
+    #[test]
+    fn test_default_bar_ascii() {
+        let mut state = ProgressState::new(10);
+        state.pos = 5;
+        let mut buf = Vec::new();
+        ProgressStyle::default_bar_ascii().format_state(&state, &mut buf, 16, None);
+        assert_eq!(&buf[0], "#####>----- 5/10");
+        assert!(buf[0].is_ascii());
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let ms = Duration::from_millis;
+        let durations = [ms(80), ms(300), ms(20)];
+        assert_eq!(frame_at(&durations, ms(0)), 0);
+        assert_eq!(frame_at(&durations, ms(79)), 0);
+        assert_eq!(frame_at(&durations, ms(80)), 1);
+        assert_eq!(frame_at(&durations, ms(385)), 2);
+        assert_eq!(frame_at(&durations, ms(400)), 0);
+        assert_eq!(frame_at(&[ms(0), ms(0)], ms(5)), 0);
+    }
+
+    #[test]
+    fn test_aux_keys() {
+        let mut state = ProgressState::new(10);
+        state.pos = 5;
+        state.aux_pos = 1;
+        state.aux_len = 4;
+        let style = ProgressStyle::default_bar()
+            .template("{pos}/{len} {bar:4} {aux_pos}/{aux_len} {aux_bar:4}")
+            .progress_chars("#>-");
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(buf, ["5/10 ##>- 1/4 #>--"]);
+    }
+
+    #[test]
+    fn test_unknown_width() {
+        let mut state = ProgressState::new(10);
+        state.message = "hello".into();
+        let style = ProgressStyle::default_bar()
+            .template("[{wide_bar}] {wide_msg}")
+            .progress_chars("#>-");
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 0, Some(false));
+        assert_eq!(buf, [format!("[{}] hello", "-".repeat(20))]);
+    }
+
+    #[test]
+    fn test_validation() {
+        let style = ProgressStyle::default_bar;
+        assert_eq!(
+            style().try_progress_chars("#").err(),
+            Some(StyleError::TooFewProgressChars)
+        );
+        assert_eq!(
+            style().try_tick_strings(&["x"]).err(),
+            Some(StyleError::TooFewTickStrings)
+        );
+        assert_eq!(
+            style().try_tick_chars("").err(),
+            Some(StyleError::TooFewTickStrings)
+        );
+        assert_eq!(
+            style().try_tick_strings(&["", ""]).err(),
+            Some(StyleError::EmptyTickString)
+        );
+        assert_eq!(
+            style().try_template("{bar:0}").err(),
+            Some(StyleError::InvalidWidth {
+                key: "bar".into(),
+                width: "0".into()
+            })
+        );
+        for template in ["{a:{", "{a::", "{!:/", "{a:40:}"].iter() {
+            assert!(matches!(
+                style().try_template(template).err(),
+                Some(StyleError::InvalidTemplate { .. })
+            ));
+        }
+        assert_eq!(
+            style().try_template("x{a:{").err(),
+            Some(StyleError::InvalidTemplate {
+                character: '{',
+                offset: 4
+            })
+        );
+        assert_eq!(
+            style().try_template("{pos} {bar:70000.red}").err(),
+            Some(StyleError::InvalidWidth {
+                key: "bar".into(),
+                width: "70000".into()
+            })
+        );
+
+        let style = style()
+            .try_template("{bar:40}")
+            .and_then(|style| style.try_progress_chars("=>-"))
+            .and_then(|style| style.try_tick_chars("-\\|/ "))
+            .unwrap();
+        assert_eq!(style.progress_chars.len(), 3);
+        assert_eq!(style.tick_strings.len(), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 2 tick strings required")]
+    fn test_tick_strings_panic() {
+        let _ = ProgressStyle::default_spinner().tick_strings(&["x"]);
+    }
+
+    #[test]
+    fn test_tick_every() {
+        let mut state = ProgressState::new(10);
+        let style = ProgressStyle::default_spinner()
+            .tick_chars("abc!")
+            .tick_every(3);
+        let frames = (0..9)
+            .map(|tick| {
+                state.tick = tick;
+                style.current_tick_str(&state)
+            })
+            .collect::<String>();
+        assert_eq!(frames, "aaabbbccc");
+
+        let style = style.tick_interval(Duration::from_secs(3600));
+        state.tick = 5;
+        assert_eq!(style.current_tick_str(&state), "a");
+    }
+
+    #[test]
+    fn test_line_overflow() {
+        let mut state = ProgressState::new(10);
+        state.message = "a message too long".into();
+        let style = ProgressStyle::default_bar().template("{pos}/{len} {msg}\n{pos}");
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 8, None);
+        assert_eq!(buf, ["0/10 a m", "0"]);
+
+        buf.clear();
+        let style = style.line_overflow(LineOverflow::Wrap);
+        style.format_state(&state, &mut buf, 8, None);
+        assert_eq!(buf, ["0/10 a m", "essage t", "oo long", "0"]);
+
+        assert_eq!(
+            wrap_str("\x1b[31m日本語\x1b[0m", 5),
+            ["\x1b[31m日本\x1b[0m", "\x1b[31m語\x1b[0m"]
+        );
+    }
+
+    #[test]
+    fn test_truncate_str() {
+        assert_eq!(
+            truncate_str("\x1b[31mİstanbul\x1b[0m", 4, "…"),
+            "\x1b[31mİst…\x1b[0m"
+        );
+        assert_eq!(truncate_str("日本語", 3, ""), "日");
+        assert_eq!(truncate_str("abcdef", 1, "..."), "a");
+
+        let state = ProgressState::new(10);
+        let mut style = ProgressStyle::default_bar();
+        style.format_map.insert("foo", |_| "日本語".into());
+        style.template = Template::from_str("{foo:4!}|");
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 80, None);
+        assert_eq!(&buf[0], "日本|");
+    }
+
+    #[test]
+    fn test_forced_colors() {
+        let state = ProgressState::new(10);
+        let mut style = ProgressStyle::default_bar();
+        style.format_map.insert("foo", |_| "XXX".into());
+        style.template = Template::from_str("{foo:.red} {bar:3./blue}");
+
+        let mut buf = Vec::new();
+        style.format_state(&state, &mut buf, 80, Some(true));
+        assert_eq!(&buf[0], "\u{1b}[31mXXX\u{1b}[0m \u{1b}[34m░░░\u{1b}[0m");
+
+        buf.clear();
+        style.format_state(&state, &mut buf, 80, Some(false));
+        assert_eq!(&buf[0], "XXX ░░░");
+    }
+
     #[test]
     fn test_expand_template_flags() {
-        use console::set_colors_enabled;
+        use crate::compat::set_colors_enabled;
         set_colors_enabled(true);
 
         let draw_target = ProgressDrawTarget::stdout();
@@ -744,22 +2746,85 @@ mod tests {
         style.format_map.insert("foo", |_| "XXX".into());
 
         style.template = Template::from_str("{foo:5}");
-        style.format_state(&state, &mut buf, width);
+        style.format_state(&state, &mut buf, width, None);
         assert_eq!(&buf[0], "XXX  ");
 
         buf.clear();
         style.template = Template::from_str("{foo:.red.on_blue}");
-        style.format_state(&state, &mut buf, width);
+        style.format_state(&state, &mut buf, width, None);
         assert_eq!(&buf[0], "\u{1b}[31m\u{1b}[44mXXX\u{1b}[0m");
 
         buf.clear();
         style.template = Template::from_str("{foo:^5.red.on_blue}");
-        style.format_state(&state, &mut buf, width);
+        style.format_state(&state, &mut buf, width, None);
         assert_eq!(&buf[0], "\u{1b}[31m\u{1b}[44m XXX \u{1b}[0m");
 
         buf.clear();
         style.template = Template::from_str("{foo:^5.red.on_blue/green.on_cyan}");
-        style.format_state(&state, &mut buf, width);
+        style.format_state(&state, &mut buf, width, None);
         assert_eq!(&buf[0], "\u{1b}[31m\u{1b}[44m XXX \u{1b}[0m");
     }
+
+    #[test]
+    fn test_multiple_wide_elements() {
+        let mut state = ProgressState::new(10);
+        state.message = "msg".into();
+        let mut buf = Vec::new();
+
+        let mut style = ProgressStyle::default_bar().progress_chars("#-");
+        style.template = Template::from_str("{wide_msg:1}|{wide_bar:2}|");
+        style.format_state(&state, &mut buf, 32, None);
+        assert_eq!(&buf[0], "msg       |--------------------|");
+
+        buf.clear();
+        style.template = Template::from_str("{wide_bar}\n{wide_msg:>}");
+        style.format_state(&state, &mut buf, 5, None);
+        assert_eq!(buf, ["-----", "  msg"]);
+    }
+
+    #[test]
+    fn test_marquee() {
+        let mut state = ProgressState::new(10);
+        state.message = "long_name.txt".into();
+        let mut buf = Vec::new();
+
+        let mut style = ProgressStyle::default_bar().marquee(true);
+        style.template = Template::from_str("[{wide_msg}]");
+        style.format_state(&state, &mut buf, 10, None);
+        assert_eq!(&buf[0], "[long_nam]");
+
+        buf.clear();
+        state.tick = 10;
+        style.format_state(&state, &mut buf, 10, None);
+        assert_eq!(&buf[0], "[txt   lo]");
+
+        buf.clear();
+        state.tick = 5;
+        style.template = Template::from_str("[{msg:4!}] {msg:4}");
+        style.format_state(&state, &mut buf, 80, None);
+        assert_eq!(&buf[0], "[name] long_name.txt");
+    }
+
+    #[test]
+    fn test_columns_match_template() {
+        let state = ProgressState::new(10);
+        let (mut expected, mut buf) = (Vec::new(), Vec::new());
+
+        let mut style = ProgressStyle::default_bar();
+        style.format_map.insert("foo", |_| "FOO".into());
+
+        style.template = Template::from_str("{spinner} {bar:12} {foo} - {percent} {len}");
+        style.format_state(&state, &mut expected, 80, None);
+
+        let style = style.columns(vec![
+            Column::Spinner,
+            Column::Bar(12),
+            Column::Key("foo"),
+            Column::Literal("-".into()),
+            Column::Percent,
+            Column::Len,
+        ]);
+        style.format_state(&state, &mut buf, 80, None);
+        assert_eq!(buf, expected);
+    }
 }
@@ -0,0 +1,205 @@
+use std::env;
+use std::ffi::OsString;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::TermLike;
+
+/// Reports progress to systemd as the status of the service
+///
+/// The lines drawn by a progress bar are sent with `sd_notify(STATUS=...)`, so they show up in
+/// `systemctl status`. Status updates are sent at most once per second by default, the latest
+/// status is sent when the target is dropped. Nothing is sent if the process isn't started by
+/// systemd, i.e. if `NOTIFY_SOCKET` isn't set. Abstract notification sockets are not
+/// supported.
+///
+/// Use [`SystemdStatus::tee()`] to also draw to a terminal.
+///
+/// ```rust,no_run
+/// # use indicatif::{ProgressBar, ProgressDrawTarget, SystemdStatus};
+/// let target = ProgressDrawTarget::term_like(Box::new(SystemdStatus::new()));
+/// let pb = ProgressBar::with_draw_target(1000, target);
+/// ```
+#[derive(Debug)]
+pub struct SystemdStatus {
+    inner: Option<Box<dyn TermLike>>,
+    socket: Option<(UnixDatagram, OsString)>,
+    interval: Duration,
+    state: Mutex<StatusState>,
+}
+
+#[derive(Debug, Default)]
+struct StatusState {
+    /// Output of the frame being drawn
+    frame: String,
+    /// Last status, and whether it has been sent
+    status: String,
+    sent: bool,
+    last_sent: Option<Instant>,
+}
+
+impl SystemdStatus {
+    /// Creates a target reporting to the socket in `NOTIFY_SOCKET`
+    pub fn new() -> SystemdStatus {
+        let socket = env::var_os("NOTIFY_SOCKET")
+            .filter(|path| !path.to_string_lossy().starts_with('@'))
+            .and_then(|path| Some((UnixDatagram::unbound().ok()?, path)));
+        SystemdStatus {
+            inner: None,
+            socket,
+            interval: Duration::from_secs(1),
+            state: Mutex::new(StatusState::default()),
+        }
+    }
+
+    /// Also draws to `inner`, e.g. a [`Term`](crate::Term)
+    pub fn tee(mut self, inner: Box<dyn TermLike>) -> SystemdStatus {
+        self.inner = Some(inner);
+        self
+    }
+
+    /// Sets the minimum interval between status updates
+    pub fn interval(mut self, interval: Duration) -> SystemdStatus {
+        self.interval = interval;
+        self
+    }
+
+    fn send(&self, state: &mut StatusState, now: Instant) -> io::Result<()> {
+        state.sent = true;
+        state.last_sent = Some(now);
+        match &self.socket {
+            Some((socket, path)) => socket
+                .send_to(format!("STATUS={}\n", state.status).as_bytes(), path)
+                .map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn forward(&self, f: impl FnOnce(&dyn TermLike) -> io::Result<()>) -> io::Result<()> {
+        match &self.inner {
+            Some(inner) => f(&**inner),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for SystemdStatus {
+    fn default() -> SystemdStatus {
+        SystemdStatus::new()
+    }
+}
+
+/// Turns the output of a frame into a single status line
+fn status_line(frame: &str) -> String {
+    crate::compat::strip_ansi_codes(frame)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+impl TermLike for SystemdStatus {
+    fn width(&self) -> usize {
+        self.inner.as_ref().map_or(80, |inner| inner.width())
+    }
+
+    fn height(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.height())
+    }
+
+    fn move_cursor_up(&self, n: usize) -> io::Result<()> {
+        self.forward(|inner| inner.move_cursor_up(n))
+    }
+
+    fn move_cursor_down(&self, n: usize) -> io::Result<()> {
+        self.forward(|inner| inner.move_cursor_down(n))
+    }
+
+    fn move_cursor_right(&self, n: usize) -> io::Result<()> {
+        self.forward(|inner| inner.move_cursor_right(n))
+    }
+
+    fn move_cursor_left(&self, n: usize) -> io::Result<()> {
+        self.forward(|inner| inner.move_cursor_left(n))
+    }
+
+    fn write_line(&self, s: &str) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.frame.push_str(s);
+        state.frame.push('\n');
+        self.forward(|inner| inner.write_line(s))
+    }
+
+    fn write_str(&self, s: &str) -> io::Result<()> {
+        self.state.lock().unwrap().frame.push_str(s);
+        self.forward(|inner| inner.write_str(s))
+    }
+
+    fn clear_line(&self) -> io::Result<()> {
+        self.forward(|inner| inner.clear_line())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.forward(|inner| inner.flush())?;
+
+        let mut state = self.state.lock().unwrap();
+        let frame = std::mem::take(&mut state.frame);
+        let status = status_line(&frame);
+        if status.is_empty() || status == state.status {
+            return Ok(());
+        }
+
+        state.status = status;
+        state.sent = false;
+        let now = Instant::now();
+        match state.last_sent {
+            Some(last) if now.saturating_duration_since(last) < self.interval => Ok(()),
+            _ => self.send(&mut state, now),
+        }
+    }
+}
+
+impl Drop for SystemdStatus {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        if !state.sent && !state.status.is_empty() {
+            let _ = self.send(&mut state, Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+    #[test]
+    fn reports_status() {
+        let dir = env::temp_dir().join(format!("indicatif-notify-{}", std::process::id()));
+        let _ = std::fs::remove_file(&dir);
+        let listener = UnixDatagram::bind(&dir).unwrap();
+        listener.set_nonblocking(true).unwrap();
+
+        let mut status = SystemdStatus::new().interval(Duration::from_secs(3600));
+        status.socket = Some((UnixDatagram::unbound().unwrap(), dir.clone().into()));
+
+        let pb = ProgressBar::with_draw_target(10, ProgressDrawTarget::term_like(Box::new(status)))
+            .with_style(ProgressStyle::default_bar().template("{msg:.red} {pos}/{len}"));
+        pb.set_message("migrating");
+        pb.set_position(1);
+        pb.finish_with_message("done");
+        drop(pb);
+
+        let mut buf = [0; 64];
+        let n = listener.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"STATUS=migrating 0/10\n");
+        // The updates within the interval are dropped, the last one is sent on drop
+        let n = listener.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"STATUS=done 10/10\n");
+        assert!(listener.recv(&mut buf).is_err());
+        std::fs::remove_file(&dir).unwrap();
+    }
+}
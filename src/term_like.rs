@@ -1,3 +1,4 @@
+#[cfg(feature = "console")]
 use console::Term;
 use std::fmt::Debug;
 use std::io;
@@ -11,6 +12,11 @@ pub trait TermLike: Debug + Send + Sync {
     /// Return the terminal width
     fn width(&self) -> usize;
 
+    /// Return the terminal height, or 0 if it is unknown
+    fn height(&self) -> usize {
+        0
+    }
+
     /// Move the cursor up by `n` lines
     fn move_cursor_up(&self, n: usize) -> io::Result<()>;
     /// Move the cursor down by `n` lines
@@ -30,11 +36,16 @@ pub trait TermLike: Debug + Send + Sync {
     fn flush(&self) -> io::Result<()>;
 }
 
+#[cfg(feature = "console")]
 impl TermLike for Term {
     fn width(&self) -> usize {
         self.size().1 as usize
     }
 
+    fn height(&self) -> usize {
+        self.size().0 as usize
+    }
+
     fn move_cursor_up(&self, n: usize) -> io::Result<()> {
         self.move_cursor_up(n)
     }
@@ -67,3 +78,82 @@ impl TermLike for Term {
         self.flush()
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// Records everything written to it
+    #[derive(Debug, Default)]
+    pub(crate) struct Recorder(pub(crate) Mutex<Vec<String>>);
+
+    impl TermLike for Arc<Recorder> {
+        fn width(&self) -> usize {
+            20
+        }
+        fn height(&self) -> usize {
+            10
+        }
+        fn move_cursor_up(&self, _: usize) -> io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_down(&self, _: usize) -> io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_right(&self, _: usize) -> io::Result<()> {
+            Ok(())
+        }
+        fn move_cursor_left(&self, _: usize) -> io::Result<()> {
+            Ok(())
+        }
+        fn write_line(&self, s: &str) -> io::Result<()> {
+            self.write_str(&format!("{}\n", s))
+        }
+        fn write_str(&self, s: &str) -> io::Result<()> {
+            self.0.lock().unwrap().push(s.to_owned());
+            Ok(())
+        }
+        fn clear_line(&self) -> io::Result<()> {
+            Ok(())
+        }
+        fn flush(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Fails every write, like a closed pipe
+    #[derive(Debug)]
+    pub(crate) struct BrokenPipe;
+
+    impl TermLike for BrokenPipe {
+        fn width(&self) -> usize {
+            80
+        }
+        fn move_cursor_up(&self, _: usize) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn move_cursor_down(&self, _: usize) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn move_cursor_right(&self, _: usize) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn move_cursor_left(&self, _: usize) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn write_line(&self, _: &str) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn write_str(&self, _: &str) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn clear_line(&self) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+        fn flush(&self) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+    }
+}
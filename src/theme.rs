@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::env;
+
+/// The background color of a terminal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Background {
+    /// A dark background, with light text
+    Dark,
+    /// A light background, with dark text
+    Light,
+}
+
+impl Background {
+    /// Guesses the background color of the terminal from the environment
+    ///
+    /// This looks at the `COLORFGBG` variable, set by terminals like rxvt and Konsole. Returns
+    /// `None` if the background can't be determined.
+    pub fn detect() -> Option<Background> {
+        parse_colorfgbg(&env::var("COLORFGBG").ok()?)
+    }
+}
+
+/// Parses `COLORFGBG`, formatted as `fg;bg` or `fg;default;bg`, with ANSI color indices
+fn parse_colorfgbg(value: &str) -> Option<Background> {
+    match value.rsplit(';').next()?.parse::<u8>().ok()? {
+        7 | 9..=15 => Some(Background::Light),
+        0..=6 | 8 => Some(Background::Dark),
+        _ => None,
+    }
+}
+
+/// Adapts the colors of a [`ProgressStyle`] to the background of the terminal
+///
+/// A theme replaces the colors used in the template of a style by colors that are readable on
+/// the background, e.g. `white` by `black` on light backgrounds. Styles set with
+/// [`ProgressStyle::key_style()`] are used as is.
+///
+/// ```rust
+/// # use indicatif::{ProgressStyle, Theme};
+/// let style = ProgressStyle::default_bar()
+///     .template("{spinner:.yellow} {bar:40.cyan/blue} {msg:.white}")
+///     .theme(Theme::detect());
+/// ```
+///
+/// [`ProgressStyle`]: crate::ProgressStyle
+/// [`ProgressStyle::key_style()`]: crate::ProgressStyle::key_style
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Theme {
+    colors: HashMap<String, String>,
+}
+
+impl Theme {
+    /// Creates a theme for `background`
+    pub fn new(background: Background) -> Theme {
+        let colors: &[(&str, &str)] = match background {
+            Background::Dark => &[("black", "white")],
+            Background::Light => &[
+                ("white", "black"),
+                ("yellow", "130"),
+                ("cyan", "30"),
+                ("green", "28"),
+                ("bright", "bold"),
+            ],
+        };
+        let mut theme = Theme::default();
+        for (from, to) in colors {
+            theme = theme.color(from, to);
+        }
+        theme
+    }
+
+    /// Creates a theme for the detected background, see [`Background::detect()`]
+    ///
+    /// Falls back to a dark background if the background can't be detected.
+    pub fn detect() -> Theme {
+        Theme::new(Background::detect().unwrap_or(Background::Dark))
+    }
+
+    /// Replaces the style component `from` by `to`
+    ///
+    /// Components are the parts of a dotted style string, as in `{msg:.yellow.bold}`. `to` may
+    /// be a dotted style string itself, or empty to remove the component.
+    pub fn color(mut self, from: &str, to: &str) -> Theme {
+        self.colors.insert(from.to_owned(), to.to_owned());
+        self
+    }
+
+    /// Applies the theme to a dotted style string
+    pub(crate) fn apply(&self, style: &str) -> String {
+        style
+            .split('.')
+            .map(|part| self.colors.get(part).map_or(part, |to| to.as_str()))
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ProgressState;
+    use crate::ProgressStyle;
+
+    #[test]
+    fn light_theme() {
+        assert_eq!(parse_colorfgbg("0;15"), Some(Background::Light));
+        assert_eq!(parse_colorfgbg("15;default;0"), Some(Background::Dark));
+        assert_eq!(parse_colorfgbg("garbage"), None);
+
+        let theme = Theme::new(Background::Light);
+        assert_eq!(theme.apply("white.on_blue"), "black.on_blue");
+        assert_eq!(theme.apply("yellow.bright"), "130.bold");
+
+        let state = ProgressState::new(10);
+        let mut themed = Vec::new();
+        ProgressStyle::default_bar()
+            .template("{pos:.white}")
+            .theme(theme)
+            .format_state(&state, &mut themed, 80, Some(true));
+        let mut expected = Vec::new();
+        ProgressStyle::default_bar()
+            .template("{pos:.black}")
+            .format_state(&state, &mut expected, 80, Some(true));
+        assert_eq!(themed, expected);
+    }
+}
@@ -0,0 +1,274 @@
+use std::mem;
+use std::sync::{Condvar, Mutex, MutexGuard, RwLock, Weak};
+use std::thread;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use crate::multi::MultiProgressState;
+use crate::state::BarState;
+use crate::time::Instant;
+use crate::WeakProgressBar;
+
+/// Returns the process-wide ticker shared by all progress bars with steady ticks enabled.
+pub(crate) fn ticker() -> &'static Ticker {
+    static TICKER: Lazy<Ticker> = Lazy::new(Ticker::default);
+    &TICKER
+}
+
+/// Returns the instant all frame boundaries are measured from.
+pub(crate) fn frame_epoch() -> Instant {
+    static EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
+    *EPOCH
+}
+
+/// Returns the first frame boundary after `now` for frames of length `interval`.
+///
+/// Frames are aligned to [`frame_epoch()`], so that ticks and draws with the same interval land
+/// on the same boundaries, no matter when they were scheduled.
+pub(crate) fn next_frame(now: Instant, interval: Duration) -> Instant {
+    let epoch = frame_epoch();
+    let interval_nanos = interval.as_nanos().max(1);
+    let frames = now.saturating_duration_since(epoch).as_nanos() / interval_nanos + 1;
+    epoch + Duration::from_nanos((frames * interval_nanos) as u64)
+}
+
+/// Ticks progress bars from a single background thread.
+///
+/// The thread is spawned when the first progress bar is registered and exits once no progress
+/// bars are left to tick. It also draws progress bars and multi progresses once at a later time,
+/// see [`Ticker::schedule_draw()`].
+#[derive(Default)]
+pub(crate) struct Ticker {
+    state: Mutex<TickerState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct TickerState {
+    entries: Vec<Entry>,
+    /// Progress bars and multi progresses to draw once, and when
+    draws: Vec<(Scheduled, Instant)>,
+    running: bool,
+}
+
+impl TickerState {
+    fn next(&self) -> Option<Instant> {
+        let ticks = self.entries.iter().map(|e| e.next);
+        ticks.chain(self.draws.iter().map(|&(_, at)| at)).min()
+    }
+}
+
+/// Something drawn once by the ticker
+pub(crate) enum Scheduled {
+    Bar(WeakProgressBar),
+    Multi(Weak<RwLock<MultiProgressState>>),
+}
+
+impl Scheduled {
+    fn is(&self, other: &Scheduled) -> bool {
+        match (self, other) {
+            (Scheduled::Bar(a), Scheduled::Bar(b)) => a.ptr_eq(b),
+            (Scheduled::Multi(a), Scheduled::Multi(b)) => a.ptr_eq(b),
+            _ => false,
+        }
+    }
+
+    /// Draws the progress bar or multi progress if it still exists
+    fn draw(&self) {
+        match self {
+            Scheduled::Bar(bar) => {
+                if let Some(bar) = bar.upgrade() {
+                    bar.redraw(Instant::now());
+                }
+            }
+            Scheduled::Multi(multi) => {
+                let multi = match multi.upgrade() {
+                    Some(multi) => multi,
+                    None => return,
+                };
+
+                let mut state = multi.write().unwrap();
+                let _ = state.draw(true, Instant::now());
+                let events = state.events.clone();
+                drop(state);
+                events.dispatch();
+            }
+        }
+    }
+}
+
+struct Entry {
+    // Using a weak pointer is required to prevent a potential deadlock. See issue #133
+    bar: Weak<Mutex<BarState>>,
+    interval: Duration,
+    next: Instant,
+}
+
+impl Ticker {
+    /// Ticks `bar` every `interval`, replacing the interval if it's already registered.
+    pub(crate) fn register(&'static self, bar: Weak<Mutex<BarState>>, interval: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let next = next_frame(Instant::now(), interval);
+        match state.entries.iter_mut().find(|e| e.bar.ptr_eq(&bar)) {
+            Some(entry) => {
+                entry.interval = interval;
+                entry.next = next;
+            }
+            None => state.entries.push(Entry {
+                bar,
+                interval,
+                next,
+            }),
+        }
+
+        self.wake(state);
+    }
+
+    /// Draws `target` once at `at`, or earlier if it's already scheduled to be drawn earlier
+    ///
+    /// The draw is forced, so that it isn't lost to rate limiting. This does nothing on `wasm32`
+    /// targets, which have no threads.
+    pub(crate) fn schedule_draw(&'static self, target: Scheduled, at: Instant) {
+        if cfg!(target_arch = "wasm32") {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        match state.draws.iter_mut().find(|(t, _)| t.is(&target)) {
+            Some((_, scheduled)) if *scheduled <= at => return,
+            Some((_, scheduled)) => *scheduled = at,
+            None => state.draws.push((target, at)),
+        }
+        self.wake(state);
+    }
+
+    /// Starts the thread if it isn't running, or lets it know that the schedule changed
+    fn wake(&'static self, mut state: MutexGuard<'_, TickerState>) {
+        if !state.running {
+            state.running = true;
+            thread::spawn(move || self.run());
+        }
+        self.changed.notify_one();
+    }
+
+    /// Stops ticking `bar`.
+    pub(crate) fn unregister(&self, bar: &Weak<Mutex<BarState>>) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.retain(|e| !e.bar.ptr_eq(bar));
+        self.changed.notify_one();
+    }
+
+    fn run(&self) {
+        loop {
+            let mut state = self.state.lock().unwrap();
+            let now = loop {
+                let next = match state.next() {
+                    Some(next) => next,
+                    None => {
+                        state.running = false;
+                        return;
+                    }
+                };
+
+                let now = Instant::now();
+                if next <= now {
+                    break now;
+                }
+                state = self.changed.wait_timeout(state, next - now).unwrap().0;
+            };
+
+            let mut due = Vec::new();
+            for entry in state.entries.iter_mut().filter(|e| e.next <= now) {
+                entry.next = next_frame(now, entry.interval);
+                due.push(entry.bar.clone());
+            }
+            let (draws, pending) = mem::take(&mut state.draws)
+                .into_iter()
+                .partition::<Vec<_>, _>(|&(_, at)| at <= now);
+            state.draws = pending;
+
+            // Bars are locked before the ticker elsewhere, so the ticker must be unlocked here
+            drop(state);
+            for (target, _) in draws {
+                target.draw();
+            }
+            let done = due
+                .into_iter()
+                .filter(|bar| !Self::tick(bar))
+                .collect::<Vec<_>>();
+            if !done.is_empty() {
+                let mut state = self.state.lock().unwrap();
+                state
+                    .entries
+                    .retain(|e| !done.iter().any(|bar| e.bar.ptr_eq(bar)));
+            }
+        }
+    }
+
+    /// Ticks `bar`, returns `false` if it no longer needs to be ticked.
+    fn tick(bar: &Weak<Mutex<BarState>>) -> bool {
+        let bar = match bar.upgrade() {
+            Some(bar) => bar,
+            None => return false,
+        };
+
+        let mut state = bar.lock().unwrap();
+        if state.state.is_finished() || state.state.steady_tick == 0 {
+            state.state.steady_tick = 0;
+            return false;
+        }
+
+        if state.state.tick != 0 {
+            state.state.tick = state.state.tick.saturating_add(1);
+        }
+        state.draw(false, Instant::now()).ok();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProgressBar;
+
+    #[test]
+    fn frame_boundaries() {
+        let epoch = frame_epoch();
+        let interval = Duration::from_millis(100);
+        assert_eq!(next_frame(epoch, interval), epoch + interval);
+        let now = epoch + Duration::from_millis(250);
+        assert_eq!(
+            next_frame(now, interval),
+            epoch + Duration::from_millis(300)
+        );
+        let now = epoch + Duration::from_millis(300);
+        assert_eq!(
+            next_frame(now, interval),
+            epoch + Duration::from_millis(400)
+        );
+    }
+
+    #[test]
+    fn shared_ticker() {
+        let bars = (0..2).map(|_| ProgressBar::hidden()).collect::<Vec<_>>();
+        for pb in &bars {
+            pb.enable_steady_tick(5);
+        }
+        thread::sleep(Duration::from_millis(50));
+        for pb in &bars {
+            assert!(pb.state().state.tick > 1);
+        }
+
+        bars[0].disable_steady_tick();
+        bars[1].finish();
+        let ticks = bars
+            .iter()
+            .map(|pb| pb.state().state.tick)
+            .collect::<Vec<_>>();
+        thread::sleep(Duration::from_millis(50));
+        for (pb, tick) in bars.iter().zip(ticks) {
+            assert_eq!(pb.state().state.tick, tick);
+        }
+    }
+}
@@ -0,0 +1,6 @@
+// `std::time` panics on `wasm32-unknown-unknown`, the `wasm` feature uses the clocks of the JS
+// runtime there instead. Elsewhere `web-time` re-exports `std::time`.
+#[cfg(not(feature = "wasm"))]
+pub(crate) use std::time::{Instant, SystemTime, UNIX_EPOCH};
+#[cfg(feature = "wasm")]
+pub(crate) use web_time::{Instant, SystemTime, UNIX_EPOCH};
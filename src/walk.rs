@@ -0,0 +1,224 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::multi::MultiProgress;
+use crate::progress_bar::ProgressBar;
+use crate::style::ProgressStyle;
+
+type FileCallback = Box<dyn FnMut(&Path, u64) + Send>;
+
+/// Tracks a walk over files with a bar counting the files and a bar counting their bytes
+///
+/// Wrap the iterator yielding the files and their sizes with [`WalkProgress::wrap()`]. A file
+/// counts as processed once the next one is yielded, its bytes are added to the bytes bar then,
+/// unless they were already counted by reading the file through [`WalkProgress::wrap_read()`].
+/// Both bars are finished once the iterator is exhausted.
+///
+/// ```rust,no_run
+/// # use std::fs::File;
+/// # use indicatif::{MultiProgress, WalkProgress};
+/// # fn files() -> Vec<(std::path::PathBuf, u64)> { vec![] }
+/// let walk = WalkProgress::new(&MultiProgress::new(), 120, 3_500_000)
+///     .on_file(|path, size| eprintln!("copying {} ({} bytes)", path.display(), size));
+/// for (path, _) in walk.wrap(files()) {
+///     let mut source = walk.wrap_read(File::open(&path)?);
+///     std::io::copy(&mut source, &mut std::io::sink())?;
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct WalkProgress {
+    files: ProgressBar,
+    bytes: ProgressBar,
+    /// Bytes of the current file that haven't been counted yet
+    remaining: Arc<AtomicU64>,
+    on_file: Option<Arc<Mutex<FileCallback>>>,
+}
+
+impl WalkProgress {
+    /// Adds a files bar and a bytes bar with default styles to `multi`
+    pub fn new(multi: &MultiProgress, total_files: u64, total_bytes: u64) -> WalkProgress {
+        let files = ProgressBar::new(total_files).with_style(
+            ProgressStyle::default_bar().template("{spinner} {pos}/{len} files {wide_msg}"),
+        );
+        let bytes = ProgressBar::new(total_bytes).with_style(
+            ProgressStyle::default_bar()
+                .template("{wide_bar} {bytes}/{total_bytes} {bytes_per_sec} {eta}"),
+        );
+        WalkProgress::from_bars(multi.add(files), multi.add(bytes))
+    }
+
+    /// Tracks the walk with the given bars, e.g. with custom styles
+    pub fn from_bars(files: ProgressBar, bytes: ProgressBar) -> WalkProgress {
+        WalkProgress {
+            files,
+            bytes,
+            remaining: Arc::default(),
+            on_file: None,
+        }
+    }
+
+    /// Calls `f` with the path and size of every file, when it is yielded
+    pub fn on_file(mut self, f: impl FnMut(&Path, u64) + Send + 'static) -> WalkProgress {
+        self.on_file = Some(Arc::new(Mutex::new(Box::new(f))));
+        self
+    }
+
+    /// Returns the bar counting the files
+    pub fn files(&self) -> &ProgressBar {
+        &self.files
+    }
+
+    /// Returns the bar counting the bytes
+    pub fn bytes(&self) -> &ProgressBar {
+        &self.bytes
+    }
+
+    /// Wraps an iterator yielding files with their sizes
+    ///
+    /// The files bar shows the path of the current file as its message.
+    pub fn wrap<I, P>(&self, iter: I) -> WalkIter<I::IntoIter>
+    where
+        I: IntoIterator<Item = (P, u64)>,
+        P: AsRef<Path>,
+    {
+        WalkIter {
+            it: iter.into_iter(),
+            walk: self.clone(),
+        }
+    }
+
+    /// Wraps a reader of the current file, counting the bytes read as they are read
+    pub fn wrap_read<R: io::Read>(&self, read: R) -> WalkReader<R> {
+        WalkReader {
+            it: read,
+            walk: self.clone(),
+        }
+    }
+
+    /// Counts `n` bytes of the current file
+    fn count(&self, n: u64) {
+        // A single atomic update, so concurrent readers never count the same bytes twice
+        let remaining = self
+            .remaining
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |remaining| {
+                Some(remaining - n.min(remaining))
+            })
+            .unwrap_or_else(|remaining| remaining);
+        self.bytes.inc(n.min(remaining));
+    }
+
+    /// Counts the rest of the current file, and starts `next`, if any
+    fn advance(&self, next: Option<(&Path, u64)>) {
+        self.bytes.inc(self.remaining.swap(0, Ordering::Relaxed));
+        match next {
+            Some((path, size)) => {
+                self.remaining.store(size, Ordering::Relaxed);
+                if let Some(on_file) = &self.on_file {
+                    (on_file.lock().unwrap())(path, size);
+                }
+                self.files.set_message(path.display().to_string());
+                self.files.inc(1);
+            }
+            None => {
+                self.files.finish_using_style();
+                self.bytes.finish_using_style();
+            }
+        }
+    }
+}
+
+impl fmt::Debug for WalkProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalkProgress")
+            .field("files", &self.files)
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+/// Iterator created by [`WalkProgress::wrap()`]
+#[derive(Debug)]
+pub struct WalkIter<I> {
+    it: I,
+    walk: WalkProgress,
+}
+
+impl<I, P> Iterator for WalkIter<I>
+where
+    I: Iterator<Item = (P, u64)>,
+    P: AsRef<Path>,
+{
+    type Item = (P, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.it.next();
+        self.walk
+            .advance(item.as_ref().map(|(path, size)| (path.as_ref(), *size)));
+        item
+    }
+}
+
+/// Reader created by [`WalkProgress::wrap_read()`]
+#[derive(Debug)]
+pub struct WalkReader<R> {
+    it: R,
+    walk: WalkProgress,
+}
+
+impl<R: io::Read> io::Read for WalkReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.it.read(buf)?;
+        self.walk.count(n as u64);
+        Ok(n)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        let n = self.it.read_vectored(bufs)?;
+        self.walk.count(n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::ProgressDrawTarget;
+
+    #[test]
+    fn walk_counts_files_and_bytes() {
+        let bar = |len| ProgressBar::with_draw_target(len, ProgressDrawTarget::hidden());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let walk = WalkProgress::from_bars(bar(2), bar(10)).on_file({
+            let seen = seen.clone();
+            move |path, size| seen.lock().unwrap().push((path.to_owned(), size))
+        });
+
+        let mut iter = walk.wrap(vec![("a", 4), ("b", 6)]);
+        iter.next();
+        assert_eq!((walk.files().position(), walk.bytes().position()), (1, 0));
+        walk.wrap_read(&b"abcdefgh"[..])
+            .read_exact(&mut [0; 3])
+            .unwrap();
+        assert_eq!(walk.bytes().position(), 3);
+
+        iter.next();
+        assert_eq!((walk.files().position(), walk.bytes().position()), (2, 4));
+        assert_eq!(walk.files().message(), "b");
+        assert!(iter.next().is_none());
+        assert_eq!(walk.bytes().position(), 10);
+        assert!(walk.files().is_finished() && walk.bytes().is_finished());
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (Path::new("a").to_owned(), 4),
+                (Path::new("b").to_owned(), 6)
+            ]
+        );
+    }
+}